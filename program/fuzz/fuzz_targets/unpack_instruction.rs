@@ -0,0 +1,16 @@
+//! Fuzz target: instruction decoding must never panic, and anything that
+//! decodes must re-encode to the exact input bytes.
+
+#![no_main]
+
+use e9th_token_program::instruction::{E9thInstruction, E9thTokenInstruction};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(instruction) = E9thInstruction::unpack(data) {
+        assert_eq!(instruction.pack(), data);
+    }
+    if let Ok(instruction) = E9thTokenInstruction::unpack(data) {
+        assert_eq!(instruction.pack(), data);
+    }
+});
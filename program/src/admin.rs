@@ -2,13 +2,18 @@
 
 use crate::{
     error::E9thTokenError,
-    state::{deserialize_account_data, serialize_account_data, ProgramState},
+    events::{Burned, ConfigChanged, E9thEvent},
+    processor::{borrow_token_config_mut, load_program_state, program_state_rules, record_burn},
+    stake::StakeProcessor,
+    state::{serialize_account_data, BurnKind, ProgramState},
+    validation::{check_system_program, check_token_program, AccountRules},
 };
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
     msg,
-    program::invoke,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
     pubkey::Pubkey,
     rent::Rent,
     system_instruction,
@@ -38,13 +43,12 @@ impl AdminProcessor {
         let token_program = next_account_info(account_info_iter)?;
 
         // Validate accounts
-        if !program_state_account.is_writable {
-            return Err(E9thTokenError::InvalidAccountData.into());
-        }
-
-        if !admin_account.is_signer {
-            return Err(E9thTokenError::Unauthorized.into());
-        }
+        let (program_state_pda, bump) = StakeProcessor::get_program_state_pda(program_id)?;
+        AccountRules::new().writable().address(program_state_pda).check(program_state_account)?;
+        AccountRules::new().signer().writable().check(admin_account)?;
+        AccountRules::new().owned_by(&spl_token::id()).check(mint_account)?;
+        check_system_program(system_program)?;
+        check_token_program(token_program)?;
 
         // Check if program state account is already initialized
         if program_state_account.data_is_empty() {
@@ -53,7 +57,7 @@ impl AdminProcessor {
             let space = ProgramState::LEN;
             let lamports = rent.minimum_balance(space);
 
-            invoke(
+            invoke_signed(
                 &system_instruction::create_account(
                     admin_account.key,
                     program_state_account.key,
@@ -66,6 +70,7 @@ impl AdminProcessor {
                     program_state_account.clone(),
                     system_program.clone(),
                 ],
+                &[&[b"program_state", &[bump]]],
             )?;
 
             // Initialize program state
@@ -78,7 +83,9 @@ impl AdminProcessor {
                 min_stake_period,
                 max_stake_period,
                 total_staked: 0,
-                bump: 0, // This would be calculated from the PDA derivation
+                bump,
+                legacy_disabled: false,
+                migrated: false,
             };
 
             serialize_account_data(program_state_account, &program_state)?;
@@ -103,15 +110,14 @@ impl AdminProcessor {
         let destination_account = next_account_info(account_info_iter)?;
         let token_program = next_account_info(account_info_iter)?;
 
-        // Validate admin
-        let program_state: ProgramState = deserialize_account_data(program_state_account)?;
+        // Validate accounts
+        program_state_rules(program_id).writable().check(program_state_account)?;
+        let mut program_state = load_program_state(program_id, program_state_account)?;
+        AccountRules::new().signer().check(admin_account)?;
         if program_state.admin != *admin_account.key {
             return Err(E9thTokenError::InvalidAdmin.into());
         }
-
-        if !admin_account.is_signer {
-            return Err(E9thTokenError::Unauthorized.into());
-        }
+        Self::check_mint_accounts(&program_state, mint_account, destination_account, token_program)?;
 
         // Validate amount
         if amount == 0 {
@@ -139,7 +145,6 @@ impl AdminProcessor {
         )?;
 
         // Update program state
-        let mut program_state: ProgramState = deserialize_account_data(program_state_account)?;
         program_state.total_supply = program_state.total_supply.saturating_add(amount);
         serialize_account_data(program_state_account, &program_state)?;
 
@@ -159,15 +164,27 @@ impl AdminProcessor {
         let mint_account = next_account_info(account_info_iter)?;
         let source_account = next_account_info(account_info_iter)?;
         let token_program = next_account_info(account_info_iter)?;
+        let config_account = account_info_iter.next();
 
-        // Validate admin
-        let program_state: ProgramState = deserialize_account_data(program_state_account)?;
+        // Validate accounts
+        program_state_rules(program_id).writable().check(program_state_account)?;
+        let mut program_state = load_program_state(program_id, program_state_account)?;
+        AccountRules::new().signer().check(admin_account)?;
         if program_state.admin != *admin_account.key {
             return Err(E9thTokenError::InvalidAdmin.into());
         }
-
-        if !admin_account.is_signer {
-            return Err(E9thTokenError::Unauthorized.into());
+        Self::check_mint_accounts(&program_state, mint_account, source_account, token_program)?;
+        // Once migrated, every burn must reach the config's burn stats
+        if program_state.migrated && config_account.is_none() {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        let mut config = config_account
+            .map(|account| borrow_token_config_mut(program_id, account))
+            .transpose()?;
+        if let Some(config) = &config {
+            if config.mint != program_state.mint {
+                return Err(E9thTokenError::InvalidMint.into());
+            }
         }
 
         // Validate amount
@@ -196,11 +213,19 @@ impl AdminProcessor {
         )?;
 
         // Update program state
-        let mut program_state: ProgramState = deserialize_account_data(program_state_account)?;
         program_state.total_supply = program_state.total_supply.saturating_sub(amount);
         serialize_account_data(program_state_account, &program_state)?;
+        if let Some(config) = config.as_deref_mut() {
+            config.total_supply = u64::from(config.total_supply).saturating_sub(amount).into();
+            record_burn(config, BurnKind::Manual, amount)?;
+        }
 
-        msg!("Burned {} tokens", amount);
+        E9thEvent::Burned(Burned {
+            mint: *mint_account.key,
+            authority: *admin_account.key,
+            amount,
+        })
+        .emit();
         Ok(())
     }
 
@@ -218,15 +243,13 @@ impl AdminProcessor {
         let admin_account = next_account_info(account_info_iter)?;
 
         // Validate admin
-        let mut program_state: ProgramState = deserialize_account_data(program_state_account)?;
+        program_state_rules(program_id).writable().check(program_state_account)?;
+        let mut program_state = load_program_state(program_id, program_state_account)?;
+        AccountRules::new().signer().check(admin_account)?;
         if program_state.admin != *admin_account.key {
             return Err(E9thTokenError::InvalidAdmin.into());
         }
 
-        if !admin_account.is_signer {
-            return Err(E9thTokenError::Unauthorized.into());
-        }
-
         // Update settings
         if let Some(rate) = reward_rate {
             program_state.reward_rate = rate;
@@ -254,7 +277,8 @@ impl AdminProcessor {
 
         serialize_account_data(program_state_account, &program_state)?;
 
-        msg!("Settings updated successfully");
+        E9thEvent::ConfigChanged(ConfigChanged::from_program_state(*admin_account.key, &program_state))
+            .emit();
         Ok(())
     }
 
@@ -269,20 +293,34 @@ impl AdminProcessor {
         let current_admin_account = next_account_info(account_info_iter)?;
 
         // Validate current admin
-        let mut program_state: ProgramState = deserialize_account_data(program_state_account)?;
+        program_state_rules(program_id).writable().check(program_state_account)?;
+        let mut program_state = load_program_state(program_id, program_state_account)?;
+        AccountRules::new().signer().check(current_admin_account)?;
         if program_state.admin != *current_admin_account.key {
             return Err(E9thTokenError::InvalidAdmin.into());
         }
 
-        if !current_admin_account.is_signer {
-            return Err(E9thTokenError::Unauthorized.into());
-        }
-
         // Transfer admin
         program_state.admin = new_admin;
         serialize_account_data(program_state_account, &program_state)?;
 
-        msg!("Admin transferred to: {}", new_admin);
+        E9thEvent::ConfigChanged(ConfigChanged::from_program_state(
+            *current_admin_account.key,
+            &program_state,
+        ))
+        .emit();
         Ok(())
     }
+
+    /// The mint must be the program's and the token account must hold it
+    fn check_mint_accounts(
+        program_state: &ProgramState,
+        mint_account: &AccountInfo,
+        token_account: &AccountInfo,
+        token_program: &AccountInfo,
+    ) -> ProgramResult {
+        AccountRules::new().writable().mint(&program_state.mint).check(mint_account)?;
+        AccountRules::new().writable().token_account(&program_state.mint).check(token_account)?;
+        check_token_program(token_program)
+    }
 }
@@ -0,0 +1,446 @@
+//! Merkle airdrop functionality for the E9th Token Program
+
+use crate::{
+    error::E9thTokenError,
+    events::{AirdropSent, E9thEvent},
+    merkle,
+    processor::{load_token_config, Processor},
+    state::{
+        deserialize_account_data, serialize_account_data, AirdropGate, AirdropRound, AirdropRoundParams,
+        TokenConfig,
+    },
+    validation::{check_system_program, check_token_program, AccountRules},
+    zero_copy::{load_account, StakeEntryPod},
+};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    ed25519_program,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::{self, instructions::get_instruction_relative, Sysvar},
+};
+use spl_token::{instruction::transfer, state::Account as TokenAccount};
+
+pub struct AirdropProcessor;
+
+impl AirdropProcessor {
+    /// Create a merkle airdrop round and fund its vault
+    pub fn process_create_round(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        params: AirdropRoundParams,
+    ) -> ProgramResult {
+        let AirdropRoundParams { round_id, total_amount, num_recipients, expiry_timestamp, .. } = params;
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let round_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let funding_account = next_account_info(account_info_iter)?;
+        let authority_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // The round size fixes the account size, so bound it before anything else
+        if num_recipients > AirdropRound::MAX_RECIPIENTS {
+            return Err(E9thTokenError::TooManyRecipients.into());
+        }
+
+        let config = load_token_config(program_id, config_account)?;
+        Self::validate_authority(&config, authority_account)?;
+        check_system_program(system_program)?;
+        check_token_program(token_program)?;
+
+        if total_amount == 0 || num_recipients == 0 {
+            return Err(E9thTokenError::InvalidAmount.into());
+        }
+        // Round 0 stands for batch `Airdrop`s in `AirdropSent` events
+        if round_id == 0 {
+            return Err(E9thTokenError::InvalidInstruction.into());
+        }
+
+        let now = Clock::get()?.unix_timestamp as u64;
+        if expiry_timestamp <= now {
+            return Err(E9thTokenError::AirdropExpired.into());
+        }
+
+        let (round_pda, bump) = Self::get_round_pda(program_id, config_account.key, round_id);
+        AccountRules::new().writable().address(round_pda).check(round_account)?;
+        if !round_account.data_is_empty() {
+            return Err(E9thTokenError::AccountAlreadyInitialized.into());
+        }
+
+        // The vault must be a token account for the E9TH mint controlled by the round PDA
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(&round_pda)
+            .check(vault_account)?;
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(authority_account.key)
+            .check(funding_account)?;
+
+        // Create the round account
+        let space = AirdropRound::space(num_recipients);
+        let lamports = Rent::get()?.minimum_balance(space);
+        invoke_signed(
+            &system_instruction::create_account(
+                authority_account.key,
+                round_account.key,
+                lamports,
+                space as u64,
+                program_id,
+            ),
+            &[
+                authority_account.clone(),
+                round_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"airdrop",
+                config_account.key.as_ref(),
+                &round_id.to_le_bytes(),
+                &[bump],
+            ]],
+        )?;
+
+        // Fund the vault
+        invoke(
+            &transfer(
+                token_program.key,
+                funding_account.key,
+                vault_account.key,
+                authority_account.key,
+                &[],
+                total_amount,
+            )?,
+            &[
+                funding_account.clone(),
+                vault_account.clone(),
+                authority_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        let round = AirdropRound::new(params, *vault_account.key, bump);
+        serialize_account_data(round_account, &round)?;
+
+        msg!(
+            "Airdrop round {} created: {} tokens for {} recipients",
+            round_id,
+            total_amount,
+            num_recipients
+        );
+        Ok(())
+    }
+
+    /// Claim an allocation from a merkle airdrop round
+    pub fn process_claim(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        round_id: u64,
+        index: u64,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let round_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let claimant_account = next_account_info(account_info_iter)?;
+        let claimant_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        AccountRules::new().signer().check(claimant_account)?;
+        check_token_program(token_program)?;
+
+        let (config, mut round) = Self::load_round(program_id, config_account, round_account, round_id)?;
+        AccountRules::new().writable().address(round.vault).check(vault_account)?;
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(claimant_account.key)
+            .check(claimant_token_account)?;
+
+        let now = Clock::get()?.unix_timestamp as u64;
+        if round.clawed_back || round.is_expired(now) {
+            return Err(E9thTokenError::AirdropExpired.into());
+        }
+
+        if index >= round.num_recipients {
+            return Err(E9thTokenError::InvalidMerkleProof.into());
+        }
+        if round.is_claimed(index) {
+            return Err(E9thTokenError::AirdropAlreadyClaimed.into());
+        }
+
+        let leaf = merkle::leaf_hash(index, claimant_account.key, amount);
+        if !merkle::verify_proof(&proof, &round.merkle_root, leaf) {
+            return Err(E9thTokenError::InvalidMerkleProof.into());
+        }
+
+        Self::check_gate(
+            program_id,
+            config_account,
+            round_account,
+            claimant_account,
+            account_info_iter,
+            &round.gate,
+        )?;
+
+        let total_claimed = round
+            .total_claimed
+            .checked_add(amount)
+            .ok_or(E9thTokenError::MathOverflow)?;
+        if total_claimed > round.total_amount {
+            return Err(E9thTokenError::AirdropExhausted.into());
+        }
+
+        Self::transfer_from_vault(
+            config_account,
+            round_account,
+            vault_account,
+            claimant_token_account,
+            token_program,
+            &round,
+            amount,
+        )?;
+
+        round.set_claimed(index);
+        round.total_claimed = total_claimed;
+        serialize_account_data(round_account, &round)?;
+
+        E9thEvent::AirdropSent(AirdropSent {
+            round_id,
+            recipient: *claimant_account.key,
+            amount,
+        })
+        .emit();
+        Ok(())
+    }
+
+    /// Return unclaimed tokens of an expired round to the treasury
+    pub fn process_clawback(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        round_id: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let round_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let treasury_token_account = next_account_info(account_info_iter)?;
+        let authority_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        let (config, mut round) = Self::load_round(program_id, config_account, round_account, round_id)?;
+        AccountRules::new().writable().address(round.vault).check(vault_account)?;
+        Self::validate_authority(&config, authority_account)?;
+        check_token_program(token_program)?;
+
+        let now = Clock::get()?.unix_timestamp as u64;
+        if !round.is_expired(now) {
+            return Err(E9thTokenError::AirdropNotExpired.into());
+        }
+        if round.clawed_back {
+            return Err(E9thTokenError::AirdropAlreadyClawedBack.into());
+        }
+
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(&config.treasury)
+            .check(treasury_token_account)?;
+
+        let remaining = TokenAccount::unpack(&vault_account.try_borrow_data()?)?.amount;
+        if remaining > 0 {
+            Self::transfer_from_vault(
+                config_account,
+                round_account,
+                vault_account,
+                treasury_token_account,
+                token_program,
+                &round,
+                remaining,
+            )?;
+        }
+
+        round.clawed_back = true;
+        serialize_account_data(round_account, &round)?;
+
+        msg!("Clawed back {} tokens from airdrop round {}", remaining, round_id);
+        Ok(())
+    }
+
+    /// Get airdrop round PDA
+    pub fn get_round_pda(program_id: &Pubkey, config: &Pubkey, round_id: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[b"airdrop", config.as_ref(), &round_id.to_le_bytes()],
+            program_id,
+        )
+    }
+
+    /// Load the config and the round, checking the round is the config's `round_id` PDA
+    fn load_round(
+        program_id: &Pubkey,
+        config_account: &AccountInfo,
+        round_account: &AccountInfo,
+        round_id: u64,
+    ) -> Result<(TokenConfig, AirdropRound), ProgramError> {
+        let config = load_token_config(program_id, config_account)?;
+        AccountRules::new().writable().owned_by(program_id).check(round_account)?;
+        let round: AirdropRound = deserialize_account_data(round_account)?;
+        let expected = Pubkey::create_program_address(
+            &[
+                b"airdrop",
+                config_account.key.as_ref(),
+                &round_id.to_le_bytes(),
+                &[round.bump],
+            ],
+            program_id,
+        )?;
+        if expected != *round_account.key || round.round_id != round_id {
+            return Err(E9thTokenError::UnexpectedAccount.into());
+        }
+        Ok((config, round))
+    }
+
+    fn transfer_from_vault<'a>(
+        config_account: &AccountInfo<'a>,
+        round_account: &AccountInfo<'a>,
+        vault_account: &AccountInfo<'a>,
+        destination_account: &AccountInfo<'a>,
+        token_program: &AccountInfo<'a>,
+        round: &AirdropRound,
+        amount: u64,
+    ) -> ProgramResult {
+        invoke_signed(
+            &transfer(
+                token_program.key,
+                vault_account.key,
+                destination_account.key,
+                round_account.key,
+                &[],
+                amount,
+            )?,
+            &[
+                vault_account.clone(),
+                destination_account.clone(),
+                round_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[
+                b"airdrop",
+                config_account.key.as_ref(),
+                &round.round_id.to_le_bytes(),
+                &[round.bump],
+            ]],
+        )
+    }
+
+    /// Message a verifier signs to attest that `claimant` may claim from `round`
+    pub fn attestation_message(round: &Pubkey, claimant: &Pubkey) -> Vec<u8> {
+        [b"e9th-airdrop".as_ref(), round.as_ref(), claimant.as_ref()].concat()
+    }
+
+    /// Enforce the round's anti-sybil requirements. Optional accounts follow the
+    /// fixed claim accounts: the stake entry when a minimum stake or holding
+    /// period is required, then the instructions sysvar when an attestation is
+    /// required.
+    fn check_gate(
+        program_id: &Pubkey,
+        config_account: &AccountInfo,
+        round_account: &AccountInfo,
+        claimant_account: &AccountInfo,
+        account_info_iter: &mut std::slice::Iter<AccountInfo>,
+        gate: &AirdropGate,
+    ) -> ProgramResult {
+        // Balances and holding periods are read from the stake entry: tokens in
+        // the vault can't be borrowed for the length of one claim
+        if gate.requires_stake() {
+            let stake_account = next_account_info(account_info_iter)?;
+            let (stake_pda, _) =
+                Processor::get_stake_entry_pda(program_id, config_account.key, claimant_account.key);
+            AccountRules::new()
+                .owned_by(program_id)
+                .address(stake_pda)
+                .check(stake_account)?;
+            let stake = load_account::<StakeEntryPod>(stake_account)?;
+            if stake.staker != *claimant_account.key || u64::from(stake.amount) == 0 {
+                return Err(E9thTokenError::InvalidStakeAccount.into());
+            }
+            if u64::from(stake.amount) < gate.min_balance {
+                return Err(E9thTokenError::InsufficientHolding.into());
+            }
+            let current_epoch = Clock::get()?.epoch;
+            if current_epoch.saturating_sub(stake.start_epoch.into()) < gate.min_holding_epochs {
+                return Err(E9thTokenError::HoldingPeriodTooShort.into());
+            }
+        }
+
+        if gate.requires_attestation() {
+            let instructions_sysvar = next_account_info(account_info_iter)?;
+            AccountRules::new()
+                .address(sysvar::instructions::id())
+                .check(instructions_sysvar)?;
+            let message = Self::attestation_message(round_account.key, claimant_account.key);
+            Self::verify_attestation(instructions_sysvar, &gate.verifier, &message)?;
+        }
+
+        Ok(())
+    }
+
+    /// Check that the instruction immediately before this one is an ed25519
+    /// signature verification of `message` by `verifier`
+    fn verify_attestation(
+        instructions_sysvar: &AccountInfo,
+        verifier: &Pubkey,
+        message: &[u8],
+    ) -> ProgramResult {
+        let ix = get_instruction_relative(-1, instructions_sysvar)
+            .map_err(|_| E9thTokenError::InvalidAttestation)?;
+        if ix.program_id != ed25519_program::id() {
+            return Err(E9thTokenError::InvalidAttestation.into());
+        }
+
+        // Ed25519SignatureOffsets layout: count, padding, then seven u16 fields
+        let data = &ix.data;
+        if data.len() < 16 || data[0] != 1 {
+            return Err(E9thTokenError::InvalidAttestation.into());
+        }
+        let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+        let signature_ix = read_u16(4);
+        let pubkey_offset = read_u16(6) as usize;
+        let pubkey_ix = read_u16(8);
+        let message_offset = read_u16(10) as usize;
+        let message_len = read_u16(12) as usize;
+        let message_ix = read_u16(14);
+
+        // All data must live in the ed25519 instruction itself
+        if signature_ix != u16::MAX || pubkey_ix != u16::MAX || message_ix != u16::MAX {
+            return Err(E9thTokenError::InvalidAttestation.into());
+        }
+        let signed_pubkey = data.get(pubkey_offset..pubkey_offset + 32);
+        let signed_message = data.get(message_offset..message_offset + message_len);
+        if signed_pubkey != Some(verifier.as_ref()) || signed_message != Some(message) {
+            return Err(E9thTokenError::InvalidAttestation.into());
+        }
+        Ok(())
+    }
+
+    fn validate_authority(config: &TokenConfig, authority: &AccountInfo) -> ProgramResult {
+        AccountRules::new().signer().check(authority)?;
+        if *authority.key != config.operator && *authority.key != config.owner {
+            return Err(E9thTokenError::Unauthorized.into());
+        }
+        Ok(())
+    }
+}
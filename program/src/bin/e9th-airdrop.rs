@@ -0,0 +1,99 @@
+//! Offline merkle tree builder for airdrop rounds
+//!
+//! Usage: `e9th-airdrop <recipients.csv> <output.json>`
+//!
+//! The CSV has one `wallet,amount` pair per line (an optional `wallet,amount`
+//! header is skipped). Duplicate wallets are merged by summing their amounts.
+//! The output holds the merkle root plus each recipient's leaf index and proof,
+//! hashed exactly as `ClaimAirdrop` verifies them on chain.
+
+use e9th_token_program::{
+    merkle::{self, MerkleTree, RecipientList},
+    state::AirdropRound,
+};
+use serde::Serialize;
+use std::{env, fs, process};
+
+#[derive(Serialize)]
+struct AirdropOutput {
+    merkle_root: String,
+    total_amount: u64,
+    num_recipients: u64,
+    claims: Vec<ClaimOutput>,
+}
+
+#[derive(Serialize)]
+struct ClaimOutput {
+    index: u64,
+    wallet: String,
+    amount: u64,
+    proof: Vec<String>,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!("Usage: {} <recipients.csv> <output.json>", args[0]);
+        process::exit(2);
+    }
+
+    if let Err(err) = run(&args[1], &args[2]) {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run(input_path: &str, output_path: &str) -> Result<(), String> {
+    let csv = fs::read_to_string(input_path)
+        .map_err(|e| format!("failed to read {}: {}", input_path, e))?;
+    let RecipientList { recipients, merged } = merkle::parse_recipients(&csv)?;
+    for (line, wallet) in merged {
+        eprintln!("Warning: line {}: merging duplicate wallet {}", line, wallet);
+    }
+    if recipients.is_empty() {
+        return Err("no recipients found".to_string());
+    }
+    if recipients.len() as u64 > AirdropRound::MAX_RECIPIENTS {
+        return Err(format!("too many recipients for a single round (max {})", AirdropRound::MAX_RECIPIENTS));
+    }
+
+    let leaves = recipients
+        .iter()
+        .enumerate()
+        .map(|(index, (wallet, amount))| merkle::leaf_hash(index as u64, wallet, *amount))
+        .collect();
+    let tree = MerkleTree::new(leaves);
+
+    let mut total_amount: u64 = 0;
+    let mut claims = Vec::with_capacity(recipients.len());
+    for (index, (wallet, amount)) in recipients.iter().enumerate() {
+        total_amount = total_amount
+            .checked_add(*amount)
+            .ok_or("total amount overflows u64")?;
+        claims.push(ClaimOutput {
+            index: index as u64,
+            wallet: wallet.to_string(),
+            amount: *amount,
+            proof: tree.proof(index).iter().map(|node| to_hex(node)).collect(),
+        });
+    }
+
+    let output = AirdropOutput {
+        merkle_root: to_hex(&tree.root()),
+        total_amount,
+        num_recipients: recipients.len() as u64,
+        claims,
+    };
+    let json = serde_json::to_string_pretty(&output).map_err(|e| e.to_string())?;
+    fs::write(output_path, json).map_err(|e| format!("failed to write {}: {}", output_path, e))?;
+
+    println!(
+        "Wrote {} claims totalling {} to {} (root {})",
+        output.num_recipients, output.total_amount, output_path, output.merkle_root
+    );
+    Ok(())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,29 @@
+//! IDL generator
+//!
+//! Usage: `e9th-idl [output.json]`
+//!
+//! Writes the program IDL to the given path, or to stdout when no path is given.
+//! The checked-in `idl/e9th_token_program.json` must match this output; the
+//! integration tests fail when it goes stale.
+
+use e9th_token_program::idl;
+use std::{env, fs, process};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let json = idl::generate();
+
+    match args.len() {
+        1 => print!("{}", json),
+        2 => {
+            if let Err(err) = fs::write(&args[1], json) {
+                eprintln!("Error: failed to write {}: {}", args[1], err);
+                process::exit(1);
+            }
+        }
+        _ => {
+            eprintln!("Usage: {} [output.json]", args[0]);
+            process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Offline event indexer
+//!
+//! Usage: `e9th-indexer <program-id> <logs>...`
+//!
+//! Each input is either RPC `getTransaction` JSON (`.json`: one result, an array
+//! of them, or a JSON-RPC response) or saved `solana logs` output. The program's
+//! events are indexed in slot order and the derived tables are printed as JSON.
+
+use e9th_token_program::indexer::{self, Indexer};
+use serde::Serialize;
+use solana_program::pubkey::Pubkey;
+use std::{env, fs, process, str::FromStr};
+
+#[derive(Serialize)]
+struct IndexerOutput {
+    event_count: u64,
+    failed_transactions: u64,
+    cumulative_burned: u64,
+    burned_history: Vec<BurnedPoint>,
+    total_staked_history: Vec<StakedPoint>,
+    rewards_claimed: Vec<RewardsClaimed>,
+}
+
+#[derive(Serialize)]
+struct BurnedPoint {
+    slot: u64,
+    cumulative_burned: u64,
+}
+
+#[derive(Serialize)]
+struct StakedPoint {
+    slot: u64,
+    total_staked: u64,
+}
+
+#[derive(Serialize)]
+struct RewardsClaimed {
+    wallet: String,
+    amount: u64,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("Usage: {} <program-id> <logs>...", args[0]);
+        process::exit(2);
+    }
+
+    if let Err(err) = run(&args[1], &args[2..]) {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run(program_id: &str, input_paths: &[String]) -> Result<(), String> {
+    let program_id =
+        Pubkey::from_str(program_id).map_err(|_| format!("invalid program id `{}`", program_id))?;
+
+    let mut transactions = Vec::new();
+    for path in input_paths {
+        let input =
+            fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+        let parsed = if path.ends_with(".json") {
+            indexer::parse_transactions_json(&input)
+        } else {
+            indexer::parse_validator_output(&input)
+        };
+        transactions.extend(parsed.map_err(|e| format!("{}: {}", path, e))?);
+    }
+
+    let mut indexer = Indexer::new();
+    indexer.ingest_all(&program_id, &mut transactions)?;
+
+    let output = IndexerOutput {
+        event_count: indexer.event_count,
+        failed_transactions: indexer.failed_transactions,
+        cumulative_burned: indexer.cumulative_burned,
+        burned_history: indexer
+            .burned_history
+            .iter()
+            .map(|&(slot, cumulative_burned)| BurnedPoint { slot, cumulative_burned })
+            .collect(),
+        total_staked_history: indexer
+            .total_staked_history
+            .iter()
+            .map(|&(slot, total_staked)| StakedPoint { slot, total_staked })
+            .collect(),
+        rewards_claimed: indexer
+            .rewards_claimed
+            .iter()
+            .map(|(wallet, amount)| RewardsClaimed { wallet: wallet.to_string(), amount: *amount })
+            .collect(),
+    };
+    let json = serde_json::to_string_pretty(&output).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
@@ -0,0 +1,112 @@
+//! Holder snapshot builder
+//!
+//! Usage: `e9th-snapshot <program-id> <mint> <accounts.json>... [--slot <slot>]`
+//!
+//! Each input is a base64 `getProgramAccounts` dump: token accounts of the mint
+//! (from the token program) and the program's own accounts. The slot comes from
+//! the dumps' `context` (take them `withContext`) or `--slot`, and all of them
+//! must agree. Prints each wallet's balance and stake, sorted by wallet, with
+//! the snapshot hash.
+
+use e9th_token_program::snapshot::{self, Snapshot};
+use serde::Serialize;
+use solana_program::pubkey::Pubkey;
+use std::{env, fs, process, str::FromStr};
+
+#[derive(Serialize)]
+struct SnapshotOutput {
+    mint: String,
+    slot: u64,
+    hash: String,
+    holder_count: u64,
+    total_balance: String,
+    total_staked: String,
+    holders: Vec<HolderOutput>,
+}
+
+#[derive(Serialize)]
+struct HolderOutput {
+    wallet: String,
+    balance: u64,
+    staked: u64,
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let slot = match args.iter().position(|arg| arg == "--slot") {
+        Some(position) if position + 1 < args.len() => {
+            let slot = args.remove(position + 1);
+            args.remove(position);
+            match slot.parse::<u64>() {
+                Ok(slot) => Some(slot),
+                Err(_) => {
+                    eprintln!("Error: invalid slot `{}`", slot);
+                    process::exit(2);
+                }
+            }
+        }
+        Some(_) => {
+            eprintln!("Error: --slot needs a value");
+            process::exit(2);
+        }
+        None => None,
+    };
+    if args.len() < 4 {
+        eprintln!("Usage: {} <program-id> <mint> <accounts.json>... [--slot <slot>]", args[0]);
+        process::exit(2);
+    }
+
+    if let Err(err) = run(&args[1], &args[2], &args[3..], slot) {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run(program_id: &str, mint: &str, input_paths: &[String], mut slot: Option<u64>) -> Result<(), String> {
+    let program_id =
+        Pubkey::from_str(program_id).map_err(|_| format!("invalid program id `{}`", program_id))?;
+    let mint = Pubkey::from_str(mint).map_err(|_| format!("invalid mint `{}`", mint))?;
+
+    let mut accounts = Vec::new();
+    for path in input_paths {
+        let input =
+            fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+        let (dumped, dump_slot) =
+            snapshot::parse_program_accounts_json(&input).map_err(|e| format!("{}: {}", path, e))?;
+        match (slot, dump_slot) {
+            (Some(slot), Some(dump_slot)) if slot != dump_slot => {
+                return Err(format!("{} was taken at slot {}, not {}", path, dump_slot, slot));
+            }
+            (None, dump_slot) => slot = dump_slot,
+            _ => {}
+        }
+        accounts.extend(dumped);
+    }
+    let slot = slot.ok_or("no slot: dump accounts `withContext` or pass --slot")?;
+
+    let snapshot = Snapshot::build(&program_id, &mint, slot, &accounts)?;
+    let output = SnapshotOutput {
+        mint: mint.to_string(),
+        slot,
+        hash: to_hex(&snapshot.hash()),
+        holder_count: snapshot.holders.len() as u64,
+        total_balance: snapshot.total_balance().to_string(),
+        total_staked: snapshot.total_staked().to_string(),
+        holders: snapshot
+            .holders
+            .iter()
+            .map(|holder| HolderOutput {
+                wallet: holder.wallet.to_string(),
+                balance: holder.balance,
+                staked: holder.staked,
+            })
+            .collect(),
+    };
+    let json = serde_json::to_string_pretty(&output).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -1,8 +1,9 @@
 //! Error types for the E9th Token Program
 
+use num_derive::FromPrimitive;
 use thiserror::Error;
 
-#[derive(Error, Debug, Copy, Clone)]
+#[derive(Error, Debug, Copy, Clone, FromPrimitive)]
 pub enum E9thTokenError {
     #[error("Invalid instruction")]
     InvalidInstruction,
@@ -60,6 +61,138 @@ pub enum E9thTokenError {
 
     #[error("Reward calculation failed")]
     RewardCalculationFailed,
+
+    #[error("Invalid merkle proof")]
+    InvalidMerkleProof,
+
+    #[error("Airdrop already claimed")]
+    AirdropAlreadyClaimed,
+
+    #[error("Airdrop round expired")]
+    AirdropExpired,
+
+    #[error("Airdrop round not expired")]
+    AirdropNotExpired,
+
+    #[error("Airdrop round exhausted")]
+    AirdropExhausted,
+
+    #[error("Airdrop round was already clawed back")]
+    AirdropAlreadyClawedBack,
+
+    #[error("Airdrop rounds are limited to 65536 recipients")]
+    TooManyRecipients,
+
+    #[error("Insufficient holdings for airdrop claim")]
+    InsufficientHolding,
+
+    #[error("Holding period too short for airdrop claim")]
+    HoldingPeriodTooShort,
+
+    #[error("Missing or invalid verifier attestation")]
+    InvalidAttestation,
+
+    #[error("Sale not active")]
+    SaleNotActive,
+
+    #[error("Sale not ended")]
+    SaleNotEnded,
+
+    #[error("Wallet not whitelisted for sale")]
+    NotWhitelisted,
+
+    #[error("Purchase below minimum")]
+    PurchaseBelowMinimum,
+
+    #[error("Purchase exceeds wallet maximum")]
+    PurchaseAboveMaximum,
+
+    #[error("Sale hard cap exceeded")]
+    HardCapExceeded,
+
+    #[error("Refunds are only available after a sale fails its soft cap")]
+    RefundNotAvailable,
+
+    #[error("Account holds a different account type")]
+    AccountTypeMismatch,
+
+    #[error("Account layout version is not supported; migrate the account first")]
+    UnsupportedAccountVersion,
+
+    #[error("Legacy instructions are disabled")]
+    LegacyInstructionsDisabled,
+
+    #[error("Account must be writable")]
+    AccountNotWritable,
+
+    #[error("Account is not the expected address or PDA")]
+    UnexpectedAccount,
+
+    #[error("Serialized state does not fit in the account")]
+    AccountDataTooSmall,
+
+    #[error("Account has stale bytes after its serialized state")]
+    StaleAccountData,
+
+    #[error("Account is blacklisted")]
+    AccountBlacklisted,
+
+    #[error("Legacy staking is retired; stake with the enhanced instructions and migrate legacy stakes")]
+    LegacyStakingRetired,
+
+    #[error("Token is paused")]
+    TokenPaused,
+
+    #[error("Tier thresholds must be non-zero and strictly increasing")]
+    InvalidTierThresholds,
+
+    #[error("Merchant is not active")]
+    MerchantInactive,
+
+    #[error("Voucher already redeemed")]
+    VoucherAlreadyRedeemed,
+
+    #[error("Voucher has expired")]
+    VoucherExpired,
+
+    #[error("Voucher was reclaimed by its holder")]
+    VoucherReclaimed,
+
+    #[error("Only locked vouchers can be reclaimed, once expired or their merchant is inactive")]
+    VoucherNotReclaimable,
+
+    #[error("Payment terms can't exceed 100% (10000 basis points)")]
+    InvalidPaymentTerms,
+
+    #[error("Order reference must be 1 to 64 bytes")]
+    InvalidOrderReference,
+
+    #[error("Subscription amount, period and number of periods must be non-zero")]
+    InvalidSubscriptionTerms,
+
+    #[error("Subscription is cancelled")]
+    SubscriptionCancelled,
+
+    #[error("Subscription has collected all its periods")]
+    SubscriptionComplete,
+
+    #[error("Subscription period has not elapsed")]
+    SubscriptionPeriodNotElapsed,
+
+    #[error("Launch capacity must be 1 to 200 and its opening in the future")]
+    InvalidLaunchTerms,
+
+    #[error("Launch is open to the public; reservations are closed")]
+    LaunchAlreadyOpen,
+
+    #[error("Wallet's utility tier is below the launch minimum")]
+    TierTooLow,
+
+    #[error("Wallet already holds a launch slot")]
+    AlreadyReserved,
+
+    #[error("Launch is full")]
+    LaunchFull,
 }
 
 impl From<E9thTokenError> for solana_program::program_error::ProgramError {
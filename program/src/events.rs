@@ -0,0 +1,270 @@
+//! Structured events for the E9th Token Program
+//!
+//! Handlers log events with `sol_log_data`, so each one shows up as a
+//! `Program data: <base64>` line. The payload is [EVENT_DISCRIMINATOR], the
+//! [EVENT_VERSION] byte and the Borsh-encoded [E9thEvent], whose variant tag
+//! identifies the event.
+
+use crate::state::{ProgramState, SubscriptionTerms, UtilityTier, VoucherMode};
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+use solana_program::{log::sol_log_data, pubkey::Pubkey};
+use std::io::{Error, ErrorKind};
+
+/// Marks a `Program data` payload as an E9TH event
+pub const EVENT_DISCRIMINATOR: [u8; 4] = *b"E9TH";
+
+/// Layout version of the event payload. Bump it when an event's fields change.
+pub const EVENT_VERSION: u8 = 1;
+
+/// Bytes before the Borsh-encoded event
+pub const EVENT_HEADER_LEN: usize = EVENT_DISCRIMINATOR.len() + 1;
+
+/// Every event the program emits. Variant order is part of the event format,
+/// so new events are appended.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub enum E9thEvent {
+    Staked(Staked),
+    Unstaked(Unstaked),
+    RewardsClaimed(RewardsClaimed),
+    Burned(Burned),
+    Transferred(Transferred),
+    BlacklistChanged(BlacklistChanged),
+    Paused(Paused),
+    ConfigChanged(ConfigChanged),
+    AirdropSent(AirdropSent),
+    MembershipChanged(MembershipChanged),
+    VoucherIssued(VoucherIssued),
+    VoucherRedeemed(VoucherRedeemed),
+    VoucherReclaimed(VoucherReclaimed),
+    PaymentReceived(PaymentReceived),
+    SubscriptionCreated(SubscriptionCreated),
+    SubscriptionCollected(SubscriptionCollected),
+    SubscriptionCancelled(SubscriptionCancelled),
+    LaunchSlotReserved(LaunchSlotReserved),
+}
+
+/// Tokens staked
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct Staked {
+    pub staker: Pubkey,
+    pub amount: u64,
+    /// Stake period (in epochs)
+    pub period: u64,
+    /// Program-wide total staked after this stake
+    pub total_staked: u64,
+}
+
+/// Tokens unstaked
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct Unstaked {
+    pub staker: Pubkey,
+    pub amount: u64,
+    /// Program-wide total staked after this unstake
+    pub total_staked: u64,
+}
+
+/// Staking rewards claimed
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct RewardsClaimed {
+    pub staker: Pubkey,
+    pub amount: u64,
+}
+
+/// Tokens burned
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct Burned {
+    pub mint: Pubkey,
+    /// Signer that authorized the burn
+    pub authority: Pubkey,
+    pub amount: u64,
+}
+
+/// Tokens transferred, net of the transfer burn
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct Transferred {
+    pub from: Pubkey,
+    pub to: Pubkey,
+    /// Amount received by `to`
+    pub amount: u64,
+    /// Amount burned on the way
+    pub burned: u64,
+}
+
+/// Account added to or removed from the blacklist
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct BlacklistChanged {
+    pub account: Pubkey,
+    pub blacklisted: bool,
+}
+
+/// Transfers paused or unpaused
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct Paused {
+    pub paused: bool,
+}
+
+/// Program settings or authorities changed, with the settings after the change
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct ConfigChanged {
+    /// Signer that made the change
+    pub authority: Pubkey,
+    /// Admin (legacy program state) or owner (token config)
+    pub admin: Pubkey,
+    pub staking_enabled: bool,
+    pub reward_rate: u16,
+    pub min_stake_period: u64,
+    pub max_stake_period: u64,
+    pub legacy_disabled: bool,
+}
+
+/// Airdrop tokens sent to a recipient
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct AirdropSent {
+    /// Merkle round the tokens were claimed from (0 = batch `Airdrop` from the treasury)
+    pub round_id: u64,
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+/// Membership pass issued, upgraded, downgraded or revoked (tier `None`)
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct MembershipChanged {
+    pub holder: Pubkey,
+    pub tier: UtilityTier,
+    /// Stake the tier was computed from
+    pub staked: u64,
+}
+
+/// Voucher minted by a holder
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct VoucherIssued {
+    pub merchant: Pubkey,
+    pub holder: Pubkey,
+    pub voucher_id: u64,
+    /// E9TH locked or burned for the voucher
+    pub amount: u64,
+    pub mode: VoucherMode,
+}
+
+/// Voucher redeemed by its merchant
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct VoucherRedeemed {
+    pub merchant: Pubkey,
+    pub holder: Pubkey,
+    pub voucher_id: u64,
+}
+
+/// Holder took back the E9TH locked for an expired voucher or one of an
+/// inactive merchant
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct VoucherReclaimed {
+    pub merchant: Pubkey,
+    pub holder: Pubkey,
+    pub voucher_id: u64,
+    pub amount: u64,
+}
+
+/// Holder paid a merchant with `Pay`: the receipt
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct PaymentReceived {
+    pub merchant: Pubkey,
+    pub payer: Pubkey,
+    /// List price before the tier discount
+    pub amount: u64,
+    pub tier: UtilityTier,
+    pub discount: u64,
+    pub burned: u64,
+    /// Transferred to the merchant
+    pub paid: u64,
+    /// Merchant's order reference, also logged as the transaction memo
+    pub order_reference: String,
+}
+
+/// Subscriber pre-approved a merchant's recurring pulls
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct SubscriptionCreated {
+    pub subscription: Pubkey,
+    pub merchant: Pubkey,
+    pub subscriber: Pubkey,
+    pub terms: SubscriptionTerms,
+}
+
+/// One subscription period pulled from the subscriber's vault
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct SubscriptionCollected {
+    pub subscription: Pubkey,
+    pub merchant: Pubkey,
+    pub subscriber: Pubkey,
+    pub amount: u64,
+    /// Periods collected so far, including this one
+    pub periods_collected: u64,
+}
+
+/// Subscriber cancelled a subscription
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct SubscriptionCancelled {
+    pub subscription: Pubkey,
+    pub subscriber: Pubkey,
+    pub periods_collected: u64,
+}
+
+/// Wallet reserved an early-access launch slot
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct LaunchSlotReserved {
+    pub launch: Pubkey,
+    pub wallet: Pubkey,
+    /// Stake the slot is ranked by (0 for first-come launches)
+    pub weight: u64,
+    /// Rank in the queue when reserved (0 is first)
+    pub position: u32,
+    /// Wallet that lost its slot to this one, if the launch was full
+    pub bumped: Option<Pubkey>,
+}
+
+impl ConfigChanged {
+    /// Snapshot the legacy program state settings
+    pub fn from_program_state(authority: Pubkey, state: &ProgramState) -> Self {
+        Self {
+            authority,
+            admin: state.admin,
+            staking_enabled: state.staking_enabled,
+            reward_rate: state.reward_rate,
+            min_stake_period: state.min_stake_period,
+            max_stake_period: state.max_stake_period,
+            legacy_disabled: state.legacy_disabled,
+        }
+    }
+}
+
+impl E9thEvent {
+    /// Log the event as program data
+    pub fn emit(&self) {
+        sol_log_data(&[&self.to_bytes()]);
+    }
+
+    /// Encode the event payload
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(EVENT_HEADER_LEN + 64);
+        data.extend_from_slice(&EVENT_DISCRIMINATOR);
+        data.push(EVENT_VERSION);
+        // Writing into a Vec can't fail
+        self.serialize(&mut data).unwrap();
+        data
+    }
+
+    /// Decode a `Program data` payload, or `None` if it isn't an E9TH event
+    pub fn decode(data: &[u8]) -> Result<Option<Self>, Error> {
+        let body = match data.strip_prefix(&EVENT_DISCRIMINATOR[..]) {
+            Some(body) => body,
+            None => return Ok(None),
+        };
+        match body.split_first() {
+            Some((&EVENT_VERSION, event)) => Self::try_from_slice(event).map(Some),
+            Some((version, _)) => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported event version {}", version),
+            )),
+            None => Err(Error::new(ErrorKind::UnexpectedEof, "missing event version")),
+        }
+    }
+}
@@ -0,0 +1,503 @@
+//! IDL generation for off-chain clients
+//!
+//! Instruction names, tags and args come from the instruction enums' Borsh
+//! schemas, account metas from the `Accounts:` list documented on each variant
+//! (captured at compile time as `VARIANT_DOCS`) and account layouts from the
+//! state types' Borsh schemas, so the IDL can't drift from the Rust
+//! definitions. The checked-in copy
+//! lives at `idl/e9th_token_program.json` and is regenerated with
+//! `cargo run --features cli --bin e9th-idl -- idl/e9th_token_program.json`.
+
+use crate::{
+    error::E9thTokenError,
+    events::{
+        AirdropSent, BlacklistChanged, Burned, ConfigChanged, LaunchSlotReserved, MembershipChanged, Paused,
+        PaymentReceived, RewardsClaimed, Staked, SubscriptionCancelled, SubscriptionCollected,
+        SubscriptionCreated, Transferred, Unstaked, VoucherIssued, VoucherReclaimed, VoucherRedeemed,
+        EVENT_DISCRIMINATOR, EVENT_VERSION,
+    },
+    instruction::{E9thInstruction, E9thTokenInstruction, ENHANCED_NAMESPACE, LEGACY_NAMESPACE},
+    state::{
+        AccountState, AirdropRound, Blacklist, Launch, MembershipPass, Merchant, ProgramState, Sale, SalePurchase,
+        StakeAccount, StakeEntry, Subscription, TierConfig, TokenConfig, Voucher,
+    },
+};
+use borsh::schema::{BorshSchema, BorshSchemaContainer, Definition, Fields};
+use num_traits::FromPrimitive;
+use std::fmt::Write;
+
+/// Minimal JSON value with deterministic (insertion-ordered) objects
+enum Json {
+    Bool(bool),
+    Number(u64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    fn string(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+
+    fn write(&self, out: &mut String, indent: usize) {
+        match self {
+            Json::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+            Json::Number(value) => out.push_str(&value.to_string()),
+            Json::String(value) => {
+                out.push('"');
+                for c in value.chars() {
+                    match c {
+                        '"' => out.push_str("\\\""),
+                        '\\' => out.push_str("\\\\"),
+                        '\n' => out.push_str("\\n"),
+                        c if (c as u32) < 0x20 => {
+                            let _ = write!(out, "\\u{:04x}", c as u32);
+                        }
+                        c => out.push(c),
+                    }
+                }
+                out.push('"');
+            }
+            Json::Array(items) if items.is_empty() => out.push_str("[]"),
+            Json::Array(items) => {
+                out.push_str("[\n");
+                for (i, item) in items.iter().enumerate() {
+                    out.push_str(&"  ".repeat(indent + 1));
+                    item.write(out, indent + 1);
+                    out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&"  ".repeat(indent));
+                out.push(']');
+            }
+            Json::Object(fields) if fields.is_empty() => out.push_str("{}"),
+            Json::Object(fields) => {
+                out.push_str("{\n");
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    let _ = write!(out, "{}\"{}\": ", "  ".repeat(indent + 1), key);
+                    value.write(out, indent + 1);
+                    out.push_str(if i + 1 < fields.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&"  ".repeat(indent));
+                out.push('}');
+            }
+        }
+    }
+}
+
+/// An account meta parsed from an instruction's `Accounts:` doc list
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdlAccountMeta {
+    /// camelCase name derived from the description
+    pub name: String,
+    /// Description as documented
+    pub docs: String,
+    pub is_writable: bool,
+    pub is_signer: bool,
+    /// Only passed in some configurations (documented as "only if ...")
+    pub is_optional: bool,
+    /// Repeated for the remaining accounts (documented as "(one per ...)")
+    pub is_rest: bool,
+}
+
+/// Definition of one instruction variant
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdlInstructionDocs {
+    pub name: String,
+    /// Wire tag (declaration index within its enum)
+    pub tag: u8,
+    pub docs: String,
+    pub accounts: Vec<IdlAccountMeta>,
+    /// `(field name, Borsh declaration)` pairs in declaration (= Borsh) order
+    pub args: Vec<(String, String)>,
+}
+
+/// Documented variants of the enhanced instruction enum (excluding `Legacy*` wrappers)
+pub fn enhanced_instruction_docs() -> Vec<IdlInstructionDocs> {
+    instruction_docs(&E9thInstruction::schema_container(), E9thInstruction::VARIANT_DOCS)
+        .into_iter()
+        .filter(|docs| !docs.name.starts_with("Legacy"))
+        .collect()
+}
+
+/// Documented variants of the legacy instruction enum
+pub fn legacy_instruction_docs() -> Vec<IdlInstructionDocs> {
+    instruction_docs(&E9thTokenInstruction::schema_container(), E9thTokenInstruction::VARIANT_DOCS)
+}
+
+/// Generate the IDL as pretty-printed JSON
+pub fn generate() -> String {
+    let mut types = Vec::new();
+
+    let instructions = instructions_json(
+        ENHANCED_NAMESPACE,
+        &E9thInstruction::schema_container(),
+        &enhanced_instruction_docs(),
+        &mut types,
+    );
+    let legacy_instructions = instructions_json(
+        LEGACY_NAMESPACE,
+        &E9thTokenInstruction::schema_container(),
+        &legacy_instruction_docs(),
+        &mut types,
+    );
+
+    let accounts = vec![
+        account_json::<TokenConfig>(Some(TokenConfig::LEN), &mut types),
+        account_json::<Blacklist>(Some(Blacklist::LEN), &mut types),
+        account_json::<StakeEntry>(Some(StakeEntry::LEN), &mut types),
+        account_json::<AirdropRound>(None, &mut types),
+        account_json::<Sale>(Some(Sale::LEN), &mut types),
+        account_json::<SalePurchase>(Some(SalePurchase::LEN), &mut types),
+        account_json::<ProgramState>(Some(ProgramState::LEN), &mut types),
+        account_json::<StakeAccount>(Some(StakeAccount::LEN), &mut types),
+        account_json::<TierConfig>(Some(TierConfig::LEN), &mut types),
+        account_json::<MembershipPass>(Some(MembershipPass::LEN), &mut types),
+        account_json::<Merchant>(Some(Merchant::LEN), &mut types),
+        account_json::<Voucher>(Some(Voucher::LEN), &mut types),
+        account_json::<Subscription>(Some(Subscription::LEN), &mut types),
+        account_json::<Launch>(None, &mut types),
+    ];
+
+    let events = events_json(&mut types);
+
+    let errors = (0u32..)
+        .map_while(E9thTokenError::from_u32)
+        .enumerate()
+        .map(|(code, error)| {
+            Json::Object(vec![
+                ("code", Json::Number(code as u64)),
+                ("name", Json::String(format!("{:?}", error))),
+                ("msg", Json::String(error.to_string())),
+            ])
+        })
+        .collect();
+
+    let idl = Json::Object(vec![
+        ("version", Json::string(env!("CARGO_PKG_VERSION"))),
+        ("name", Json::string("e9th_token_program")),
+        ("instructions", Json::Array(instructions)),
+        ("legacyInstructions", Json::Array(legacy_instructions)),
+        ("accounts", Json::Array(accounts)),
+        ("events", Json::Array(events)),
+        ("types", Json::Array(types.into_iter().map(|(_, json)| json).collect())),
+        ("errors", Json::Array(errors)),
+    ]);
+
+    let mut out = String::new();
+    idl.write(&mut out, 0);
+    out.push('\n');
+    out
+}
+
+/// Instruction entries for the documented variants of one namespace
+fn instructions_json(
+    namespace: u8,
+    schema: &BorshSchemaContainer,
+    docs: &[IdlInstructionDocs],
+    types: &mut Vec<(String, Json)>,
+) -> Vec<Json> {
+    docs.iter()
+        .map(|doc| {
+            let accounts = doc
+                .accounts
+                .iter()
+                .map(|account| {
+                    let mut fields = vec![
+                        ("name", Json::string(&account.name)),
+                        ("isMut", Json::Bool(account.is_writable)),
+                        ("isSigner", Json::Bool(account.is_signer)),
+                    ];
+                    if account.is_optional {
+                        fields.push(("isOptional", Json::Bool(true)));
+                    }
+                    if account.is_rest {
+                        fields.push(("isRest", Json::Bool(true)));
+                    }
+                    fields.push(("docs", Json::Array(vec![Json::string(&account.docs)])));
+                    Json::Object(fields)
+                })
+                .collect();
+            let args = doc
+                .args
+                .iter()
+                .map(|(name, ty)| {
+                    Json::Object(vec![
+                        ("name", Json::string(&lower_camel(name))),
+                        ("type", type_json(schema, ty, types)),
+                    ])
+                })
+                .collect();
+
+            Json::Object(vec![
+                ("name", Json::string(&lower_camel(&doc.name))),
+                ("docs", Json::Array(vec![Json::string(&doc.docs)])),
+                (
+                    "discriminator",
+                    Json::Array(vec![Json::Number(namespace as u64), Json::Number(doc.tag as u64)]),
+                ),
+                ("accounts", Json::Array(accounts)),
+                ("args", Json::Array(args)),
+            ])
+        })
+        .collect()
+}
+
+/// Account entry for a state type
+fn account_json<T: BorshSchema + AccountState>(
+    size: Option<usize>,
+    types: &mut Vec<(String, Json)>,
+) -> Json {
+    let schema = T::schema_container();
+    let mut fields = vec![
+        ("name", Json::string(&schema.declaration)),
+        // Account header: type discriminator and layout version
+        (
+            "discriminator",
+            Json::Array(vec![
+                Json::Number(T::ACCOUNT_TYPE as u64),
+                Json::Number(T::VERSION as u64),
+            ]),
+        ),
+    ];
+    if let Some(size) = size {
+        fields.push(("size", Json::Number(size as u64)));
+    }
+    fields.push((
+        "type",
+        Json::Object(vec![
+            ("kind", Json::string("struct")),
+            ("fields", fields_json(&schema, &schema.declaration, types)),
+        ]),
+    ));
+    Json::Object(fields)
+}
+
+/// Event entries, in `E9thEvent` variant order so the index is the event tag
+fn events_json(types: &mut Vec<(String, Json)>) -> Vec<Json> {
+    let schemas = [
+        Staked::schema_container(),
+        Unstaked::schema_container(),
+        RewardsClaimed::schema_container(),
+        Burned::schema_container(),
+        Transferred::schema_container(),
+        BlacklistChanged::schema_container(),
+        Paused::schema_container(),
+        ConfigChanged::schema_container(),
+        AirdropSent::schema_container(),
+        MembershipChanged::schema_container(),
+        VoucherIssued::schema_container(),
+        VoucherRedeemed::schema_container(),
+        VoucherReclaimed::schema_container(),
+        PaymentReceived::schema_container(),
+        SubscriptionCreated::schema_container(),
+        SubscriptionCollected::schema_container(),
+        SubscriptionCancelled::schema_container(),
+        LaunchSlotReserved::schema_container(),
+    ];
+    schemas
+        .iter()
+        .enumerate()
+        .map(|(tag, schema)| {
+            let discriminator = EVENT_DISCRIMINATOR
+                .iter()
+                .chain(&[EVENT_VERSION, tag as u8])
+                .map(|byte| Json::Number(*byte as u64))
+                .collect();
+            Json::Object(vec![
+                ("name", Json::string(&schema.declaration)),
+                ("discriminator", Json::Array(discriminator)),
+                ("fields", fields_json(schema, &schema.declaration, types)),
+            ])
+        })
+        .collect()
+}
+
+/// Named fields of a struct declaration as `{ name, type }` entries
+fn fields_json(
+    schema: &BorshSchemaContainer,
+    declaration: &str,
+    types: &mut Vec<(String, Json)>,
+) -> Json {
+    match schema.definitions.get(declaration) {
+        Some(Definition::Struct { fields: Fields::NamedFields(fields) }) => Json::Array(
+            fields
+                .iter()
+                .map(|(name, field)| {
+                    Json::Object(vec![
+                        ("name", Json::string(&lower_camel(name))),
+                        ("type", type_json(schema, field, types)),
+                    ])
+                })
+                .collect(),
+        ),
+        _ => Json::Array(Vec::new()),
+    }
+}
+
+/// IDL type of a Borsh declaration, registering named structs in `types`
+fn type_json(schema: &BorshSchemaContainer, declaration: &str, types: &mut Vec<(String, Json)>) -> Json {
+    match declaration {
+        "Pubkey" => return Json::string("publicKey"),
+        "bool" | "u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64" | "i128"
+        | "string" => return Json::string(declaration),
+        _ => {}
+    }
+    assert!(
+        schema.definitions.contains_key(declaration),
+        "no schema for IDL type `{}`",
+        declaration
+    );
+
+    match schema.definitions.get(declaration) {
+        Some(Definition::Array { length, elements }) => Json::Object(vec![(
+            "array",
+            Json::Array(vec![type_json(schema, elements, types), Json::Number(*length as u64)]),
+        )]),
+        Some(Definition::Sequence { elements }) => {
+            Json::Object(vec![("vec", type_json(schema, elements, types))])
+        }
+        Some(Definition::Enum { variants }) if declaration.starts_with("Option<") => {
+            let inner = &variants[1].1;
+            Json::Object(vec![("option", type_json(schema, inner, types))])
+        }
+        _ => {
+            if !types.iter().any(|(name, _)| name == declaration) {
+                // Reserve the slot first so nested types keep first-use order
+                types.push((declaration.to_string(), Json::Array(Vec::new())));
+                let position = types.len() - 1;
+                let kind = match schema.definitions.get(declaration) {
+                    Some(Definition::Enum { variants }) => Json::Object(vec![
+                        ("kind", Json::string("enum")),
+                        (
+                            "variants",
+                            Json::Array(
+                                variants
+                                    .iter()
+                                    .map(|(name, _)| Json::Object(vec![("name", Json::string(name))]))
+                                    .collect(),
+                            ),
+                        ),
+                    ]),
+                    _ => Json::Object(vec![
+                        ("kind", Json::string("struct")),
+                        ("fields", fields_json(schema, declaration, types)),
+                    ]),
+                };
+                types[position].1 =
+                    Json::Object(vec![("name", Json::string(declaration)), ("type", kind)]);
+            }
+            Json::Object(vec![("defined", Json::string(declaration))])
+        }
+    }
+}
+
+/// One entry per variant of an instruction enum, in tag order. `variant_docs`
+/// holds each variant's doc comment lines.
+fn instruction_docs(schema: &BorshSchemaContainer, variant_docs: &[&[&str]]) -> Vec<IdlInstructionDocs> {
+    let Some(Definition::Enum { variants }) = schema.definitions.get(&schema.declaration) else {
+        panic!("`{}` is not an enum", schema.declaration);
+    };
+    assert_eq!(variants.len(), variant_docs.len(), "{} docs out of sync", schema.declaration);
+
+    variants
+        .iter()
+        .zip(variant_docs)
+        .enumerate()
+        .map(|(tag, ((name, declaration), doc_lines))| {
+            let doc_lines: Vec<&str> = doc_lines.iter().map(|line| line.trim()).collect();
+            let mut docs = parse_variant_docs(name.clone(), tag as u8, &doc_lines);
+            if let Some(Definition::Struct { fields: Fields::NamedFields(fields) }) =
+                schema.definitions.get(declaration)
+            {
+                docs.args = fields.clone();
+            }
+            docs
+        })
+        .collect()
+}
+
+fn parse_variant_docs(name: String, tag: u8, doc_lines: &[&str]) -> IdlInstructionDocs {
+    let split = doc_lines.iter().position(|line| *line == "Accounts:").unwrap_or(doc_lines.len());
+    let docs = doc_lines[..split].join(" ");
+    let accounts = doc_lines[split..]
+        .iter()
+        .skip(1)
+        .filter_map(|line| parse_account_line(line))
+        .collect();
+    IdlInstructionDocs { name, tag, docs, accounts, args: Vec::new() }
+}
+
+/// Parse a line such as `3. [signer, writable] Buyer's token account`
+fn parse_account_line(line: &str) -> Option<IdlAccountMeta> {
+    let (index, rest) = line.split_once(' ')?;
+    if !index.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let flags_end = rest.find(']')?;
+    let flags = rest.get(1..flags_end)?;
+    let docs = rest[flags_end + 1..].trim().to_string();
+
+    Some(IdlAccountMeta {
+        name: account_name(&docs),
+        is_writable: flags.contains("writable"),
+        is_signer: flags.contains("signer"),
+        is_optional: docs.contains("(only if"),
+        is_rest: docs.contains("(one per"),
+        docs,
+    })
+}
+
+/// camelCase identifier from an account description, ignoring parentheticals
+/// and anything after a comma
+fn account_name(docs: &str) -> String {
+    let mut words = Vec::new();
+    let mut depth = 0;
+    let mut word = String::new();
+    for c in docs.split(',').next().unwrap_or_default().chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if depth == 0 && c.is_alphanumeric() => word.push(c),
+            '\'' => {}
+            _ if depth == 0 && !word.is_empty() => words.push(std::mem::take(&mut word)),
+            _ => {}
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| {
+            let lower = word.to_lowercase();
+            if i == 0 {
+                lower
+            } else {
+                let mut chars = lower.chars();
+                chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+            }
+        })
+        .collect()
+}
+
+/// `snake_case` or `PascalCase` to `camelCase`
+fn lower_camel(name: &str) -> String {
+    let mut out = String::new();
+    let mut upper_next = false;
+    for (i, c) in name.chars().enumerate() {
+        if c == '_' {
+            upper_next = true;
+        } else if i == 0 {
+            out.extend(c.to_lowercase());
+        } else if upper_next {
+            out.extend(c.to_uppercase());
+            upper_next = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
@@ -0,0 +1,220 @@
+//! Off-chain event indexer
+//!
+//! Decodes the [E9thEvent] stream from transaction logs and keeps derived
+//! tables: cumulative burned, total staked over time and rewards claimed per
+//! wallet. Logs come from `solana logs` output ([parse_validator_output]) or,
+//! with the `cli` feature, RPC `getTransaction` JSON ([parse_transactions_json]),
+//! so recorded fixtures can be indexed fully offline.
+
+use crate::events::E9thEvent;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_program::pubkey::Pubkey;
+use std::{collections::BTreeMap, str::FromStr};
+
+/// Log messages of one transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionLogs {
+    pub slot: u64,
+    pub signature: String,
+    /// Failed transactions are rolled back, so their events are skipped
+    pub failed: bool,
+    pub logs: Vec<String>,
+}
+
+/// Decode the events `program_id` logged, ignoring data logged by other
+/// programs (including CPIs made by this one)
+pub fn decode_events(program_id: &Pubkey, logs: &[String]) -> Result<Vec<E9thEvent>, String> {
+    let mut stack: Vec<Pubkey> = Vec::new();
+    let mut events = Vec::new();
+
+    for log in logs {
+        if let Some(data) = log.strip_prefix("Program data: ") {
+            if stack.last() != Some(program_id) {
+                continue;
+            }
+            let mut payload = Vec::new();
+            for field in data.split_whitespace() {
+                let bytes = STANDARD
+                    .decode(field)
+                    .map_err(|e| format!("invalid program data `{}`: {}", field, e))?;
+                payload.extend_from_slice(&bytes);
+            }
+            if let Some(event) = E9thEvent::decode(&payload)
+                .map_err(|e| format!("invalid event `{}`: {}", data, e))?
+            {
+                events.push(event);
+            }
+        } else if let Some(rest) = log.strip_prefix("Program ") {
+            let mut words = rest.split_whitespace();
+            let (id, action) = match (words.next(), words.next()) {
+                (Some(id), Some(action)) => (id, action),
+                _ => continue,
+            };
+            match action {
+                "invoke" => stack.push(
+                    Pubkey::from_str(id).map_err(|_| format!("invalid program id `{}`", id))?,
+                ),
+                "success" | "failed:" => {
+                    stack.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(events)
+}
+
+/// Split `solana logs` output into transactions
+pub fn parse_validator_output(output: &str) -> Result<Vec<TransactionLogs>, String> {
+    let mut transactions: Vec<TransactionLogs> = Vec::new();
+    let mut in_logs = false;
+
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(slot) = line
+            .strip_prefix("Transaction executed in slot ")
+            .and_then(|rest| rest.strip_suffix(':'))
+        {
+            let slot = slot.parse().map_err(|_| format!("invalid slot in `{}`", line))?;
+            transactions.push(TransactionLogs {
+                slot,
+                signature: String::new(),
+                failed: false,
+                logs: Vec::new(),
+            });
+            in_logs = false;
+            continue;
+        }
+        let transaction = match transactions.last_mut() {
+            Some(transaction) => transaction,
+            None => continue,
+        };
+        if let Some(signature) = line.strip_prefix("Signature: ") {
+            transaction.signature = signature.to_string();
+        } else if let Some(status) = line.strip_prefix("Status: ") {
+            transaction.failed = status != "Ok";
+        } else if line == "Log Messages:" {
+            in_logs = true;
+        } else if in_logs && !line.is_empty() {
+            transaction.logs.push(line.to_string());
+        }
+    }
+
+    Ok(transactions)
+}
+
+/// Read RPC `getTransaction` results: one result, an array of them, or either
+/// wrapped in a JSON-RPC response
+#[cfg(feature = "cli")]
+pub fn parse_transactions_json(json: &str) -> Result<Vec<TransactionLogs>, String> {
+    use serde_json::Value;
+
+    fn transaction(value: &Value) -> Result<TransactionLogs, String> {
+        let slot = value["slot"].as_u64().ok_or("transaction is missing `slot`")?;
+        let meta = &value["meta"];
+        let logs = meta["logMessages"]
+            .as_array()
+            .ok_or("transaction is missing `meta.logMessages`")?
+            .iter()
+            .map(|log| log.as_str().map(str::to_string).ok_or("log message is not a string"))
+            .collect::<Result<_, _>>()?;
+        Ok(TransactionLogs {
+            slot,
+            signature: value["transaction"]["signatures"][0].as_str().unwrap_or_default().to_string(),
+            failed: !meta["err"].is_null(),
+            logs,
+        })
+    }
+
+    let value: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let value = value.get("result").unwrap_or(&value);
+    match value {
+        Value::Array(values) => values.iter().map(transaction).collect(),
+        value => Ok(vec![transaction(value)?]),
+    }
+}
+
+/// Tables derived from the event stream
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Indexer {
+    /// Total burned so far, by burns and transfer burns
+    pub cumulative_burned: u64,
+    /// `(slot, cumulative burned)` after each slot with a burn
+    pub burned_history: Vec<(u64, u64)>,
+    /// `(slot, total staked)` after each slot that staked or unstaked
+    pub total_staked_history: Vec<(u64, u64)>,
+    /// Rewards claimed per wallet
+    pub rewards_claimed: BTreeMap<Pubkey, u64>,
+    /// Events indexed
+    pub event_count: u64,
+    /// Transactions skipped because they failed
+    pub failed_transactions: u64,
+}
+
+impl Indexer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index transactions in slot order. Transactions within a slot keep their
+    /// input order.
+    pub fn ingest_all(
+        &mut self,
+        program_id: &Pubkey,
+        transactions: &mut [TransactionLogs],
+    ) -> Result<(), String> {
+        transactions.sort_by_key(|transaction| transaction.slot);
+        for transaction in transactions.iter() {
+            self.ingest(program_id, transaction)?;
+        }
+        Ok(())
+    }
+
+    /// Index one transaction
+    pub fn ingest(&mut self, program_id: &Pubkey, transaction: &TransactionLogs) -> Result<(), String> {
+        if transaction.failed {
+            self.failed_transactions += 1;
+            return Ok(());
+        }
+        let events = decode_events(program_id, &transaction.logs)
+            .map_err(|e| format!("slot {} {}: {}", transaction.slot, transaction.signature, e))?;
+        for event in &events {
+            self.apply(transaction.slot, event);
+        }
+        Ok(())
+    }
+
+    /// Update the tables with one event
+    pub fn apply(&mut self, slot: u64, event: &E9thEvent) {
+        self.event_count += 1;
+        match event {
+            E9thEvent::Burned(burned) => self.add_burned(slot, burned.amount),
+            E9thEvent::Transferred(transferred) if transferred.burned > 0 => {
+                self.add_burned(slot, transferred.burned)
+            }
+            E9thEvent::Staked(staked) => record(&mut self.total_staked_history, slot, staked.total_staked),
+            E9thEvent::Unstaked(unstaked) => {
+                record(&mut self.total_staked_history, slot, unstaked.total_staked)
+            }
+            E9thEvent::RewardsClaimed(claimed) => {
+                let total = self.rewards_claimed.entry(claimed.staker).or_default();
+                *total = total.saturating_add(claimed.amount);
+            }
+            _ => {}
+        }
+    }
+
+    fn add_burned(&mut self, slot: u64, amount: u64) {
+        self.cumulative_burned = self.cumulative_burned.saturating_add(amount);
+        record(&mut self.burned_history, slot, self.cumulative_burned);
+    }
+}
+
+/// Record `value` for `slot`, keeping one (the latest) point per slot
+fn record(history: &mut Vec<(u64, u64)>, slot: u64, value: u64) {
+    match history.last_mut() {
+        Some(last) if last.0 == slot => last.1 = value,
+        _ => history.push((slot, value)),
+    }
+}
@@ -1,282 +1,746 @@
 //! Instruction definitions for the E9th Token Program
 
+use crate::{
+    airdrop::AirdropProcessor,
+    launch::LaunchProcessor,
+    membership::MembershipProcessor,
+    merchant::MerchantProcessor,
+    merkle::MAX_PROOF_LEN,
+    processor::Processor,
+    sale::SaleProcessor,
+    stake::StakeProcessor,
+    state::{
+        AccountType, AirdropGate, LaunchTerms, PaymentTerms, SaleTerms, SubscriptionTerms, TierThresholds,
+        VoucherMode,
+    },
+    subscription::SubscriptionProcessor,
+    tier::TierProcessor,
+    voucher::VoucherProcessor,
+};
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
+    instruction::{AccountMeta, Instruction},
     program_error::ProgramError,
     pubkey::Pubkey,
+    system_program, sysvar,
 };
+use spl_associated_token_account::get_associated_token_address;
 
-/// Enhanced instructions for the E9th Token Program
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
-pub enum E9thInstruction {
-    /// Initialize the config account
-    Initialize {
-        owner: Pubkey,
-        operator: Pubkey,
-        treasury: Pubkey,
-        burn_rate_basis_points: u16,
-    },
-    /// Pause or unpause token transfers
-    SetPause {
-        pause: bool,
-    },
-    /// Add or remove from blacklist
-    ModifyBlacklist {
-        account: Pubkey,
-        add: bool,
-    },
-    /// Transfer tokens (with burn rate / deflation logic)
-    Transfer {
-        amount: u64,
-    },
-    /// Batch Airdrop: airdrop to multiple accounts
-    Airdrop {
-        recipients: Vec<Pubkey>,
-        amounts: Vec<u64>,
-    },
-    /// Stake some tokens
-    Stake {
-        amount: u64,
-    },
-    /// Unstake
-    Unstake {
-        amount: u64,
-    },
-    /// Claim staking rewards
-    ClaimRewards,
-    /// Legacy instructions for backward compatibility
-    LegacyInitialize {
-        total_supply: u64,
-        reward_rate: u16,
-        min_stake_period: u64,
-        max_stake_period: u64,
-    },
-    LegacyMint {
-        amount: u64,
-    },
-    LegacyBurn {
-        amount: u64,
-    },
-    LegacyStake {
-        amount: u64,
-        period: u64,
-    },
-    LegacyUnstake,
-    LegacyClaimRewards,
-    LegacyUpdateSettings {
-        reward_rate: Option<u16>,
-        min_stake_period: Option<u64>,
-        max_stake_period: Option<u64>,
-        staking_enabled: Option<bool>,
-    },
-    LegacyTransferAdmin {
-        new_admin: Pubkey,
-    },
+/// Namespace byte prefixed to every [E9thInstruction](enum.E9thInstruction.html).
+/// Both instruction families reuse tags from 0, so the namespace decides which
+/// enum decodes the rest of the data. The values sit outside the 0–7 tag range of
+/// the original unprefixed format, so old-format data is rejected, not misrouted.
+pub const ENHANCED_NAMESPACE: u8 = 0xE9;
+/// Namespace byte prefixed to every [E9thTokenInstruction](enum.E9thTokenInstruction.html)
+pub const LEGACY_NAMESPACE: u8 = 0x4C;
+
+/// Declares an instruction enum together with `VARIANT_DOCS`, the doc comment
+/// lines of each variant in declaration (tag) order. The IDL reads variant
+/// descriptions and `Accounts:` lists from it and everything else from the
+/// enum's Borsh schema.
+macro_rules! instruction_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[doc = $doc:literal])*
+                $variant:ident $({ $($field:ident: $ty:ty),* $(,)? })?
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $(
+                $(#[doc = $doc])*
+                $variant $({ $($field: $ty),* })?
+            ),*
+        }
+
+        #[cfg(not(target_os = "solana"))]
+        impl $name {
+            /// Doc comment lines of each variant, in declaration (tag) order
+            pub const VARIANT_DOCS: &'static [&'static [&'static str]] = &[$(&[$($doc),*] as &[&str]),*];
+
+            /// Borsh schema of the instruction data after the namespace byte
+            #[allow(dead_code)]
+            pub fn schema_container() -> borsh::schema::BorshSchemaContainer {
+                // Derived on a field-for-field copy: deriving it on the enum itself
+                // leaves borsh's per-variant helper structs flagged as dead code
+                #[derive(borsh::BorshSchema)]
+                enum $name {
+                    $($variant $({ $($field: $ty),* })?),*
+                }
+                <$name as borsh::BorshSchema>::schema_container()
+            }
+        }
+    };
 }
 
-/// Instructions supported by the E9th Token Program (legacy)
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
-pub enum E9thTokenInstruction {
-    /// Initialize the program
-    /// Accounts:
-    /// 0. [writable] Program state account
-    /// 1. [signer] Admin authority
-    /// 2. [writable] Token mint account
-    /// 3. [] System program
-    /// 4. [] Token program
-    Initialize {
-        total_supply: u64,
-        reward_rate: u16,
-        min_stake_period: u64,
-        max_stake_period: u64,
-    },
+instruction_enum! {
+    /// Enhanced instructions for the E9th Token Program
+    ///
+    /// Wire format: [`ENHANCED_NAMESPACE`] followed by the Borsh encoding of the
+    /// variant, whose first byte is the variant's tag (see [`E9thInstruction::tag`]).
+    /// Tags are the declaration order, so new enhanced variants go directly before
+    /// the `Legacy*` block and existing variants are never reordered. `Legacy*`
+    /// variants are always packed in the legacy namespace.
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+    pub enum E9thInstruction {
+        /// Initialize the config account
+        /// Accounts:
+        /// 0. [writable] Token config account (PDA: ["config", mint])
+        /// 1. [writable] Blacklist account (PDA: ["blacklist", config])
+        /// 2. [signer, writable] Owner authority (the mint authority; pays for both accounts)
+        /// 3. [] Token mint account
+        /// 4. [] System program
+        /// 5. [] Token program
+        Initialize {
+            owner: Pubkey,
+            operator: Pubkey,
+            treasury: Pubkey,
+            burn_rate_basis_points: u16,
+        },
+        /// Pause or unpause token transfers
+        /// Accounts:
+        /// 0. [writable] Token config account
+        /// 1. [signer] Owner authority
+        SetPause {
+            pause: bool,
+        },
+        /// Add or remove from blacklist
+        /// Accounts:
+        /// 0. [writable] Blacklist account
+        /// 1. [signer] Operator or owner authority
+        /// 2. [] Token config account
+        ModifyBlacklist {
+            account: Pubkey,
+            add: bool,
+        },
+        /// Transfer tokens, burning `burn_rate_basis_points` of the amount from the
+        /// source. Rejected while paused or when either wallet is blacklisted.
+        /// Accounts:
+        /// 0. [writable] Token config account
+        /// 1. [] Blacklist account
+        /// 2. [writable] Source token account
+        /// 3. [writable] Destination token account
+        /// 4. [] Token program
+        /// 5. [signer] Source token account owner
+        /// 6. [writable] Token mint
+        Transfer {
+            amount: u64,
+        },
+        /// Batch Airdrop: transfer `amounts[i]` from the treasury to each recipient
+        /// Accounts:
+        /// 0. [] Token config account
+        /// 1. [writable] Treasury token account
+        /// 2. [] Token program
+        /// 3. [signer] Treasury authority
+        /// 4. [writable] Recipient token accounts (one per entry of `recipients`, in order)
+        Airdrop {
+            recipients: Vec<Pubkey>,
+            amounts: Vec<u64>,
+        },
+        /// Stake some tokens: move them into the staking vault and add them to the
+        /// staker's entry, locked for the config's minimum stake period. With the
+        /// membership accounts, the staker's pass is issued or raised to the tier
+        /// of the entry.
+        /// Accounts:
+        /// 0. [writable] Token config account
+        /// 1. [writable] Stake entry account (PDA: ["stake_entry", config, staker])
+        /// 2. [writable] Staker token account
+        /// 3. [writable] Staking vault (token account owned by the config PDA)
+        /// 4. [] Token program
+        /// 5. [signer, writable] Staker, pays for a new stake entry or membership pass
+        /// 6. [] System program
+        /// 7. [] Tier config (only if syncing the membership pass; PDA: ["tier_config", config]; may be uninitialized)
+        /// 8. [writable] Membership pass (only if syncing the membership pass; PDA: ["membership", config, staker])
+        Stake {
+            amount: u64,
+        },
+        /// Unstake: return tokens from the staking vault once the entry's lock
+        /// has passed, and lower the staker's membership pass to the tier of
+        /// what stays staked. The pass is required so it can't outlive the stake.
+        /// Accounts:
+        /// 0. [writable] Token config account
+        /// 1. [writable] Stake entry account (PDA: ["stake_entry", config, staker])
+        /// 2. [writable] Staker token account
+        /// 3. [writable] Staking vault (token account owned by the config PDA)
+        /// 4. [] Token program
+        /// 5. [signer] Staker
+        /// 6. [] Tier config (PDA: ["tier_config", config]; may be uninitialized)
+        /// 7. [writable] Membership pass (PDA: ["membership", config, staker]; may be uninitialized)
+        Unstake {
+            amount: u64,
+        },
+        /// Claim staking rewards, including rewards carried from a migrated legacy
+        /// stake. They are paid from the treasury token account, which must have
+        /// approved the config PDA as its delegate for at least the claim.
+        /// Accounts:
+        /// 0. [] Token config account
+        /// 1. [writable] Stake entry account (PDA: ["stake_entry", config, staker])
+        /// 2. [writable] Staker token account
+        /// 3. [writable] Treasury token account (config PDA as delegate)
+        /// 4. [] Token program
+        /// 5. [signer] Staker
+        ClaimRewards,
+        /// Create a merkle airdrop round and fund its vault (operator or owner).
+        /// Round ids start at 1.
+        /// Accounts:
+        /// 0. [] Token config account
+        /// 1. [writable] Airdrop round account (PDA: ["airdrop", config, round_id])
+        /// 2. [writable] Round vault token account (owned by the round PDA)
+        /// 3. [writable] Funding token account
+        /// 4. [signer, writable] Operator or owner authority
+        /// 5. [] System program
+        /// 6. [] Token program
+        CreateAirdropRound {
+            round_id: u64,
+            merkle_root: [u8; 32],
+            total_amount: u64,
+            num_recipients: u64,
+            expiry_timestamp: u64,
+            gate: AirdropGate,
+        },
+        /// Claim an allocation from a merkle airdrop round
+        /// Accounts:
+        /// 0. [] Token config account
+        /// 1. [writable] Airdrop round account
+        /// 2. [writable] Round vault token account
+        /// 3. [signer] Claimant
+        /// 4. [writable] Claimant's token account
+        /// 5. [] Token program
+        /// 6. [] Claimant's stake entry (only if the round requires a minimum stake or holding period)
+        /// 7. [] Instructions sysvar (only if the round requires an attestation)
+        ClaimAirdrop {
+            round_id: u64,
+            index: u64,
+            amount: u64,
+            proof: Vec<[u8; 32]>,
+        },
+        /// Return unclaimed tokens of an expired round to the treasury
+        /// Accounts:
+        /// 0. [] Token config account
+        /// 1. [writable] Airdrop round account
+        /// 2. [writable] Round vault token account
+        /// 3. [writable] Treasury token account
+        /// 4. [signer] Operator or owner authority
+        /// 5. [] Token program
+        ClawbackAirdrop {
+            round_id: u64,
+        },
+        /// Create a public sale and fund its vault with the hard cap (owner only)
+        /// Accounts:
+        /// 0. [] Token config account
+        /// 1. [writable] Sale account (PDA: ["sale", config, sale_id])
+        /// 2. [writable] Sale vault token account (E9TH, owned by the sale PDA)
+        /// 3. [] Quote vault token account (owned by the sale PDA; ignored for lamport sales)
+        /// 4. [writable] Funding token account
+        /// 5. [signer, writable] Owner authority
+        /// 6. [] System program
+        /// 7. [] Token program
+        CreateSale {
+            sale_id: u64,
+            terms: SaleTerms,
+        },
+        /// Buy E9TH from an active sale
+        /// Accounts:
+        /// 0. [] Token config account
+        /// 1. [writable] Sale account
+        /// 2. [writable] Sale vault token account
+        /// 3. [writable] Purchase record (PDA: ["sale_purchase", sale, buyer])
+        /// 4. [signer, writable] Buyer
+        /// 5. [writable] Buyer's E9TH token account
+        /// 6. [writable] Buyer's quote token account (ignored for lamport sales)
+        /// 7. [writable] Quote vault token account (ignored for lamport sales)
+        /// 8. [] System program
+        /// 9. [] Token program
+        BuySale {
+            sale_id: u64,
+            amount: u64,
+            whitelist_index: u64,
+            allocation: u64,
+            proof: Vec<[u8; 32]>,
+        },
+        /// Send proceeds and unsold E9TH to the treasury after the sale ends (owner only)
+        /// Accounts:
+        /// 0. [] Token config account
+        /// 1. [writable] Sale account
+        /// 2. [writable] Sale vault token account
+        /// 3. [writable] Treasury E9TH token account
+        /// 4. [writable] Quote vault token account (ignored for lamport sales)
+        /// 5. [writable] Treasury wallet (lamport sales) or treasury quote token account
+        /// 6. [signer] Owner authority
+        /// 7. [] Token program
+        WithdrawSaleProceeds {
+            sale_id: u64,
+        },
+        /// Refund a purchase after the sale ended below its soft cap
+        /// Accounts:
+        /// 0. [] Token config account
+        /// 1. [writable] Sale account
+        /// 2. [writable] Sale vault token account
+        /// 3. [writable] Purchase record
+        /// 4. [signer, writable] Buyer
+        /// 5. [writable] Buyer's E9TH token account
+        /// 6. [writable] Buyer's quote token account (ignored for lamport sales)
+        /// 7. [writable] Quote vault token account (ignored for lamport sales)
+        /// 8. [] Token program
+        RefundSale {
+            sale_id: u64,
+        },
+        /// Rewrite an account stored in an older layout (`from_version` 0 = no header)
+        /// in the current layout, reallocating it if the layout grew. Only the types
+        /// that predate headers (token config, blacklist, stake entry, program state
+        /// and legacy stake) have an older layout. Authorized by the config owner, or
+        /// by the program admin for `ProgramState` and `StakeAccount`; migrate the
+        /// config or program state itself first. The account must sit at its type's PDA.
+        /// Accounts:
+        /// 0. [writable] Account to migrate
+        /// 1. [] Token config, or program state for legacy account types (may be account 0)
+        /// 2. [signer, writable] Config owner or program admin, pays for any extra rent
+        /// 3. [] System program
+        MigrateAccount {
+            account_type: AccountType,
+            from_version: u8,
+        },
+        /// Convert the legacy program state into the token config for its mint,
+        /// carrying its supply and staking settings, and mark the state migrated
+        /// (admin only)
+        /// Accounts:
+        /// 0. [writable] Program state account (PDA: ["program_state"])
+        /// 1. [writable] Token config account (PDA: ["config", mint])
+        /// 2. [signer, writable] Program admin, becomes the config owner and pays for it
+        /// 3. [] System program
+        MigrateProgramState {
+            operator: Pubkey,
+            treasury: Pubkey,
+            burn_rate_basis_points: u16,
+        },
+        /// Convert the staker's mature legacy stake into a stake entry carrying its
+        /// unclaimed rewards, and close the legacy stake account. The staked amount
+        /// moves from the staker's token account into the staking vault. With the
+        /// membership accounts, the staker's pass is issued for the new entry.
+        /// Accounts:
+        /// 0. [writable] Program state account (PDA: ["program_state"])
+        /// 1. [writable] Token config account (PDA: ["config", mint])
+        /// 2. [writable] Legacy stake account (PDA: ["stake", staker])
+        /// 3. [writable] Stake entry account (PDA: ["stake_entry", config, staker])
+        /// 4. [signer, writable] Staker, pays for the stake entry
+        /// 5. [] System program
+        /// 6. [writable] Staker token account
+        /// 7. [writable] Staking vault (token account owned by the config PDA)
+        /// 8. [] Token program
+        /// 9. [] Tier config (only if syncing the membership pass; PDA: ["tier_config", config]; may be uninitialized)
+        /// 10. [writable] Membership pass (only if syncing the membership pass; PDA: ["membership", config, staker])
+        MigrateStake,
+        /// Reject (or accept again) every legacy instruction (owner only)
+        /// Accounts:
+        /// 0. [writable] Token config account (PDA: ["config", mint])
+        /// 1. [writable] Program state account (PDA: ["program_state"])
+        /// 2. [signer] Owner authority
+        SetLegacyDisabled {
+            disabled: bool,
+        },
+        /// Create or replace the utility tier thresholds (owner only)
+        /// Accounts:
+        /// 0. [] Token config account (PDA: ["config", mint])
+        /// 1. [writable] Tier config account (PDA: ["tier_config", config])
+        /// 2. [signer, writable] Owner authority, pays for the tier config
+        /// 3. [] System program
+        SetTierThresholds {
+            thresholds: TierThresholds,
+        },
+        /// Compute a wallet's utility tier from its held plus staked E9TH and set
+        /// it (Borsh `UtilityTier`, one byte) as return data. Read-only, so partner
+        /// programs can CPI into it and clients can simulate it.
+        /// Accounts:
+        /// 0. [] Token config account (PDA: ["config", mint])
+        /// 1. [] Tier config account (PDA: ["tier_config", config])
+        /// 2. [] Wallet
+        /// 3. [] Wallet's token account for the mint
+        /// 4. [] Wallet's stake entry (PDA: ["stake_entry", config, wallet]; may be uninitialized)
+        VerifyTier,
+        /// Register a merchant or update its voucher terms (operator only). The
+        /// vault can't change while vouchers are outstanding.
+        /// Accounts:
+        /// 0. [] Token config account (PDA: ["config", mint])
+        /// 1. [writable] Merchant account (PDA: ["merchant", config, merchant authority])
+        /// 2. [] Merchant authority, signs redemptions
+        /// 3. [] Voucher vault: token account for the mint owned by the merchant PDA
+        /// 4. [signer, writable] Operator authority, pays for the merchant account
+        /// 5. [] System program
+        RegisterMerchant {
+            voucher_cost: u64,
+            voucher_mode: VoucherMode,
+            active: bool,
+        },
+        /// Mint a voucher for an active merchant, locking the voucher cost in the
+        /// merchant's vault or burning it, per the merchant's voucher mode
+        /// Accounts:
+        /// 0. [writable] Token config account (PDA: ["config", mint])
+        /// 1. [writable] Merchant account (PDA: ["merchant", config, merchant authority])
+        /// 2. [writable] Voucher account (PDA: ["voucher", merchant, holder, voucher_id LE bytes])
+        /// 3. [signer, writable] Holder, pays for the voucher
+        /// 4. [writable] Holder's token account
+        /// 5. [writable] Token mint
+        /// 6. [writable] Merchant's voucher vault
+        /// 7. [] Token program
+        /// 8. [] System program
+        MintVoucher {
+            voucher_id: u64,
+        },
+        /// Redeem an unexpired voucher (merchant authority only). Locked E9TH
+        /// goes back to the holder.
+        /// Accounts:
+        /// 0. [] Token config account (PDA: ["config", mint])
+        /// 1. [writable] Merchant account (PDA: ["merchant", config, merchant authority])
+        /// 2. [writable] Voucher account (PDA: ["voucher", merchant, holder, voucher_id LE bytes])
+        /// 3. [signer] Merchant authority
+        /// 4. [writable] Holder's token account
+        /// 5. [writable] Merchant's voucher vault
+        /// 6. [] Token program
+        RedeemVoucher,
+        /// Take back the E9TH locked for a voucher (holder only), once it has
+        /// expired or its merchant is inactive. Allowed while paused.
+        /// Accounts:
+        /// 0. [] Token config account (PDA: ["config", mint])
+        /// 1. [] Merchant account (PDA: ["merchant", config, merchant authority])
+        /// 2. [writable] Voucher account (PDA: ["voucher", merchant, holder, voucher_id LE bytes])
+        /// 3. [signer] Holder
+        /// 4. [writable] Holder's token account
+        /// 5. [writable] Merchant's voucher vault
+        /// 6. [] Token program
+        ReclaimVoucher,
+        /// Set a merchant's `Pay` tier discounts and burn share (operator only)
+        /// Accounts:
+        /// 0. [] Token config account (PDA: ["config", mint])
+        /// 1. [writable] Merchant account (PDA: ["merchant", config, merchant authority])
+        /// 2. [signer] Operator authority
+        SetPaymentTerms {
+            terms: PaymentTerms,
+        },
+        /// Pay a merchant `amount` (list price) less the discount for the tier of
+        /// the payer's stake, burning the merchant's burn share; the order
+        /// reference is logged as a memo
+        /// Accounts:
+        /// 0. [writable] Token config account (PDA: ["config", mint])
+        /// 1. [] Blacklist account (PDA: ["blacklist", config])
+        /// 2. [] Tier config account (PDA: ["tier_config", config]; may be uninitialized)
+        /// 3. [] Merchant account (PDA: ["merchant", config, merchant authority])
+        /// 4. [signer] Payer
+        /// 5. [writable] Payer's token account
+        /// 6. [] Payer's stake entry (PDA: ["stake_entry", config, payer]; may be uninitialized)
+        /// 7. [writable] Merchant authority's token account
+        /// 8. [writable] Token mint
+        /// 9. [] Token program
+        /// 10. [] Memo program
+        Pay {
+            amount: u64,
+            order_reference: String,
+        },
+        /// Pre-approve an active merchant to pull `terms.amount` every period by
+        /// delegating the full allowance from the subscriber's vault to the
+        /// subscription. The first period is due immediately.
+        /// Accounts:
+        /// 0. [] Token config account (PDA: ["config", mint])
+        /// 1. [] Merchant account (PDA: ["merchant", config, merchant authority])
+        /// 2. [writable] Subscription account (PDA: ["subscription", merchant, subscriber, subscription_id LE bytes])
+        /// 3. [signer, writable] Subscriber, pays for the subscription
+        /// 4. [writable] Subscriber's vault token account
+        /// 5. [] Token program
+        /// 6. [] System program
+        CreateSubscription {
+            subscription_id: u64,
+            terms: SubscriptionTerms,
+        },
+        /// Pull one due period from the vault to the merchant (permissionless)
+        /// Accounts:
+        /// 0. [] Token config account (PDA: ["config", mint])
+        /// 1. [] Merchant account (PDA: ["merchant", config, merchant authority])
+        /// 2. [writable] Subscription account (PDA: ["subscription", merchant, subscriber, subscription_id LE bytes])
+        /// 3. [writable] Subscriber's vault token account
+        /// 4. [writable] Merchant authority's token account
+        /// 5. [] Token program
+        CollectSubscription,
+        /// Cancel a subscription and revoke its delegation (subscriber only)
+        /// Accounts:
+        /// 0. [writable] Subscription account (PDA: ["subscription", merchant, subscriber, subscription_id LE bytes])
+        /// 1. [signer] Subscriber
+        /// 2. [writable] Subscriber's vault token account
+        /// 3. [] Token program
+        CancelSubscription,
+        /// Create an early-access launch with a reservation queue (operator only)
+        /// Accounts:
+        /// 0. [] Token config account (PDA: ["config", mint])
+        /// 1. [writable] Launch account (PDA: ["launch", config, launch_id LE bytes])
+        /// 2. [signer, writable] Operator, pays for the launch account
+        /// 3. [] System program
+        CreateLaunch {
+            launch_id: u64,
+            terms: LaunchTerms,
+        },
+        /// Reserve an early-access slot before the launch opens. The tier of the
+        /// wallet's stake entry, which is escrowed in the staking vault, must
+        /// meet the launch minimum, and stake-weighted launches rank it by that
+        /// stake (legacy stakes don't count until migrated). The weight is
+        /// recorded at reservation and goes stale if the wallet unstakes.
+        /// Accounts:
+        /// 0. [] Token config account (PDA: ["config", mint])
+        /// 1. [] Tier config account (PDA: ["tier_config", config]; may be uninitialized)
+        /// 2. [writable] Launch account (PDA: ["launch", config, launch_id LE bytes])
+        /// 3. [signer] Wallet
+        /// 4. [] Wallet's stake entry (PDA: ["stake_entry", config, wallet]; may be uninitialized)
+        ReserveLaunchSlot,
+        /// Legacy instructions for backward compatibility. Accounts match the
+        /// corresponding [E9thTokenInstruction](enum.E9thTokenInstruction.html) variant.
+        LegacyInitialize {
+            total_supply: u64,
+            reward_rate: u16,
+            min_stake_period: u64,
+            max_stake_period: u64,
+        },
+        LegacyMint {
+            amount: u64,
+        },
+        LegacyBurn {
+            amount: u64,
+        },
+        LegacyStake {
+            amount: u64,
+            period: u64,
+        },
+        LegacyUnstake,
+        LegacyClaimRewards,
+        LegacyUpdateSettings {
+            reward_rate: Option<u16>,
+            min_stake_period: Option<u64>,
+            max_stake_period: Option<u64>,
+            staking_enabled: Option<bool>,
+        },
+        LegacyTransferAdmin {
+            new_admin: Pubkey,
+        },
+    }
+}
 
-    /// Mint tokens to a user
-    /// Accounts:
-    /// 0. [writable] Program state account
-    /// 1. [signer] Admin authority
-    /// 2. [writable] Token mint account
-    /// 3. [writable] Destination token account
-    /// 4. [] Token program
-    Mint {
-        amount: u64,
-    },
+instruction_enum! {
+    /// Instructions supported by the E9th Token Program (legacy)
+    ///
+    /// Wire format: [`LEGACY_NAMESPACE`] followed by the Borsh encoding of the variant,
+    /// whose first byte is the variant's tag (see [`E9thTokenInstruction::tag`]).
+    /// Tags are the declaration order, so new legacy variants are appended at the end
+    /// and existing variants are never reordered or removed.
+    ///
+    /// Every variant but `Initialize` fails with `LegacyInstructionsDisabled` once
+    /// the config owner disables the family with `SetLegacyDisabled`.
+    #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+    pub enum E9thTokenInstruction {
+        /// Initialize the program
+        /// Accounts:
+        /// 0. [writable] Program state account (PDA: ["program_state"])
+        /// 1. [signer, writable] Admin authority
+        /// 2. [writable] Token mint account
+        /// 3. [] System program
+        /// 4. [] Token program
+        Initialize {
+            total_supply: u64,
+            reward_rate: u16,
+            min_stake_period: u64,
+            max_stake_period: u64,
+        },
 
-    /// Burn tokens from a user
-    /// Accounts:
-    /// 0. [writable] Program state account
-    /// 1. [signer] Admin authority
-    /// 2. [writable] Token mint account
-    /// 3. [writable] Source token account
-    /// 4. [] Token program
-    Burn {
-        amount: u64,
-    },
+        /// Mint tokens to a user
+        /// Accounts:
+        /// 0. [writable] Program state account
+        /// 1. [signer] Admin authority
+        /// 2. [writable] Token mint account
+        /// 3. [writable] Destination token account
+        /// 4. [] Token program
+        Mint {
+            amount: u64,
+        },
 
-    /// Stake tokens
-    /// Accounts:
-    /// 0. [writable] Program state account
-    /// 1. [writable] Stake account
-    /// 2. [signer] User
-    /// 3. [writable] User's token account
-    /// 4. [] Token program
-    Stake {
-        amount: u64,
-        period: u64,
-    },
+        /// Burn tokens from a user
+        /// Accounts:
+        /// 0. [writable] Program state account
+        /// 1. [signer] Admin authority
+        /// 2. [writable] Token mint account
+        /// 3. [writable] Source token account
+        /// 4. [] Token program
+        /// 5. [writable] Token config (only if migrated, and then required, to record the burn in its burn stats)
+        Burn {
+            amount: u64,
+        },
 
-    /// Unstake tokens
-    /// Accounts:
-    /// 0. [writable] Program state account
-    /// 1. [writable] Stake account
-    /// 2. [signer] User
-    /// 3. [writable] User's token account
-    /// 4. [] Token program
-    Unstake,
-
-    /// Claim rewards
-    /// Accounts:
-    /// 0. [writable] Program state account
-    /// 1. [writable] Stake account
-    /// 2. [signer] User
-    /// 3. [writable] User's token account
-    /// 4. [] Token program
-    ClaimRewards,
-
-    /// Update program settings (admin only)
-    /// Accounts:
-    /// 0. [writable] Program state account
-    /// 1. [signer] Admin authority
-    UpdateSettings {
-        reward_rate: Option<u16>,
-        min_stake_period: Option<u64>,
-        max_stake_period: Option<u64>,
-        staking_enabled: Option<bool>,
-    },
+        /// Stake tokens. Retired: always fails with `LegacyStakingRetired`.
+        /// Accounts:
+        /// 0. [writable] Program state account
+        /// 1. [writable] Stake account (PDA: ["stake", user])
+        /// 2. [signer] User
+        /// 3. [writable] User's token account
+        /// 4. [] Token program
+        Stake {
+            amount: u64,
+            period: u64,
+        },
 
-    /// Transfer admin authority
-    /// Accounts:
-    /// 0. [writable] Program state account
-    /// 1. [signer] Current admin
-    /// 2. [] New admin
-    TransferAdmin {
-        new_admin: solana_program::pubkey::Pubkey,
-    },
+        /// Unstake tokens. Retired: always fails with `LegacyStakingRetired`;
+        /// `MigrateStake` moves a legacy stake into a stake entry.
+        /// Accounts:
+        /// 0. [writable] Program state account
+        /// 1. [writable] Stake account (PDA: ["stake", user])
+        /// 2. [signer] User
+        /// 3. [writable] User's token account
+        /// 4. [] Token program
+        Unstake,
+
+        /// Claim rewards. Retired: always fails with `LegacyStakingRetired`;
+        /// `MigrateStake` carries unclaimed rewards into a stake entry.
+        /// Accounts:
+        /// 0. [writable] Program state account
+        /// 1. [writable] Stake account (PDA: ["stake", user])
+        /// 2. [signer] User
+        /// 3. [writable] User's token account
+        /// 4. [] Token program
+        ClaimRewards,
+
+        /// Update program settings (admin only)
+        /// Accounts:
+        /// 0. [writable] Program state account
+        /// 1. [signer] Admin authority
+        UpdateSettings {
+            reward_rate: Option<u16>,
+            min_stake_period: Option<u64>,
+            max_stake_period: Option<u64>,
+            staking_enabled: Option<bool>,
+        },
+
+        /// Transfer admin authority
+        /// Accounts:
+        /// 0. [writable] Program state account
+        /// 1. [signer] Current admin
+        /// 2. [] New admin
+        TransferAdmin {
+            new_admin: solana_program::pubkey::Pubkey,
+        },
+    }
 }
 
 impl E9thInstruction {
     /// Unpack a byte buffer into a [E9thInstruction](enum.E9thInstruction.html).
+    /// Legacy-namespace data decodes into the matching `Legacy*` variant.
     pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
-        let (tag, rest) = input.split_first().ok_or(ProgramError::InvalidInstructionData)?;
-        Ok(match tag {
-            0 => {
-                let (owner_bytes, rest) = rest.split_at(32);
-                let (operator_bytes, rest) = rest.split_at(32);
-                let (treasury_bytes, rest) = rest.split_at(32);
-                let (burn_rate, _) = Self::unpack_u16(rest)?;
-                
-                let owner = Pubkey::try_from(owner_bytes).map_err(|_| ProgramError::InvalidInstructionData)?;
-                let operator = Pubkey::try_from(operator_bytes).map_err(|_| ProgramError::InvalidInstructionData)?;
-                let treasury = Pubkey::try_from(treasury_bytes).map_err(|_| ProgramError::InvalidInstructionData)?;
-                
-                Self::Initialize {
-                    owner,
-                    operator,
-                    treasury,
-                    burn_rate_basis_points: burn_rate,
-                }
-            }
-            1 => {
-                let (pause, _) = Self::unpack_u8(rest)?;
-                Self::SetPause { pause: pause != 0 }
-            }
-            2 => {
-                let (account_bytes, rest) = rest.split_at(32);
-                let (add, _) = Self::unpack_u8(rest)?;
-                let account = Pubkey::try_from(account_bytes).map_err(|_| ProgramError::InvalidInstructionData)?;
-                Self::ModifyBlacklist { account, add: add != 0 }
-            }
-            3 => {
-                let (amount, _) = Self::unpack_u64(rest)?;
-                Self::Transfer { amount }
-            }
-            4 => {
-                // For airdrop, we need to handle variable length vectors
-                // This is simplified - in practice, you'd need more complex parsing
-                Self::Airdrop {
-                    recipients: Vec::new(),
-                    amounts: Vec::new(),
+        let (namespace, body) = input.split_first().ok_or(ProgramError::InvalidInstructionData)?;
+        match *namespace {
+            ENHANCED_NAMESPACE => {
+                let instruction =
+                    Self::try_from_slice(body).map_err(|_| ProgramError::InvalidInstructionData)?;
+                // Legacy variants only travel in the legacy namespace
+                if instruction.is_legacy() {
+                    return Err(ProgramError::InvalidInstructionData);
                 }
+                instruction.validate()?;
+                Ok(instruction)
             }
-            5 => {
-                let (amount, _) = Self::unpack_u64(rest)?;
-                Self::Stake { amount }
-            }
-            6 => {
-                let (amount, _) = Self::unpack_u64(rest)?;
-                Self::Unstake { amount }
-            }
-            7 => Self::ClaimRewards,
-            _ => return Err(ProgramError::InvalidInstructionData),
-        })
+            LEGACY_NAMESPACE => E9thTokenInstruction::unpack(input).map(Self::from_legacy),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
     }
 
     /// Pack a [E9thInstruction](enum.E9thInstruction.html) into a byte buffer.
     pub fn pack(&self) -> Vec<u8> {
-        let mut buf = Vec::new();
+        if let Ok(legacy) = self.to_legacy() {
+            return legacy.pack();
+        }
+        let mut buf = vec![ENHANCED_NAMESPACE];
+        // Writing into a Vec cannot fail
+        self.serialize(&mut buf).unwrap();
+        buf
+    }
+
+    /// Stable wire tag of the variant (the first byte after the namespace)
+    pub fn tag(&self) -> u8 {
         match self {
-            Self::Initialize { owner, operator, treasury, burn_rate_basis_points } => {
-                buf.push(0);
-                buf.extend_from_slice(owner.as_ref());
-                buf.extend_from_slice(operator.as_ref());
-                buf.extend_from_slice(treasury.as_ref());
-                buf.extend_from_slice(&burn_rate_basis_points.to_le_bytes());
-            }
-            Self::SetPause { pause } => {
-                buf.push(1);
-                buf.push(if *pause { 1 } else { 0 });
-            }
-            Self::ModifyBlacklist { account, add } => {
-                buf.push(2);
-                buf.extend_from_slice(account.as_ref());
-                buf.push(if *add { 1 } else { 0 });
-            }
-            Self::Transfer { amount } => {
-                buf.push(3);
-                buf.extend_from_slice(&amount.to_le_bytes());
+            Self::Initialize { .. } => 0,
+            Self::SetPause { .. } => 1,
+            Self::ModifyBlacklist { .. } => 2,
+            Self::Transfer { .. } => 3,
+            Self::Airdrop { .. } => 4,
+            Self::Stake { .. } => 5,
+            Self::Unstake { .. } => 6,
+            Self::ClaimRewards => 7,
+            Self::CreateAirdropRound { .. } => 8,
+            Self::ClaimAirdrop { .. } => 9,
+            Self::ClawbackAirdrop { .. } => 10,
+            Self::CreateSale { .. } => 11,
+            Self::BuySale { .. } => 12,
+            Self::WithdrawSaleProceeds { .. } => 13,
+            Self::RefundSale { .. } => 14,
+            Self::MigrateAccount { .. } => 15,
+            Self::MigrateProgramState { .. } => 16,
+            Self::MigrateStake => 17,
+            Self::SetLegacyDisabled { .. } => 18,
+            Self::SetTierThresholds { .. } => 19,
+            Self::VerifyTier => 20,
+            Self::RegisterMerchant { .. } => 21,
+            Self::MintVoucher { .. } => 22,
+            Self::RedeemVoucher => 23,
+            Self::ReclaimVoucher => 24,
+            Self::SetPaymentTerms { .. } => 25,
+            Self::Pay { .. } => 26,
+            Self::CreateSubscription { .. } => 27,
+            Self::CollectSubscription => 28,
+            Self::CancelSubscription => 29,
+            Self::CreateLaunch { .. } => 30,
+            Self::ReserveLaunchSlot => 31,
+            // Legacy variants are packed with their legacy tag
+            _ => self.to_legacy().map(|legacy| legacy.tag()).unwrap_or(u8::MAX),
+        }
+    }
+
+    /// Whether this is one of the `Legacy*` wrapper variants
+    pub fn is_legacy(&self) -> bool {
+        self.to_legacy().is_ok()
+    }
+
+    /// Reject decoded values that Borsh accepts but the program never packs
+    fn validate(&self) -> Result<(), ProgramError> {
+        match self {
+            Self::ClaimAirdrop { proof, .. } | Self::BuySale { proof, .. }
+                if proof.len() > MAX_PROOF_LEN =>
+            {
+                Err(ProgramError::InvalidInstructionData)
             }
-            Self::Airdrop { recipients, amounts } => {
-                buf.push(4);
-                // Simplified - in practice, you'd serialize the vectors properly
-                buf.extend_from_slice(&recipients.len().to_le_bytes());
-                for recipient in recipients {
-                    buf.extend_from_slice(recipient.as_ref());
-                }
-                buf.extend_from_slice(&amounts.len().to_le_bytes());
-                for amount in amounts {
-                    buf.extend_from_slice(&amount.to_le_bytes());
+            _ => Ok(()),
+        }
+    }
+
+    /// Convert a decoded legacy instruction into its `Legacy*` variant
+    fn from_legacy(instruction: E9thTokenInstruction) -> Self {
+        match instruction {
+            E9thTokenInstruction::Initialize { total_supply, reward_rate, min_stake_period, max_stake_period } => {
+                Self::LegacyInitialize {
+                    total_supply,
+                    reward_rate,
+                    min_stake_period,
+                    max_stake_period,
                 }
             }
-            Self::Stake { amount } => {
-                buf.push(5);
-                buf.extend_from_slice(&amount.to_le_bytes());
-            }
-            Self::Unstake { amount } => {
-                buf.push(6);
-                buf.extend_from_slice(&amount.to_le_bytes());
-            }
-            Self::ClaimRewards => {
-                buf.push(7);
-            }
-            _ => {
-                // Handle legacy instructions by delegating to the old enum
-                return match self.to_legacy() {
-                    Ok(legacy_instr) => legacy_instr.pack(),
-                    Err(_) => Vec::new(), // Return empty vector on error
-                };
+            E9thTokenInstruction::Mint { amount } => Self::LegacyMint { amount },
+            E9thTokenInstruction::Burn { amount } => Self::LegacyBurn { amount },
+            E9thTokenInstruction::Stake { amount, period } => Self::LegacyStake { amount, period },
+            E9thTokenInstruction::Unstake => Self::LegacyUnstake,
+            E9thTokenInstruction::ClaimRewards => Self::LegacyClaimRewards,
+            E9thTokenInstruction::UpdateSettings { reward_rate, min_stake_period, max_stake_period, staking_enabled } => {
+                Self::LegacyUpdateSettings {
+                    reward_rate,
+                    min_stake_period,
+                    max_stake_period,
+                    staking_enabled,
+                }
             }
+            E9thTokenInstruction::TransferAdmin { new_admin } => Self::LegacyTransferAdmin { new_admin },
         }
-        buf
     }
 
     /// Convert to legacy instruction for backward compatibility
@@ -319,213 +783,1065 @@ impl E9thInstruction {
             _ => Err(ProgramError::InvalidInstructionData),
         }
     }
+}
 
-    fn unpack_u8(input: &[u8]) -> Result<(u8, &[u8]), ProgramError> {
-        if input.is_empty() {
+impl E9thTokenInstruction {
+    /// Unpack a byte buffer into a [E9thTokenInstruction](enum.E9thTokenInstruction.html).
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let (namespace, body) = input.split_first().ok_or(ProgramError::InvalidInstructionData)?;
+        if *namespace != LEGACY_NAMESPACE {
             return Err(ProgramError::InvalidInstructionData);
         }
-        Ok((input[0], &input[1..]))
+        Self::try_from_slice(body).map_err(|_| ProgramError::InvalidInstructionData)
     }
 
-    fn unpack_u16(input: &[u8]) -> Result<(u16, &[u8]), ProgramError> {
-        if input.len() < 2 {
-            return Err(ProgramError::InvalidInstructionData);
+    /// Pack a [E9thTokenInstruction](enum.E9thTokenInstruction.html) into a byte buffer.
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = vec![LEGACY_NAMESPACE];
+        // Writing into a Vec cannot fail
+        self.serialize(&mut buf).unwrap();
+        buf
+    }
+
+    /// Stable wire tag of the variant (the first byte after the namespace)
+    pub fn tag(&self) -> u8 {
+        match self {
+            Self::Initialize { .. } => 0,
+            Self::Mint { .. } => 1,
+            Self::Burn { .. } => 2,
+            Self::Stake { .. } => 3,
+            Self::Unstake => 4,
+            Self::ClaimRewards => 5,
+            Self::UpdateSettings { .. } => 6,
+            Self::TransferAdmin { .. } => 7,
         }
-        let (bytes, rest) = input.split_at(2);
-        let value = u16::from_le_bytes([bytes[0], bytes[1]]);
-        Ok((value, rest))
     }
+}
+
+/// Token config PDA that every enhanced builder derives its other accounts from
+fn config_address(program_id: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Processor::get_token_config_pda(program_id, mint).0
+}
+
+/// Legacy program state PDA, read by the migration builders
+fn program_state_address(program_id: &Pubkey) -> Pubkey {
+    // Derivation never fails; the Result is kept for API compatibility
+    StakeProcessor::get_program_state_pda(program_id).unwrap().0
+}
+
+/// Quote-side account for a sale: the owner's quote token account, or the sale
+/// PDA itself as a placeholder for lamport sales (where it is ignored)
+fn quote_account(owner: &Pubkey, quote_mint: &Pubkey, sale: &Pubkey) -> Pubkey {
+    if *quote_mint == Pubkey::default() {
+        *sale
+    } else {
+        get_associated_token_address(owner, quote_mint)
+    }
+}
 
-    fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
-        if input.len() < 8 {
-            return Err(ProgramError::InvalidInstructionData);
+// Instruction builders. Each derives the program's PDAs from `mint` and uses the
+// owners' associated token accounts, in the order documented on the variant.
+
+/// Creates an `Initialize` instruction
+pub fn initialize(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    operator: &Pubkey,
+    treasury: &Pubkey,
+    burn_rate_basis_points: u16,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (blacklist, _) = Processor::get_blacklist_pda(program_id, &config);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(config, false),
+            AccountMeta::new(blacklist, false),
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: E9thInstruction::Initialize {
+            owner: *owner,
+            operator: *operator,
+            treasury: *treasury,
+            burn_rate_basis_points,
         }
-        let (bytes, rest) = input.split_at(8);
-        let value = u64::from_le_bytes([
-            bytes[0], bytes[1], bytes[2], bytes[3],
-            bytes[4], bytes[5], bytes[6], bytes[7],
-        ]);
-        Ok((value, rest))
+        .pack(),
     }
 }
 
-impl E9thTokenInstruction {
-    /// Unpack a byte buffer into a [E9thTokenInstruction](enum.E9thTokenInstruction.html).
-    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
-        let (tag, rest) = input.split_first().ok_or(ProgramError::InvalidInstructionData)?;
-        Ok(match tag {
-            0 => {
-                let (total_supply, rest) = Self::unpack_u64(rest)?;
-                let (reward_rate, rest) = Self::unpack_u16(rest)?;
-                let (min_stake_period, rest) = Self::unpack_u64(rest)?;
-                let (max_stake_period, _) = Self::unpack_u64(rest)?;
-                Self::Initialize {
-                    total_supply,
-                    reward_rate,
-                    min_stake_period,
-                    max_stake_period,
-                }
-            }
-            1 => {
-                let (amount, _) = Self::unpack_u64(rest)?;
-                Self::Mint { amount }
-            }
-            2 => {
-                let (amount, _) = Self::unpack_u64(rest)?;
-                Self::Burn { amount }
-            }
-            3 => {
-                let (amount, rest) = Self::unpack_u64(rest)?;
-                let (period, _) = Self::unpack_u64(rest)?;
-                Self::Stake { amount, period }
-            }
-            4 => Self::Unstake,
-            5 => Self::ClaimRewards,
-            6 => {
-                let mut reward_rate = None;
-                let mut min_stake_period = None;
-                let mut max_stake_period = None;
-                let mut staking_enabled = None;
-                
-                let mut data = rest;
-                while !data.is_empty() {
-                    let (field_type, rest) = data.split_first().ok_or(ProgramError::InvalidInstructionData)?;
-                    data = rest;
-                    
-                    match field_type {
-                        0 => {
-                            let (value, rest) = Self::unpack_u16(data)?;
-                            reward_rate = Some(value);
-                            data = rest;
-                        }
-                        1 => {
-                            let (value, rest) = Self::unpack_u64(data)?;
-                            min_stake_period = Some(value);
-                            data = rest;
-                        }
-                        2 => {
-                            let (value, rest) = Self::unpack_u64(data)?;
-                            max_stake_period = Some(value);
-                            data = rest;
-                        }
-                        3 => {
-                            let (value, rest) = data.split_first().ok_or(ProgramError::InvalidInstructionData)?;
-                            staking_enabled = Some(*value != 0);
-                            data = rest;
-                        }
-                        _ => return Err(ProgramError::InvalidInstructionData),
-                    }
-                }
-                
-                Self::UpdateSettings {
-                    reward_rate,
-                    min_stake_period,
-                    max_stake_period,
-                    staking_enabled,
-                }
-            }
-            7 => {
-                let (new_admin_bytes, _) = rest.split_at(32);
-                let new_admin = solana_program::pubkey::Pubkey::try_from(new_admin_bytes)
-                    .map_err(|_| ProgramError::InvalidInstructionData)?;
-                Self::TransferAdmin { new_admin }
-            }
-            _ => return Err(ProgramError::InvalidInstructionData),
-        })
+/// Creates a `SetPause` instruction
+pub fn set_pause(program_id: &Pubkey, mint: &Pubkey, owner: &Pubkey, pause: bool) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(config_address(program_id, mint), false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data: E9thInstruction::SetPause { pause }.pack(),
     }
+}
 
-    /// Pack a [E9thTokenInstruction](enum.E9thTokenInstruction.html) into a byte buffer.
-    pub fn pack(&self) -> Vec<u8> {
-        let mut buf = Vec::new();
-        match self {
-            Self::Initialize {
+/// Creates a `ModifyBlacklist` instruction
+pub fn modify_blacklist(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    authority: &Pubkey,
+    account: &Pubkey,
+    add: bool,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (blacklist, _) = Processor::get_blacklist_pda(program_id, &config);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(blacklist, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(config, false),
+        ],
+        data: E9thInstruction::ModifyBlacklist { account: *account, add }.pack(),
+    }
+}
+
+/// Creates a `Transfer` instruction between two wallets' token accounts
+pub fn transfer(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    source_owner: &Pubkey,
+    destination_owner: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (blacklist, _) = Processor::get_blacklist_pda(program_id, &config);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(config, false),
+            AccountMeta::new_readonly(blacklist, false),
+            AccountMeta::new(get_associated_token_address(source_owner, mint), false),
+            AccountMeta::new(get_associated_token_address(destination_owner, mint), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(*source_owner, true),
+            AccountMeta::new(*mint, false),
+        ],
+        data: E9thInstruction::Transfer { amount }.pack(),
+    }
+}
+
+/// Creates a batch `Airdrop` instruction from the treasury to `recipients`
+pub fn airdrop(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    treasury: &Pubkey,
+    recipients: Vec<Pubkey>,
+    amounts: Vec<u64>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(config_address(program_id, mint), false),
+        AccountMeta::new(get_associated_token_address(treasury, mint), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(*treasury, true),
+    ];
+    accounts.extend(
+        recipients
+            .iter()
+            .map(|recipient| AccountMeta::new(get_associated_token_address(recipient, mint), false)),
+    );
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: E9thInstruction::Airdrop { recipients, amounts }.pack(),
+    }
+}
+
+/// Accounts shared by `Stake` and `Unstake`
+fn stake_accounts(program_id: &Pubkey, mint: &Pubkey, staker: &Pubkey) -> Vec<AccountMeta> {
+    let config = config_address(program_id, mint);
+    let (stake_entry, _) = Processor::get_stake_entry_pda(program_id, &config, staker);
+    vec![
+        AccountMeta::new(config, false),
+        AccountMeta::new(stake_entry, false),
+        AccountMeta::new(get_associated_token_address(staker, mint), false),
+        AccountMeta::new(get_associated_token_address(&config, mint), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ]
+}
+
+/// The tier config and membership pass that keep a staker's pass in sync
+fn membership_accounts(program_id: &Pubkey, mint: &Pubkey, staker: &Pubkey) -> [AccountMeta; 2] {
+    let config = config_address(program_id, mint);
+    let (tier_config, _) = TierProcessor::get_tier_config_pda(program_id, &config);
+    let (pass, _) = MembershipProcessor::get_membership_pass_pda(program_id, &config, staker);
+    [AccountMeta::new_readonly(tier_config, false), AccountMeta::new(pass, false)]
+}
+
+/// Creates an enhanced `Stake` instruction that also syncs the staker's membership pass
+pub fn stake(program_id: &Pubkey, mint: &Pubkey, staker: &Pubkey, amount: u64) -> Instruction {
+    let mut accounts = stake_accounts(program_id, mint, staker);
+    accounts.push(AccountMeta::new(*staker, true));
+    accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+    accounts.extend(membership_accounts(program_id, mint, staker));
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: E9thInstruction::Stake { amount }.pack(),
+    }
+}
+
+/// Creates an enhanced `Unstake` instruction; it fails while the entry is locked
+pub fn unstake(program_id: &Pubkey, mint: &Pubkey, staker: &Pubkey, amount: u64) -> Instruction {
+    let mut accounts = stake_accounts(program_id, mint, staker);
+    accounts.push(AccountMeta::new_readonly(*staker, true));
+    accounts.extend(membership_accounts(program_id, mint, staker));
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: E9thInstruction::Unstake { amount }.pack(),
+    }
+}
+
+/// Creates an enhanced `ClaimRewards` instruction paid from the treasury
+pub fn claim_rewards(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    staker: &Pubkey,
+    treasury: &Pubkey,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (stake_entry, _) = Processor::get_stake_entry_pda(program_id, &config, staker);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(stake_entry, false),
+            AccountMeta::new(get_associated_token_address(staker, mint), false),
+            AccountMeta::new(get_associated_token_address(treasury, mint), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(*staker, true),
+        ],
+        data: E9thInstruction::ClaimRewards.pack(),
+    }
+}
+
+/// Creates a `CreateAirdropRound` instruction funded from the authority's token account
+#[allow(clippy::too_many_arguments)]
+pub fn create_airdrop_round(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    authority: &Pubkey,
+    round_id: u64,
+    merkle_root: [u8; 32],
+    total_amount: u64,
+    num_recipients: u64,
+    expiry_timestamp: u64,
+    gate: AirdropGate,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (round, _) = AirdropProcessor::get_round_pda(program_id, &config, round_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(round, false),
+            AccountMeta::new(get_associated_token_address(&round, mint), false),
+            AccountMeta::new(get_associated_token_address(authority, mint), false),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: E9thInstruction::CreateAirdropRound {
+            round_id,
+            merkle_root,
+            total_amount,
+            num_recipients,
+            expiry_timestamp,
+            gate,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `ClaimAirdrop` instruction. The trailing stake entry and
+/// instructions sysvar accounts are appended when the round's `gate` needs them;
+/// an attestation also needs the verifier's ed25519 instruction placed directly
+/// before this one.
+#[allow(clippy::too_many_arguments)]
+pub fn claim_airdrop(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    claimant: &Pubkey,
+    round_id: u64,
+    index: u64,
+    amount: u64,
+    proof: Vec<[u8; 32]>,
+    gate: &AirdropGate,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (round, _) = AirdropProcessor::get_round_pda(program_id, &config, round_id);
+    let mut accounts = vec![
+        AccountMeta::new_readonly(config, false),
+        AccountMeta::new(round, false),
+        AccountMeta::new(get_associated_token_address(&round, mint), false),
+        AccountMeta::new_readonly(*claimant, true),
+        AccountMeta::new(get_associated_token_address(claimant, mint), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    if gate.requires_stake() {
+        let (stake_entry, _) = Processor::get_stake_entry_pda(program_id, &config, claimant);
+        accounts.push(AccountMeta::new_readonly(stake_entry, false));
+    }
+    if gate.requires_attestation() {
+        accounts.push(AccountMeta::new_readonly(sysvar::instructions::id(), false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: E9thInstruction::ClaimAirdrop { round_id, index, amount, proof }.pack(),
+    }
+}
+
+/// Creates a `ClawbackAirdrop` instruction returning unclaimed tokens to the treasury
+pub fn clawback_airdrop(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    authority: &Pubkey,
+    treasury: &Pubkey,
+    round_id: u64,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (round, _) = AirdropProcessor::get_round_pda(program_id, &config, round_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(round, false),
+            AccountMeta::new(get_associated_token_address(&round, mint), false),
+            AccountMeta::new(get_associated_token_address(treasury, mint), false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: E9thInstruction::ClawbackAirdrop { round_id }.pack(),
+    }
+}
+
+/// Creates a `CreateSale` instruction funded from the owner's token account
+pub fn create_sale(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    sale_id: u64,
+    terms: SaleTerms,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (sale, _) = SaleProcessor::get_sale_pda(program_id, &config, sale_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(sale, false),
+            AccountMeta::new(get_associated_token_address(&sale, mint), false),
+            AccountMeta::new_readonly(quote_account(&sale, &terms.quote_mint, &sale), false),
+            AccountMeta::new(get_associated_token_address(owner, mint), false),
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: E9thInstruction::CreateSale { sale_id, terms }.pack(),
+    }
+}
+
+/// Creates a `BuySale` instruction. `quote_mint` is the sale's quote mint
+/// (`Pubkey::default()` for lamport sales); `proof` is empty outside the
+/// whitelist phase.
+#[allow(clippy::too_many_arguments)]
+pub fn buy_sale(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    buyer: &Pubkey,
+    quote_mint: &Pubkey,
+    sale_id: u64,
+    amount: u64,
+    whitelist_index: u64,
+    allocation: u64,
+    proof: Vec<[u8; 32]>,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (sale, _) = SaleProcessor::get_sale_pda(program_id, &config, sale_id);
+    let (purchase, _) = SaleProcessor::get_purchase_pda(program_id, &sale, buyer);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(sale, false),
+            AccountMeta::new(get_associated_token_address(&sale, mint), false),
+            AccountMeta::new(purchase, false),
+            AccountMeta::new(*buyer, true),
+            AccountMeta::new(get_associated_token_address(buyer, mint), false),
+            AccountMeta::new(quote_account(buyer, quote_mint, &sale), false),
+            AccountMeta::new(quote_account(&sale, quote_mint, &sale), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: E9thInstruction::BuySale { sale_id, amount, whitelist_index, allocation, proof }.pack(),
+    }
+}
+
+/// Creates a `WithdrawSaleProceeds` instruction paying the treasury
+pub fn withdraw_sale_proceeds(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    treasury: &Pubkey,
+    quote_mint: &Pubkey,
+    sale_id: u64,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (sale, _) = SaleProcessor::get_sale_pda(program_id, &config, sale_id);
+    // Lamport proceeds go straight to the treasury wallet
+    let treasury_quote = if *quote_mint == Pubkey::default() {
+        *treasury
+    } else {
+        get_associated_token_address(treasury, quote_mint)
+    };
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(sale, false),
+            AccountMeta::new(get_associated_token_address(&sale, mint), false),
+            AccountMeta::new(get_associated_token_address(treasury, mint), false),
+            AccountMeta::new(quote_account(&sale, quote_mint, &sale), false),
+            AccountMeta::new(treasury_quote, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: E9thInstruction::WithdrawSaleProceeds { sale_id }.pack(),
+    }
+}
+
+/// Creates a `RefundSale` instruction for a failed sale
+pub fn refund_sale(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    buyer: &Pubkey,
+    quote_mint: &Pubkey,
+    sale_id: u64,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (sale, _) = SaleProcessor::get_sale_pda(program_id, &config, sale_id);
+    let (purchase, _) = SaleProcessor::get_purchase_pda(program_id, &sale, buyer);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(sale, false),
+            AccountMeta::new(get_associated_token_address(&sale, mint), false),
+            AccountMeta::new(purchase, false),
+            AccountMeta::new(*buyer, true),
+            AccountMeta::new(get_associated_token_address(buyer, mint), false),
+            AccountMeta::new(quote_account(buyer, quote_mint, &sale), false),
+            AccountMeta::new(quote_account(&sale, quote_mint, &sale), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: E9thInstruction::RefundSale { sale_id }.pack(),
+    }
+}
+
+/// Creates a `MigrateAccount` instruction for `account`, authorized by the
+/// config owner (or the program admin for legacy account types).
+pub fn migrate_account(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    authority: &Pubkey,
+    account: &Pubkey,
+    account_type: AccountType,
+    from_version: u8,
+) -> Instruction {
+    let authority_source = match account_type {
+        AccountType::ProgramState | AccountType::StakeAccount => program_state_address(program_id),
+        _ => config_address(program_id, mint),
+    };
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*account, false),
+            AccountMeta::new_readonly(authority_source, false),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: E9thInstruction::MigrateAccount { account_type, from_version }.pack(),
+    }
+}
+
+/// Creates a `MigrateProgramState` instruction for the legacy program state of `mint`
+pub fn migrate_program_state(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    admin: &Pubkey,
+    operator: &Pubkey,
+    treasury: &Pubkey,
+    burn_rate_basis_points: u16,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(program_state_address(program_id), false),
+            AccountMeta::new(config_address(program_id, mint), false),
+            AccountMeta::new(*admin, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: E9thInstruction::MigrateProgramState {
+            operator: *operator,
+            treasury: *treasury,
+            burn_rate_basis_points,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `MigrateStake` instruction for the staker's legacy stake that also
+/// issues the staker's membership pass
+pub fn migrate_stake(program_id: &Pubkey, mint: &Pubkey, staker: &Pubkey) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (stake_entry, _) = Processor::get_stake_entry_pda(program_id, &config, staker);
+    // Derivation never fails; the Result is kept for API compatibility
+    let (stake, _) = StakeProcessor::get_stake_account_pda(program_id, staker, &[]).unwrap();
+    let mut accounts = vec![
+        AccountMeta::new(program_state_address(program_id), false),
+        AccountMeta::new(config, false),
+        AccountMeta::new(stake, false),
+        AccountMeta::new(stake_entry, false),
+        AccountMeta::new(*staker, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(get_associated_token_address(staker, mint), false),
+        AccountMeta::new(get_associated_token_address(&config, mint), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    accounts.extend(membership_accounts(program_id, mint, staker));
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: E9thInstruction::MigrateStake.pack(),
+    }
+}
+
+/// Creates a `SetLegacyDisabled` instruction
+pub fn set_legacy_disabled(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    disabled: bool,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(config_address(program_id, mint), false),
+            AccountMeta::new(program_state_address(program_id), false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data: E9thInstruction::SetLegacyDisabled { disabled }.pack(),
+    }
+}
+
+/// Creates a `SetTierThresholds` instruction
+pub fn set_tier_thresholds(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    thresholds: TierThresholds,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (tier_config, _) = TierProcessor::get_tier_config_pda(program_id, &config);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(tier_config, false),
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: E9thInstruction::SetTierThresholds { thresholds }.pack(),
+    }
+}
+
+/// Creates a `VerifyTier` instruction for `wallet`, counting its associated
+/// token account
+pub fn verify_tier(program_id: &Pubkey, mint: &Pubkey, wallet: &Pubkey) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (tier_config, _) = TierProcessor::get_tier_config_pda(program_id, &config);
+    let (stake_entry, _) = Processor::get_stake_entry_pda(program_id, &config, wallet);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new_readonly(tier_config, false),
+            AccountMeta::new_readonly(*wallet, false),
+            AccountMeta::new_readonly(get_associated_token_address(wallet, mint), false),
+            AccountMeta::new_readonly(stake_entry, false),
+        ],
+        data: E9thInstruction::VerifyTier.pack(),
+    }
+}
+
+/// Creates a `RegisterMerchant` instruction whose vault is the merchant PDA's
+/// associated token account
+pub fn register_merchant(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    operator: &Pubkey,
+    authority: &Pubkey,
+    voucher_cost: u64,
+    voucher_mode: VoucherMode,
+    active: bool,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (merchant, _) = MerchantProcessor::get_merchant_pda(program_id, &config, authority);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(merchant, false),
+            AccountMeta::new_readonly(*authority, false),
+            AccountMeta::new_readonly(get_associated_token_address(&merchant, mint), false),
+            AccountMeta::new(*operator, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: E9thInstruction::RegisterMerchant { voucher_cost, voucher_mode, active }.pack(),
+    }
+}
+
+/// Creates a `MintVoucher` instruction for `holder` at the merchant of
+/// `merchant_authority`
+pub fn mint_voucher(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    holder: &Pubkey,
+    merchant_authority: &Pubkey,
+    voucher_id: u64,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (merchant, _) = MerchantProcessor::get_merchant_pda(program_id, &config, merchant_authority);
+    let (voucher, _) = VoucherProcessor::get_voucher_pda(program_id, &merchant, holder, voucher_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(config, false),
+            AccountMeta::new(merchant, false),
+            AccountMeta::new(voucher, false),
+            AccountMeta::new(*holder, true),
+            AccountMeta::new(get_associated_token_address(holder, mint), false),
+            AccountMeta::new(*mint, false),
+            AccountMeta::new(get_associated_token_address(&merchant, mint), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: E9thInstruction::MintVoucher { voucher_id }.pack(),
+    }
+}
+
+/// Creates a `RedeemVoucher` instruction for `holder`'s voucher `voucher_id`
+pub fn redeem_voucher(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    merchant_authority: &Pubkey,
+    holder: &Pubkey,
+    voucher_id: u64,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (merchant, _) = MerchantProcessor::get_merchant_pda(program_id, &config, merchant_authority);
+    let (voucher, _) = VoucherProcessor::get_voucher_pda(program_id, &merchant, holder, voucher_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(merchant, false),
+            AccountMeta::new(voucher, false),
+            AccountMeta::new_readonly(*merchant_authority, true),
+            AccountMeta::new(get_associated_token_address(holder, mint), false),
+            AccountMeta::new(get_associated_token_address(&merchant, mint), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: E9thInstruction::RedeemVoucher.pack(),
+    }
+}
+
+/// Creates a `ReclaimVoucher` instruction for `holder`'s voucher `voucher_id`
+pub fn reclaim_voucher(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    merchant_authority: &Pubkey,
+    holder: &Pubkey,
+    voucher_id: u64,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (merchant, _) = MerchantProcessor::get_merchant_pda(program_id, &config, merchant_authority);
+    let (voucher, _) = VoucherProcessor::get_voucher_pda(program_id, &merchant, holder, voucher_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new_readonly(merchant, false),
+            AccountMeta::new(voucher, false),
+            AccountMeta::new_readonly(*holder, true),
+            AccountMeta::new(get_associated_token_address(holder, mint), false),
+            AccountMeta::new(get_associated_token_address(&merchant, mint), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: E9thInstruction::ReclaimVoucher.pack(),
+    }
+}
+
+/// Creates a `SetPaymentTerms` instruction for the merchant of `merchant_authority`
+pub fn set_payment_terms(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    operator: &Pubkey,
+    merchant_authority: &Pubkey,
+    terms: PaymentTerms,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (merchant, _) = MerchantProcessor::get_merchant_pda(program_id, &config, merchant_authority);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(merchant, false),
+            AccountMeta::new_readonly(*operator, true),
+        ],
+        data: E9thInstruction::SetPaymentTerms { terms }.pack(),
+    }
+}
+
+/// Creates a `Pay` instruction from `payer` to the merchant of
+/// `merchant_authority`, paying into the authority's associated token account
+pub fn pay(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    payer: &Pubkey,
+    merchant_authority: &Pubkey,
+    amount: u64,
+    order_reference: &str,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (blacklist, _) = Processor::get_blacklist_pda(program_id, &config);
+    let (tier_config, _) = TierProcessor::get_tier_config_pda(program_id, &config);
+    let (merchant, _) = MerchantProcessor::get_merchant_pda(program_id, &config, merchant_authority);
+    let (stake_entry, _) = Processor::get_stake_entry_pda(program_id, &config, payer);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(config, false),
+            AccountMeta::new_readonly(blacklist, false),
+            AccountMeta::new_readonly(tier_config, false),
+            AccountMeta::new_readonly(merchant, false),
+            AccountMeta::new_readonly(*payer, true),
+            AccountMeta::new(get_associated_token_address(payer, mint), false),
+            AccountMeta::new_readonly(stake_entry, false),
+            AccountMeta::new(get_associated_token_address(merchant_authority, mint), false),
+            AccountMeta::new(*mint, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(spl_memo::id(), false),
+        ],
+        data: E9thInstruction::Pay { amount, order_reference: order_reference.to_string() }.pack(),
+    }
+}
+
+/// Subscription PDA shared by the subscription builders
+fn subscription_address(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    merchant_authority: &Pubkey,
+    subscriber: &Pubkey,
+    subscription_id: u64,
+) -> (Pubkey, Pubkey) {
+    let config = config_address(program_id, mint);
+    let (merchant, _) = MerchantProcessor::get_merchant_pda(program_id, &config, merchant_authority);
+    let (subscription, _) =
+        SubscriptionProcessor::get_subscription_pda(program_id, &merchant, subscriber, subscription_id);
+    (merchant, subscription)
+}
+
+/// Creates a `CreateSubscription` instruction. `vault` is a token account of
+/// `subscriber` dedicated to this subscription.
+pub fn create_subscription(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    subscriber: &Pubkey,
+    merchant_authority: &Pubkey,
+    vault: &Pubkey,
+    subscription_id: u64,
+    terms: SubscriptionTerms,
+) -> Instruction {
+    let (merchant, subscription) =
+        subscription_address(program_id, mint, merchant_authority, subscriber, subscription_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(config_address(program_id, mint), false),
+            AccountMeta::new_readonly(merchant, false),
+            AccountMeta::new(subscription, false),
+            AccountMeta::new(*subscriber, true),
+            AccountMeta::new(*vault, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: E9thInstruction::CreateSubscription { subscription_id, terms }.pack(),
+    }
+}
+
+/// Creates a `CollectSubscription` instruction, paying into the merchant
+/// authority's associated token account
+pub fn collect_subscription(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    merchant_authority: &Pubkey,
+    subscriber: &Pubkey,
+    vault: &Pubkey,
+    subscription_id: u64,
+) -> Instruction {
+    let (merchant, subscription) =
+        subscription_address(program_id, mint, merchant_authority, subscriber, subscription_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(config_address(program_id, mint), false),
+            AccountMeta::new_readonly(merchant, false),
+            AccountMeta::new(subscription, false),
+            AccountMeta::new(*vault, false),
+            AccountMeta::new(get_associated_token_address(merchant_authority, mint), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: E9thInstruction::CollectSubscription.pack(),
+    }
+}
+
+/// Creates a `CancelSubscription` instruction
+pub fn cancel_subscription(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    subscriber: &Pubkey,
+    merchant_authority: &Pubkey,
+    vault: &Pubkey,
+    subscription_id: u64,
+) -> Instruction {
+    let (_, subscription) =
+        subscription_address(program_id, mint, merchant_authority, subscriber, subscription_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(subscription, false),
+            AccountMeta::new_readonly(*subscriber, true),
+            AccountMeta::new(*vault, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: E9thInstruction::CancelSubscription.pack(),
+    }
+}
+
+/// Creates a `CreateLaunch` instruction
+pub fn create_launch(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    operator: &Pubkey,
+    launch_id: u64,
+    terms: LaunchTerms,
+) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (launch, _) = LaunchProcessor::get_launch_pda(program_id, &config, launch_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(launch, false),
+            AccountMeta::new(*operator, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: E9thInstruction::CreateLaunch { launch_id, terms }.pack(),
+    }
+}
+
+/// Creates a `ReserveLaunchSlot` instruction for the wallet's associated
+/// token account
+pub fn reserve_launch_slot(program_id: &Pubkey, mint: &Pubkey, wallet: &Pubkey, launch_id: u64) -> Instruction {
+    let config = config_address(program_id, mint);
+    let (tier_config, _) = TierProcessor::get_tier_config_pda(program_id, &config);
+    let (launch, _) = LaunchProcessor::get_launch_pda(program_id, &config, launch_id);
+    let (stake_entry, _) = Processor::get_stake_entry_pda(program_id, &config, wallet);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new_readonly(tier_config, false),
+            AccountMeta::new(launch, false),
+            AccountMeta::new_readonly(*wallet, true),
+            AccountMeta::new_readonly(stake_entry, false),
+        ],
+        data: E9thInstruction::ReserveLaunchSlot.pack(),
+    }
+}
+
+/// Builders for the legacy [E9thTokenInstruction](enum.E9thTokenInstruction.html)
+/// family, which keeps its state in the `["program_state"]` PDA
+pub mod legacy {
+    use super::{config_address, program_state_address, E9thTokenInstruction};
+    use crate::stake::StakeProcessor;
+    use solana_program::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        system_program,
+    };
+    use spl_associated_token_account::get_associated_token_address;
+
+    fn stake_address(program_id: &Pubkey, user: &Pubkey) -> Pubkey {
+        StakeProcessor::get_stake_account_pda(program_id, user, &[]).unwrap().0
+    }
+
+    /// Creates a legacy `Initialize` instruction; the admin pays for the program state
+    pub fn initialize(
+        program_id: &Pubkey,
+        admin: &Pubkey,
+        mint: &Pubkey,
+        total_supply: u64,
+        reward_rate: u16,
+        min_stake_period: u64,
+        max_stake_period: u64,
+    ) -> Instruction {
+        Instruction {
+            program_id: *program_id,
+            accounts: vec![
+                AccountMeta::new(program_state_address(program_id), false),
+                AccountMeta::new(*admin, true),
+                AccountMeta::new(*mint, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ],
+            data: E9thTokenInstruction::Initialize {
                 total_supply,
                 reward_rate,
                 min_stake_period,
                 max_stake_period,
-            } => {
-                buf.push(0);
-                buf.extend_from_slice(&total_supply.to_le_bytes());
-                buf.extend_from_slice(&reward_rate.to_le_bytes());
-                buf.extend_from_slice(&min_stake_period.to_le_bytes());
-                buf.extend_from_slice(&max_stake_period.to_le_bytes());
-            }
-            Self::Mint { amount } => {
-                buf.push(1);
-                buf.extend_from_slice(&amount.to_le_bytes());
-            }
-            Self::Burn { amount } => {
-                buf.push(2);
-                buf.extend_from_slice(&amount.to_le_bytes());
-            }
-            Self::Stake { amount, period } => {
-                buf.push(3);
-                buf.extend_from_slice(&amount.to_le_bytes());
-                buf.extend_from_slice(&period.to_le_bytes());
-            }
-            Self::Unstake => {
-                buf.push(4);
-            }
-            Self::ClaimRewards => {
-                buf.push(5);
-            }
-            Self::UpdateSettings {
-                reward_rate,
-                min_stake_period,
-                max_stake_period,
-                staking_enabled,
-            } => {
-                buf.push(6);
-                if let Some(rate) = reward_rate {
-                    buf.push(0);
-                    buf.extend_from_slice(&rate.to_le_bytes());
-                }
-                if let Some(period) = min_stake_period {
-                    buf.push(1);
-                    buf.extend_from_slice(&period.to_le_bytes());
-                }
-                if let Some(period) = max_stake_period {
-                    buf.push(2);
-                    buf.extend_from_slice(&period.to_le_bytes());
-                }
-                if let Some(enabled) = staking_enabled {
-                    buf.push(3);
-                    buf.push(if *enabled { 1 } else { 0 });
-                }
-            }
-            Self::TransferAdmin { new_admin } => {
-                buf.push(7);
-                buf.extend_from_slice(new_admin.as_ref());
             }
+            .pack(),
         }
-        buf
     }
 
-    fn unpack_u8(input: &[u8]) -> Result<(u8, &[u8]), ProgramError> {
-        if input.is_empty() {
-            return Err(ProgramError::InvalidInstructionData);
+    /// Creates a legacy `Mint` instruction to `destination_owner`'s token account
+    pub fn mint(
+        program_id: &Pubkey,
+        admin: &Pubkey,
+        mint: &Pubkey,
+        destination_owner: &Pubkey,
+        amount: u64,
+    ) -> Instruction {
+        Instruction {
+            program_id: *program_id,
+            accounts: vec![
+                AccountMeta::new(program_state_address(program_id), false),
+                AccountMeta::new_readonly(*admin, true),
+                AccountMeta::new(*mint, false),
+                AccountMeta::new(get_associated_token_address(destination_owner, mint), false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ],
+            data: E9thTokenInstruction::Mint { amount }.pack(),
         }
-        Ok((input[0], &input[1..]))
     }
 
-    fn unpack_u16(input: &[u8]) -> Result<(u16, &[u8]), ProgramError> {
-        if input.len() < 2 {
-            return Err(ProgramError::InvalidInstructionData);
+    /// Creates a legacy `Burn` instruction from the admin's token account. The
+    /// token config is appended when `migrated`, as migrated states require it.
+    pub fn burn(program_id: &Pubkey, admin: &Pubkey, mint: &Pubkey, amount: u64, migrated: bool) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(program_state_address(program_id), false),
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*mint, false),
+            AccountMeta::new(get_associated_token_address(admin, mint), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ];
+        if migrated {
+            accounts.push(AccountMeta::new(config_address(program_id, mint), false));
+        }
+        Instruction {
+            program_id: *program_id,
+            accounts,
+            data: E9thTokenInstruction::Burn { amount }.pack(),
         }
-        let (bytes, rest) = input.split_at(2);
-        let value = u16::from_le_bytes([bytes[0], bytes[1]]);
-        Ok((value, rest))
     }
 
-    fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
-        if input.len() < 8 {
-            return Err(ProgramError::InvalidInstructionData);
+    /// Accounts shared by the legacy staking instructions
+    fn stake_accounts(program_id: &Pubkey, user: &Pubkey, mint: &Pubkey) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(program_state_address(program_id), false),
+            AccountMeta::new(stake_address(program_id, user), false),
+            AccountMeta::new_readonly(*user, true),
+            AccountMeta::new(get_associated_token_address(user, mint), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ]
+    }
+
+    /// Creates a legacy `Stake` instruction
+    pub fn stake(program_id: &Pubkey, user: &Pubkey, mint: &Pubkey, amount: u64, period: u64) -> Instruction {
+        Instruction {
+            program_id: *program_id,
+            accounts: stake_accounts(program_id, user, mint),
+            data: E9thTokenInstruction::Stake { amount, period }.pack(),
+        }
+    }
+
+    /// Creates a legacy `Unstake` instruction
+    pub fn unstake(program_id: &Pubkey, user: &Pubkey, mint: &Pubkey) -> Instruction {
+        Instruction {
+            program_id: *program_id,
+            accounts: stake_accounts(program_id, user, mint),
+            data: E9thTokenInstruction::Unstake.pack(),
+        }
+    }
+
+    /// Creates a legacy `ClaimRewards` instruction
+    pub fn claim_rewards(program_id: &Pubkey, user: &Pubkey, mint: &Pubkey) -> Instruction {
+        Instruction {
+            program_id: *program_id,
+            accounts: stake_accounts(program_id, user, mint),
+            data: E9thTokenInstruction::ClaimRewards.pack(),
+        }
+    }
+
+    /// Creates a legacy `UpdateSettings` instruction; `None` leaves a setting unchanged
+    pub fn update_settings(
+        program_id: &Pubkey,
+        admin: &Pubkey,
+        reward_rate: Option<u16>,
+        min_stake_period: Option<u64>,
+        max_stake_period: Option<u64>,
+        staking_enabled: Option<bool>,
+    ) -> Instruction {
+        Instruction {
+            program_id: *program_id,
+            accounts: vec![
+                AccountMeta::new(program_state_address(program_id), false),
+                AccountMeta::new_readonly(*admin, true),
+            ],
+            data: E9thTokenInstruction::UpdateSettings {
+                reward_rate,
+                min_stake_period,
+                max_stake_period,
+                staking_enabled,
+            }
+            .pack(),
+        }
+    }
+
+    /// Creates a legacy `TransferAdmin` instruction
+    pub fn transfer_admin(program_id: &Pubkey, admin: &Pubkey, new_admin: &Pubkey) -> Instruction {
+        Instruction {
+            program_id: *program_id,
+            accounts: vec![
+                AccountMeta::new(program_state_address(program_id), false),
+                AccountMeta::new_readonly(*admin, true),
+                AccountMeta::new_readonly(*new_admin, false),
+            ],
+            data: E9thTokenInstruction::TransferAdmin { new_admin: *new_admin }.pack(),
         }
-        let (bytes, rest) = input.split_at(8);
-        let value = u64::from_le_bytes([
-            bytes[0], bytes[1], bytes[2], bytes[3],
-            bytes[4], bytes[5], bytes[6], bytes[7],
-        ]);
-        Ok((value, rest))
     }
 }
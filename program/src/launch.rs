@@ -0,0 +1,179 @@
+//! Early-access launch reservations for the E9th Token Program
+//!
+//! The operator creates a launch with a capacity and a public opening time.
+//! Until it opens, wallets whose stake reaches the launch's minimum utility
+//! tier can reserve a slot. A [LaunchPriority::StakeWeight] launch ranks slots
+//! by stake, so a larger staker takes the lowest ranked slot of a full launch.
+//! Both the tier and the weight come from the wallet's stake entry, whose whole
+//! amount sits in the staking vault (enhanced `Stake` and `MigrateStake` both
+//! escrow it), so tokens can't be borrowed for the reservation or counted for
+//! several wallets; legacy stakes count for nothing until migrated. The launch
+//! account keeps its reservations in rank order, which partners read off chain.
+//! A reservation's weight is the stake when it was made and goes stale if the
+//! wallet unstakes later, so partners re-check the stake entry when they honor
+//! a slot.
+
+use crate::{
+    error::E9thTokenError,
+    events::{E9thEvent, LaunchSlotReserved},
+    processor::load_token_config,
+    state::{deserialize_account_data, serialize_account_data, Launch, LaunchPriority, LaunchTerms},
+    tier::TierProcessor,
+    validation::{check_system_program, AccountRules},
+};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+
+pub struct LaunchProcessor;
+
+impl LaunchProcessor {
+    /// Create a launch and its reservation queue (operator only)
+    pub fn process_create(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        launch_id: u64,
+        terms: LaunchTerms,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let launch_account = next_account_info(account_info_iter)?;
+        let operator_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        let config = load_token_config(program_id, config_account)?;
+        AccountRules::new().signer().writable().check(operator_account)?;
+        if *operator_account.key != config.operator {
+            return Err(E9thTokenError::InvalidAdmin.into());
+        }
+        check_system_program(system_program)?;
+        if terms.capacity == 0 || terms.capacity > Launch::MAX_CAPACITY {
+            return Err(E9thTokenError::InvalidLaunchTerms.into());
+        }
+
+        let (launch_pda, bump) = Self::get_launch_pda(program_id, config_account.key, launch_id);
+        AccountRules::new().writable().address(launch_pda).check(launch_account)?;
+        if !launch_account.data_is_empty() {
+            return Err(E9thTokenError::AccountAlreadyInitialized.into());
+        }
+
+        let now = Clock::get()?.unix_timestamp as u64;
+        if terms.open_timestamp <= now {
+            return Err(E9thTokenError::InvalidLaunchTerms.into());
+        }
+
+        let space = Launch::space(terms.capacity);
+        let lamports = Rent::get()?.minimum_balance(space);
+        invoke_signed(
+            &system_instruction::create_account(
+                operator_account.key,
+                launch_account.key,
+                lamports,
+                space as u64,
+                program_id,
+            ),
+            &[
+                operator_account.clone(),
+                launch_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"launch",
+                config_account.key.as_ref(),
+                &launch_id.to_le_bytes(),
+                &[bump],
+            ]],
+        )?;
+        serialize_account_data(launch_account, &Launch::new(*config_account.key, launch_id, terms, bump))?;
+
+        msg!(
+            "Launch {} created: {} slots ({:?}, min {:?}), opens at {}",
+            launch_id,
+            terms.capacity,
+            terms.priority,
+            terms.min_tier,
+            terms.open_timestamp
+        );
+        Ok(())
+    }
+
+    /// Reserve an early-access slot before the launch opens
+    pub fn process_reserve(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let tier_config_account = next_account_info(account_info_iter)?;
+        let launch_account = next_account_info(account_info_iter)?;
+        let wallet_account = next_account_info(account_info_iter)?;
+        let stake_account = next_account_info(account_info_iter)?;
+
+        load_token_config(program_id, config_account)?;
+        AccountRules::new().signer().check(wallet_account)?;
+        let mut launch = Self::load_launch(program_id, config_account.key, launch_account)?;
+
+        // Only escrowed stake counts: a wallet balance could be borrowed for the reservation
+        let staked = TierProcessor::staked_amount(program_id, config_account, wallet_account, stake_account)?;
+        let tier = TierProcessor::tier_or_none(program_id, config_account.key, tier_config_account, staked)?;
+        if tier < launch.terms.min_tier {
+            return Err(E9thTokenError::TierTooLow.into());
+        }
+        let weight = match launch.terms.priority {
+            LaunchPriority::FirstCome => 0,
+            LaunchPriority::StakeWeight => staked,
+        };
+
+        if Clock::get()?.unix_timestamp as u64 >= launch.terms.open_timestamp {
+            return Err(E9thTokenError::LaunchAlreadyOpen.into());
+        }
+
+        let (position, bumped) = launch.reserve(*wallet_account.key, weight)?;
+        serialize_account_data(launch_account, &launch)?;
+
+        E9thEvent::LaunchSlotReserved(LaunchSlotReserved {
+            launch: *launch_account.key,
+            wallet: *wallet_account.key,
+            weight,
+            position: position as u32,
+            bumped,
+        })
+        .emit();
+        Ok(())
+    }
+
+    /// Load a launch of `config`, checking its address
+    pub fn load_launch(
+        program_id: &Pubkey,
+        config: &Pubkey,
+        launch_account: &AccountInfo,
+    ) -> Result<Launch, ProgramError> {
+        AccountRules::new().writable().owned_by(program_id).check(launch_account)?;
+        let launch: Launch = deserialize_account_data(launch_account)?;
+        let expected = Pubkey::create_program_address(
+            &[
+                b"launch",
+                launch.config.as_ref(),
+                &launch.launch_id.to_le_bytes(),
+                &[launch.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| E9thTokenError::UnexpectedAccount)?;
+        if expected != *launch_account.key || launch.config != *config {
+            return Err(E9thTokenError::UnexpectedAccount.into());
+        }
+        Ok(launch)
+    }
+
+    /// Get a launch PDA
+    pub fn get_launch_pda(program_id: &Pubkey, config: &Pubkey, launch_id: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"launch", config.as_ref(), &launch_id.to_le_bytes()], program_id)
+    }
+}
@@ -3,11 +3,32 @@
 
 pub mod entrypoint;
 pub mod error;
+pub mod events;
 pub mod instruction;
 pub mod processor;
 pub mod state;
 pub mod admin;
 pub mod stake;
+pub mod airdrop;
+pub mod merkle;
+pub mod sale;
+pub mod migrate;
+pub mod tier;
+pub mod membership;
+pub mod merchant;
+pub mod voucher;
+pub mod payment;
+pub mod subscription;
+pub mod launch;
+pub mod validation;
+pub mod zero_copy;
+// Off-chain tooling, left out of the program binary
+#[cfg(not(target_os = "solana"))]
+pub mod idl;
+#[cfg(not(target_os = "solana"))]
+pub mod indexer;
+#[cfg(not(target_os = "solana"))]
+pub mod snapshot;
 
 // Re-export key types for easier access
 pub use error::E9thTokenError;
@@ -0,0 +1,118 @@
+//! Membership passes for the E9th Token Program
+//!
+//! A [MembershipPass] is a PDA record per holder and config, so it can't be
+//! transferred. Partners gate access on the holder's pass with one account
+//! lookup ([MembershipProcessor::load_pass_tier]) instead of reading balances
+//! and stakes. The pass follows the holder's escrowed stake entry: enhanced
+//! `Stake` and `MigrateStake` issue or raise it when their membership accounts
+//! are passed, and enhanced `Unstake` always takes the pass and lowers it.
+//! Legacy stakes never move tokens, so they don't touch passes.
+
+use crate::{
+    events::{E9thEvent, MembershipChanged},
+    state::{deserialize_account_data, serialize_account_data, MembershipPass, UtilityTier},
+    tier::TierProcessor,
+    validation::{check_system_program, AccountRules},
+};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+
+pub struct MembershipProcessor;
+
+impl MembershipProcessor {
+    /// Bring `holder`'s pass in line with its escrowed stake of `staked`. A
+    /// missing pass is issued once the stake reaches Bronze, paid by `payer`
+    /// through the system program; without a payer (unstaking, where the tier
+    /// can only drop) a missing pass stays missing. The tier config may be
+    /// uninitialized, which reads as [UtilityTier::None].
+    pub fn sync_pass<'a>(
+        program_id: &Pubkey,
+        config: &Pubkey,
+        holder: &Pubkey,
+        staked: u64,
+        tier_config_account: &AccountInfo<'a>,
+        pass_account: &AccountInfo<'a>,
+        payer: Option<(&AccountInfo<'a>, &AccountInfo<'a>)>,
+    ) -> ProgramResult {
+        let tier = TierProcessor::tier_or_none(program_id, config, tier_config_account, staked)?;
+        let (pass_pda, bump) = Self::get_membership_pass_pda(program_id, config, holder);
+        AccountRules::new().writable().address(pass_pda).check(pass_account)?;
+
+        if pass_account.data_is_empty() {
+            let (payer_account, system_program) = match payer {
+                Some(payer) if tier != UtilityTier::None => payer,
+                _ => return Ok(()),
+            };
+            AccountRules::new().signer().writable().check(payer_account)?;
+            check_system_program(system_program)?;
+            let lamports = Rent::get()?.minimum_balance(MembershipPass::LEN);
+            invoke_signed(
+                &system_instruction::create_account(
+                    payer_account.key,
+                    pass_account.key,
+                    lamports,
+                    MembershipPass::LEN as u64,
+                    program_id,
+                ),
+                &[
+                    payer_account.clone(),
+                    pass_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[b"membership", config.as_ref(), holder.as_ref(), &[bump]]],
+            )?;
+        } else {
+            // Existing passes must be current before they are rewritten
+            AccountRules::new().owned_by(program_id).check(pass_account)?;
+            let pass: MembershipPass = deserialize_account_data(pass_account)?;
+            if pass.tier == tier && pass.staked == staked {
+                return Ok(());
+            }
+        }
+
+        Self::write_pass(pass_account, &MembershipPass::new(*config, *holder, tier, staked, bump))
+    }
+
+    /// Tier on `holder`'s pass for `config`, for partner programs. The pass
+    /// account must be the holder's PDA; a pass that was never issued reads as
+    /// [UtilityTier::None].
+    pub fn load_pass_tier(
+        program_id: &Pubkey,
+        config: &Pubkey,
+        holder: &Pubkey,
+        pass_account: &AccountInfo,
+    ) -> Result<UtilityTier, ProgramError> {
+        let (pass_pda, _) = Self::get_membership_pass_pda(program_id, config, holder);
+        AccountRules::new().address(pass_pda).check(pass_account)?;
+        if pass_account.data_is_empty() {
+            return Ok(UtilityTier::None);
+        }
+        AccountRules::new().owned_by(program_id).check(pass_account)?;
+        let pass: MembershipPass = deserialize_account_data(pass_account)?;
+        Ok(pass.tier)
+    }
+
+    /// Get a holder's membership pass PDA for a token config
+    pub fn get_membership_pass_pda(program_id: &Pubkey, config: &Pubkey, holder: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"membership", config.as_ref(), holder.as_ref()], program_id)
+    }
+
+    fn write_pass(pass_account: &AccountInfo, pass: &MembershipPass) -> ProgramResult {
+        serialize_account_data(pass_account, pass)?;
+        E9thEvent::MembershipChanged(MembershipChanged {
+            holder: pass.holder,
+            tier: pass.tier,
+            staked: pass.staked,
+        })
+        .emit();
+        Ok(())
+    }
+}
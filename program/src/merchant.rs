@@ -0,0 +1,177 @@
+//! Merchant registry for the E9th Token Program
+//!
+//! The operator registers the merchants holders can spend E9TH with, together
+//! with their voucher and payment terms. Each merchant is a PDA of the config and the
+//! merchant's signing authority.
+
+use crate::{
+    error::E9thTokenError,
+    processor::load_token_config,
+    state::{deserialize_account_data, serialize_account_data, Merchant, PaymentTerms, VoucherMode},
+    validation::{check_system_program, AccountRules},
+};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+
+pub struct MerchantProcessor;
+
+impl MerchantProcessor {
+    /// Register a merchant or update its voucher terms (operator only)
+    pub fn process_register(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        voucher_cost: u64,
+        voucher_mode: VoucherMode,
+        active: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let merchant_account = next_account_info(account_info_iter)?;
+        let authority_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let operator_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        let config = load_token_config(program_id, config_account)?;
+        AccountRules::new().signer().writable().check(operator_account)?;
+        if *operator_account.key != config.operator {
+            return Err(E9thTokenError::InvalidAdmin.into());
+        }
+        check_system_program(system_program)?;
+        if voucher_cost == 0 {
+            return Err(E9thTokenError::InvalidAmount.into());
+        }
+
+        let (merchant_pda, bump) =
+            Self::get_merchant_pda(program_id, config_account.key, authority_account.key);
+        AccountRules::new().writable().address(merchant_pda).check(merchant_account)?;
+        AccountRules::new()
+            .token_account(&config.mint)
+            .token_authority(&merchant_pda)
+            .check(vault_account)?;
+
+        let merchant = if merchant_account.data_is_empty() {
+            let lamports = Rent::get()?.minimum_balance(Merchant::LEN);
+            invoke_signed(
+                &system_instruction::create_account(
+                    operator_account.key,
+                    merchant_account.key,
+                    lamports,
+                    Merchant::LEN as u64,
+                    program_id,
+                ),
+                &[
+                    operator_account.clone(),
+                    merchant_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[
+                    b"merchant",
+                    config_account.key.as_ref(),
+                    authority_account.key.as_ref(),
+                    &[bump],
+                ]],
+            )?;
+            Merchant {
+                active,
+                ..Merchant::new(
+                    *config_account.key,
+                    *authority_account.key,
+                    *vault_account.key,
+                    voucher_cost,
+                    voucher_mode,
+                    bump,
+                )
+            }
+        } else {
+            AccountRules::new().owned_by(program_id).check(merchant_account)?;
+            let mut merchant: Merchant = deserialize_account_data(merchant_account)?;
+            // Locked vouchers are returned from the vault they were locked in
+            if merchant.vault != *vault_account.key && merchant.vouchers_issued > merchant.vouchers_redeemed {
+                return Err(E9thTokenError::UnexpectedAccount.into());
+            }
+            merchant.vault = *vault_account.key;
+            merchant.voucher_cost = voucher_cost;
+            merchant.voucher_mode = voucher_mode;
+            merchant.active = active;
+            merchant
+        };
+        serialize_account_data(merchant_account, &merchant)?;
+
+        msg!(
+            "Merchant {} registered: voucher cost {} ({:?}), active {}",
+            authority_account.key,
+            voucher_cost,
+            voucher_mode,
+            active
+        );
+        Ok(())
+    }
+
+    /// Set a merchant's `Pay` discounts and burn share (operator only)
+    pub fn process_set_payment_terms(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        terms: PaymentTerms,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let merchant_account = next_account_info(account_info_iter)?;
+        let operator_account = next_account_info(account_info_iter)?;
+
+        let config = load_token_config(program_id, config_account)?;
+        AccountRules::new().signer().check(operator_account)?;
+        if *operator_account.key != config.operator {
+            return Err(E9thTokenError::InvalidAdmin.into());
+        }
+        if !terms.is_valid() {
+            return Err(E9thTokenError::InvalidPaymentTerms.into());
+        }
+
+        AccountRules::new().writable().check(merchant_account)?;
+        let mut merchant = Self::load_merchant(program_id, config_account.key, merchant_account)?;
+        merchant.payment_terms = terms;
+        serialize_account_data(merchant_account, &merchant)?;
+
+        msg!("Merchant {} payment terms: {:?}", merchant.authority, terms);
+        Ok(())
+    }
+
+    /// Load a merchant registered with `config`, checking its address
+    pub fn load_merchant(
+        program_id: &Pubkey,
+        config: &Pubkey,
+        merchant_account: &AccountInfo,
+    ) -> Result<Merchant, ProgramError> {
+        AccountRules::new().owned_by(program_id).check(merchant_account)?;
+        let merchant: Merchant = deserialize_account_data(merchant_account)?;
+        let expected = Pubkey::create_program_address(
+            &[
+                b"merchant",
+                config.as_ref(),
+                merchant.authority.as_ref(),
+                &[merchant.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| E9thTokenError::UnexpectedAccount)?;
+        if expected != *merchant_account.key || merchant.config != *config {
+            return Err(E9thTokenError::UnexpectedAccount.into());
+        }
+        Ok(merchant)
+    }
+
+    /// Get a merchant's PDA for a token config
+    pub fn get_merchant_pda(program_id: &Pubkey, config: &Pubkey, authority: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"merchant", config.as_ref(), authority.as_ref()], program_id)
+    }
+}
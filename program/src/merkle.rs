@@ -0,0 +1,139 @@
+//! Merkle proof helpers for airdrop rounds
+
+use solana_program::{keccak, pubkey::Pubkey};
+use std::{collections::HashMap, str::FromStr};
+
+/// Domain separator for leaf hashes
+pub const LEAF_PREFIX: &[u8] = &[0];
+/// Domain separator for interior node hashes
+pub const NODE_PREFIX: &[u8] = &[1];
+/// Maximum proof length accepted on chain (supports 2^32 recipients)
+pub const MAX_PROOF_LEN: usize = 32;
+
+/// Hash an airdrop leaf: `keccak(0x00 || index || claimant || amount)`
+pub fn leaf_hash(index: u64, claimant: &Pubkey, amount: u64) -> [u8; 32] {
+    keccak::hashv(&[
+        LEAF_PREFIX,
+        &index.to_le_bytes(),
+        claimant.as_ref(),
+        &amount.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Hash two child nodes. Children are sorted so proofs don't need direction bits.
+pub fn node_hash(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    keccak::hashv(&[NODE_PREFIX, left, right]).to_bytes()
+}
+
+/// Verify that `leaf` is included under `root`
+pub fn verify_proof(proof: &[[u8; 32]], root: &[u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |hash, sibling| node_hash(&hash, sibling));
+    computed == *root
+}
+
+/// Merkle tree over airdrop leaves, used off chain to publish roots and proofs
+pub struct MerkleTree {
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    /// Build a tree from leaf hashes. An unpaired node is promoted to the next layer.
+    pub fn new(leaves: Vec<[u8; 32]>) -> Self {
+        let mut layers = vec![leaves];
+        while layers.last().is_some_and(|layer| layer.len() > 1) {
+            let next = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(next);
+        }
+        Self { layers }
+    }
+
+    /// Root hash, or all zeroes for an empty tree
+    pub fn root(&self) -> [u8; 32] {
+        self.layers
+            .last()
+            .and_then(|layer| layer.first())
+            .copied()
+            .unwrap_or([0u8; 32])
+    }
+
+    /// Sibling hashes from the leaf at `index` up to the root
+    pub fn proof(&self, index: usize) -> Vec<[u8; 32]> {
+        let mut proof = Vec::new();
+        let mut position = index;
+        for layer in &self.layers[..self.layers.len().saturating_sub(1)] {
+            if let Some(sibling) = layer.get(position ^ 1) {
+                proof.push(*sibling);
+            }
+            position /= 2;
+        }
+        proof
+    }
+}
+
+/// Recipients parsed from an airdrop CSV
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecipientList {
+    /// `(wallet, amount)` pairs in first-seen order
+    pub recipients: Vec<(Pubkey, u64)>,
+    /// `(line number, wallet)` of each duplicate line merged into an earlier one
+    pub merged: Vec<(usize, Pubkey)>,
+}
+
+/// Parse a recipients CSV of `wallet,amount` lines, keeping first-seen order.
+/// Blank lines, `#` comments and a leading `wallet,amount` header are skipped.
+/// Duplicate wallets are merged by summing their amounts and listed in
+/// `merged` so callers can report them.
+pub fn parse_recipients(csv: &str) -> Result<RecipientList, String> {
+    let mut recipients: Vec<(Pubkey, u64)> = Vec::new();
+    let mut positions: HashMap<Pubkey, usize> = HashMap::new();
+    let mut merged = Vec::new();
+
+    for (line_no, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if line_no == 0 && fields.first() == Some(&"wallet") {
+            continue;
+        }
+        if fields.len() != 2 {
+            return Err(format!("line {}: expected `wallet,amount`", line_no + 1));
+        }
+
+        let wallet = Pubkey::from_str(fields[0])
+            .map_err(|_| format!("line {}: invalid wallet `{}`", line_no + 1, fields[0]))?;
+        let amount = u64::from_str(fields[1])
+            .map_err(|_| format!("line {}: invalid amount `{}`", line_no + 1, fields[1]))?;
+        if amount == 0 {
+            return Err(format!("line {}: amount must be greater than zero", line_no + 1));
+        }
+
+        match positions.get(&wallet) {
+            Some(&position) => {
+                merged.push((line_no + 1, wallet));
+                let entry = &mut recipients[position].1;
+                *entry = entry
+                    .checked_add(amount)
+                    .ok_or_else(|| format!("line {}: amount overflows u64", line_no + 1))?;
+            }
+            None => {
+                positions.insert(wallet, recipients.len());
+                recipients.push((wallet, amount));
+            }
+        }
+    }
+
+    Ok(RecipientList { recipients, merged })
+}
@@ -0,0 +1,410 @@
+//! Account layout migrations for the E9th Token Program
+
+use crate::{
+    error::E9thTokenError,
+    events::{ConfigChanged, E9thEvent},
+    membership::MembershipProcessor,
+    processor::{borrow_token_config, borrow_token_config_mut, load_program_state, program_state_rules, Processor},
+    state::{
+        deserialize_account_data, serialize_account_data, AccountHeader, AccountState,
+        AccountType, Blacklist, ProgramState, StakeAccount, StakeEntry, TokenConfig,
+    },
+    validation::{check_system_program, check_token_program, AccountRules},
+};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+use spl_token::instruction::transfer;
+
+pub struct MigrateProcessor;
+
+impl MigrateProcessor {
+    /// Rewrite an account stored in layout `from_version` in the current layout,
+    /// growing it when the current layout is larger
+    pub fn process_migrate_account(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        account_type: AccountType,
+        from_version: u8,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let account = next_account_info(account_info_iter)?;
+        let authority_source = next_account_info(account_info_iter)?;
+        let authority = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        AccountRules::new().writable().owned_by(program_id).check(account)?;
+        AccountRules::new().owned_by(program_id).check(authority_source)?;
+        AccountRules::new().signer().writable().check(authority)?;
+        check_system_program(system_program)?;
+
+        let accounts = MigrationAccounts { account, authority_source, authority, system_program };
+        match account_type {
+            AccountType::TokenConfig => {
+                // The config authorizes its own migration
+                let config: TokenConfig = Self::read_old(account, account_type, from_version)?;
+                AccountRules::new()
+                    .seeds(&[b"config", config.mint.as_ref()], program_id)
+                    .check(account)?;
+                Self::check_authority(authority, &config.owner)?;
+                Self::write_current(&accounts, &config)
+            }
+            AccountType::ProgramState => {
+                program_state_rules(program_id).check(account)?;
+                let state: ProgramState = Self::read_old(account, account_type, from_version)?;
+                Self::check_authority(authority, &state.admin)?;
+                Self::write_current(&accounts, &state)
+            }
+            AccountType::Blacklist => {
+                Self::migrate_with_config(program_id, &accounts, from_version, |_: &Blacklist, config| {
+                    Ok(Processor::get_blacklist_pda(program_id, config).0)
+                })
+            }
+            AccountType::StakeEntry => {
+                Self::migrate_with_config(program_id, &accounts, from_version, |entry: &StakeEntry, config| {
+                    Ok(Processor::get_stake_entry_pda(program_id, config, &entry.staker).0)
+                })
+            }
+            AccountType::StakeAccount => {
+                // Legacy stakes are authorized by the (already migrated) program state
+                let state = load_program_state(program_id, authority_source)?;
+                Self::check_authority(authority, &state.admin)?;
+                let stake: StakeAccount = Self::read_old(account, account_type, from_version)?;
+                AccountRules::new()
+                    .seeds(&[b"stake", stake.owner.as_ref()], program_id)
+                    .check(account)?;
+                Self::write_current(&accounts, &stake)
+            }
+            AccountType::Uninitialized => Err(E9thTokenError::AccountTypeMismatch.into()),
+            // Every other type was created with a header in its current layout
+            _ => Err(E9thTokenError::UnsupportedAccountVersion.into()),
+        }
+    }
+
+    /// Convert the legacy program state into a token config for its mint (admin only)
+    pub fn process_migrate_program_state(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        operator: Pubkey,
+        treasury: Pubkey,
+        burn_rate_basis_points: u16,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let program_state_account = next_account_info(account_info_iter)?;
+        let config_account = next_account_info(account_info_iter)?;
+        let admin_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        program_state_rules(program_id).writable().check(program_state_account)?;
+        let mut state = load_program_state(program_id, program_state_account)?;
+        AccountRules::new().signer().writable().check(admin_account)?;
+        Self::check_authority(admin_account, &state.admin)?;
+        check_system_program(system_program)?;
+        if burn_rate_basis_points > 10_000 {
+            return Err(E9thTokenError::InvalidAmount.into());
+        }
+
+        let (config_pda, bump) = Processor::get_token_config_pda(program_id, &state.mint);
+        AccountRules::new().writable().address(config_pda).check(config_account)?;
+        if !config_account.data_is_empty() {
+            return Err(E9thTokenError::AccountAlreadyInitialized.into());
+        }
+
+        let lamports = Rent::get()?.minimum_balance(TokenConfig::LEN);
+        invoke_signed(
+            &system_instruction::create_account(
+                admin_account.key,
+                config_account.key,
+                lamports,
+                TokenConfig::LEN as u64,
+                program_id,
+            ),
+            &[
+                admin_account.clone(),
+                config_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[b"config", state.mint.as_ref(), &[bump]]],
+        )?;
+
+        let config =
+            TokenConfig::from_program_state(&state, operator, treasury, burn_rate_basis_points, bump);
+        serialize_account_data(config_account, &config)?;
+        state.migrated = true;
+        serialize_account_data(program_state_account, &state)?;
+
+        msg!("Migrated program state to token config {}", config_pda);
+        Ok(())
+    }
+
+    /// Convert the staker's mature legacy stake into a stake entry, closing the
+    /// legacy account and moving its amount into the config's total. Legacy
+    /// stakes never left the staker's wallet, so the amount is moved into the
+    /// staking vault here.
+    pub fn process_migrate_stake(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let program_state_account = next_account_info(account_info_iter)?;
+        let config_account = next_account_info(account_info_iter)?;
+        let stake_account = next_account_info(account_info_iter)?;
+        let stake_entry_account = next_account_info(account_info_iter)?;
+        let staker_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let staker_token_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        program_state_rules(program_id).writable().check(program_state_account)?;
+        let mut state = load_program_state(program_id, program_state_account)?;
+        let mut config = borrow_token_config_mut(program_id, config_account)?;
+        if config.mint != state.mint {
+            return Err(E9thTokenError::InvalidMint.into());
+        }
+        AccountRules::new().signer().writable().check(staker_account)?;
+        AccountRules::new()
+            .writable()
+            .owned_by(program_id)
+            .seeds(&[b"stake", staker_account.key.as_ref()], program_id)
+            .check(stake_account)?;
+        check_system_program(system_program)?;
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(staker_account.key)
+            .check(staker_token_account)?;
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(config_account.key)
+            .check(vault_account)?;
+        check_token_program(token_program)?;
+
+        let stake: StakeAccount = deserialize_account_data(stake_account)?;
+        if stake.owner != *staker_account.key {
+            return Err(E9thTokenError::Unauthorized.into());
+        }
+        if stake.amount == 0 {
+            return Err(E9thTokenError::InvalidAmount.into());
+        }
+        // Legacy rewards only exist at maturity; migrating earlier would forfeit them
+        let clock = Clock::get()?;
+        let current_epoch = clock.epoch;
+        if !stake.is_mature(current_epoch) {
+            return Err(E9thTokenError::StakeNotMature.into());
+        }
+
+        let (entry_pda, bump) =
+            Processor::get_stake_entry_pda(program_id, config_account.key, staker_account.key);
+        AccountRules::new().writable().address(entry_pda).check(stake_entry_account)?;
+        if !stake_entry_account.data_is_empty() {
+            return Err(E9thTokenError::AccountAlreadyInitialized.into());
+        }
+
+        let lamports = Rent::get()?.minimum_balance(StakeEntry::LEN);
+        invoke_signed(
+            &system_instruction::create_account(
+                staker_account.key,
+                stake_entry_account.key,
+                lamports,
+                StakeEntry::LEN as u64,
+                program_id,
+            ),
+            &[
+                staker_account.clone(),
+                stake_entry_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"stake_entry",
+                config_account.key.as_ref(),
+                staker_account.key.as_ref(),
+                &[bump],
+            ]],
+        )?;
+
+        // The staker's token account must still hold the legacy amount
+        invoke(
+            &transfer(
+                token_program.key,
+                staker_token_account.key,
+                vault_account.key,
+                staker_account.key,
+                &[],
+                stake.amount,
+            )?,
+            &[
+                staker_token_account.clone(),
+                vault_account.clone(),
+                staker_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        let now = clock.unix_timestamp as u64;
+        let entry = StakeEntry::from_legacy(&stake, current_epoch, now, state.reward_rate, bump);
+        serialize_account_data(stake_entry_account, &entry)?;
+
+        state.total_staked = state.total_staked.saturating_sub(stake.amount);
+        serialize_account_data(program_state_account, &state)?;
+        config.total_staked = u64::from(config.total_staked).saturating_add(stake.amount).into();
+
+        // Close the legacy stake so it can't be migrated twice
+        let stake_lamports = stake_account.lamports();
+        **stake_account.try_borrow_mut_lamports()? = 0;
+        **staker_account.try_borrow_mut_lamports()? += stake_lamports;
+        stake_account.try_borrow_mut_data()?.fill(0);
+
+        msg!(
+            "Migrated legacy stake of {} tokens with {} carried rewards",
+            stake.amount,
+            entry.carried_rewards
+        );
+
+        // The stake is escrowed now, so it can back a membership pass
+        if let Some(tier_config_account) = account_info_iter.next() {
+            let pass_account = next_account_info(account_info_iter)?;
+            MembershipProcessor::sync_pass(
+                program_id,
+                config_account.key,
+                staker_account.key,
+                entry.amount,
+                tier_config_account,
+                pass_account,
+                Some((staker_account, system_program)),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Enable or disable legacy instructions (config owner only)
+    pub fn process_set_legacy_disabled(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        disabled: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let program_state_account = next_account_info(account_info_iter)?;
+        let owner_account = next_account_info(account_info_iter)?;
+
+        let mut config = borrow_token_config_mut(program_id, config_account)?;
+        AccountRules::new().signer().check(owner_account)?;
+        Self::check_authority(owner_account, &config.owner)?;
+
+        program_state_rules(program_id).writable().check(program_state_account)?;
+        let mut state = load_program_state(program_id, program_state_account)?;
+        if state.mint != config.mint {
+            return Err(E9thTokenError::InvalidMint.into());
+        }
+
+        // Legacy instructions never see the config, so the flag is mirrored
+        // into the program state they all load
+        config.legacy_disabled = disabled.into();
+        state.legacy_disabled = disabled;
+        serialize_account_data(program_state_account, &state)?;
+
+        E9thEvent::ConfigChanged(ConfigChanged {
+            authority: *owner_account.key,
+            admin: config.owner,
+            staking_enabled: config.staking_enabled.into(),
+            reward_rate: config.reward_rate.into(),
+            min_stake_period: config.min_stake_period.into(),
+            max_stake_period: config.max_stake_period.into(),
+            legacy_disabled: disabled,
+        })
+        .emit();
+        Ok(())
+    }
+
+    /// Migrate an enhanced account authorized by the (already migrated) token
+    /// config. `address` derives the account's PDA under the config from the
+    /// decoded value.
+    fn migrate_with_config<T: AccountState>(
+        program_id: &Pubkey,
+        accounts: &MigrationAccounts,
+        from_version: u8,
+        address: impl FnOnce(&T, &Pubkey) -> Result<Pubkey, ProgramError>,
+    ) -> ProgramResult {
+        let owner = borrow_token_config(program_id, accounts.authority_source)?.owner;
+        Self::check_authority(accounts.authority, &owner)?;
+        let value: T = Self::read_old(accounts.account, T::ACCOUNT_TYPE, from_version)?;
+        // The seeds differ per account type, so this also rejects another
+        // type's headerless data decoded as `T`
+        let expected = address(&value, accounts.authority_source.key)?;
+        AccountRules::new().address(expected).check(accounts.account)?;
+        Self::write_current(accounts, &value)
+    }
+
+    /// Decode an account stored in layout `from_version`
+    fn read_old<T: AccountState>(
+        account: &AccountInfo,
+        account_type: AccountType,
+        from_version: u8,
+    ) -> Result<T, ProgramError> {
+        if from_version >= T::VERSION {
+            return Err(E9thTokenError::UnsupportedAccountVersion.into());
+        }
+        let data = account.try_borrow_data()?;
+        let body = if from_version == 0 {
+            // The original layouts have no header
+            &data[..]
+        } else {
+            let header = AccountHeader::read(&data).ok_or(ProgramError::InvalidAccountData)?;
+            if header.account_type != account_type {
+                return Err(E9thTokenError::AccountTypeMismatch.into());
+            }
+            if header.version != from_version {
+                return Err(E9thTokenError::UnsupportedAccountVersion.into());
+            }
+            &data[AccountHeader::LEN..]
+        };
+        T::upgrade(from_version, body)
+    }
+
+    /// Grow the account if needed, then store `value` in the current layout
+    fn write_current<T: AccountState>(accounts: &MigrationAccounts, value: &T) -> ProgramResult {
+        let account = accounts.account;
+        let new_len = value.account_len().max(account.data_len());
+        if new_len > account.data_len() {
+            let shortfall = Rent::get()?.minimum_balance(new_len).saturating_sub(account.lamports());
+            if shortfall > 0 {
+                invoke(
+                    &system_instruction::transfer(accounts.authority.key, account.key, shortfall),
+                    &[
+                        accounts.authority.clone(),
+                        account.clone(),
+                        accounts.system_program.clone(),
+                    ],
+                )?;
+            }
+            account.realloc(new_len, true)?;
+        }
+
+        serialize_account_data(account, value)?;
+        msg!("Migrated {:?} account to version {}", T::ACCOUNT_TYPE, T::VERSION);
+        Ok(())
+    }
+
+    fn check_authority(authority: &AccountInfo, expected: &Pubkey) -> ProgramResult {
+        if *authority.key != *expected {
+            return Err(E9thTokenError::InvalidAdmin.into());
+        }
+        Ok(())
+    }
+}
+
+/// Accounts shared by every migration path
+struct MigrationAccounts<'a, 'b> {
+    account: &'a AccountInfo<'b>,
+    authority_source: &'a AccountInfo<'b>,
+    authority: &'a AccountInfo<'b>,
+    system_program: &'a AccountInfo<'b>,
+}
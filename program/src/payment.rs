@@ -0,0 +1,165 @@
+//! Merchant payments for the E9th Token Program
+//!
+//! `Pay` charges a holder a merchant's list price less the discount for the
+//! utility tier of the holder's staked E9TH, burns the merchant's burn share of it and transfers
+//! the rest to the merchant. The order reference is logged as an SPL memo and
+//! carried in the [PaymentReceived] receipt event.
+
+use crate::{
+    error::E9thTokenError,
+    events::{Burned, E9thEvent, PaymentReceived},
+    merchant::MerchantProcessor,
+    processor::{borrow_token_config_mut, load_token_config, record_burn, Processor},
+    state::BurnKind,
+    tier::TierProcessor,
+    validation::{check_token_program, AccountRules},
+};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke,
+    pubkey::Pubkey,
+};
+use spl_token::instruction::{burn, transfer};
+
+/// Maximum length of an order reference, in bytes
+pub const MAX_ORDER_REFERENCE_LEN: usize = 64;
+
+pub struct PaymentProcessor;
+
+impl PaymentProcessor {
+    /// Pay a merchant `amount` (list price) with the holder's tier discount
+    pub fn process_pay(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+        order_reference: String,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let blacklist_account = next_account_info(account_info_iter)?;
+        let tier_config_account = next_account_info(account_info_iter)?;
+        let merchant_account = next_account_info(account_info_iter)?;
+        let payer_account = next_account_info(account_info_iter)?;
+        let payer_token_account = next_account_info(account_info_iter)?;
+        let stake_account = next_account_info(account_info_iter)?;
+        let merchant_token_account = next_account_info(account_info_iter)?;
+        let mint_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let memo_program = next_account_info(account_info_iter)?;
+
+        let config = load_token_config(program_id, config_account)?;
+        if config.is_paused {
+            return Err(E9thTokenError::TokenPaused.into());
+        }
+        if amount == 0 {
+            return Err(E9thTokenError::InvalidAmount.into());
+        }
+        if order_reference.is_empty() || order_reference.len() > MAX_ORDER_REFERENCE_LEN {
+            return Err(E9thTokenError::InvalidOrderReference.into());
+        }
+        AccountRules::new().signer().check(payer_account)?;
+        check_token_program(token_program)?;
+        AccountRules::new().address(spl_memo::id()).check(memo_program)?;
+
+        let merchant = MerchantProcessor::load_merchant(program_id, config_account.key, merchant_account)?;
+        if !merchant.active {
+            return Err(E9thTokenError::MerchantInactive.into());
+        }
+        let blacklist = Processor::load_blacklist(program_id, config_account.key, blacklist_account)?;
+        if blacklist.is_blacklisted(payer_account.key) || blacklist.is_blacklisted(&merchant.authority) {
+            return Err(E9thTokenError::AccountBlacklisted.into());
+        }
+
+        // Only escrowed stake counts: a wallet balance could be borrowed for the payment
+        let staked = TierProcessor::staked_amount(program_id, config_account, payer_account, stake_account)?;
+        // No tiers set yet: nobody gets a discount
+        let tier = TierProcessor::tier_or_none(program_id, config_account.key, tier_config_account, staked)?;
+        let quote = merchant.payment_terms.quote(amount, tier);
+
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(payer_account.key)
+            .check(payer_token_account)?;
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(&merchant.authority)
+            .check(merchant_token_account)?;
+        AccountRules::new().writable().mint(&config.mint).check(mint_account)?;
+
+        invoke(
+            &spl_memo::build_memo(order_reference.as_bytes(), &[payer_account.key]),
+            &[payer_account.clone(), memo_program.clone()],
+        )?;
+
+        if quote.burned > 0 {
+            invoke(
+                &burn(
+                    token_program.key,
+                    payer_token_account.key,
+                    mint_account.key,
+                    payer_account.key,
+                    &[],
+                    quote.burned,
+                )?,
+                &[
+                    payer_token_account.clone(),
+                    mint_account.clone(),
+                    payer_account.clone(),
+                    token_program.clone(),
+                ],
+            )?;
+            let mut config = borrow_token_config_mut(program_id, config_account)?;
+            config.total_supply = u64::from(config.total_supply).saturating_sub(quote.burned).into();
+            record_burn(&mut config, BurnKind::Utility, quote.burned)?;
+            E9thEvent::Burned(Burned {
+                mint: *mint_account.key,
+                authority: *payer_account.key,
+                amount: quote.burned,
+            })
+            .emit();
+        }
+        if quote.paid > 0 {
+            invoke(
+                &transfer(
+                    token_program.key,
+                    payer_token_account.key,
+                    merchant_token_account.key,
+                    payer_account.key,
+                    &[],
+                    quote.paid,
+                )?,
+                &[
+                    payer_token_account.clone(),
+                    merchant_token_account.clone(),
+                    payer_account.clone(),
+                    token_program.clone(),
+                ],
+            )?;
+        }
+
+        msg!(
+            "Paid {} to merchant {} ({:?}: discount {}, burned {})",
+            quote.paid,
+            merchant.authority,
+            tier,
+            quote.discount,
+            quote.burned
+        );
+        E9thEvent::PaymentReceived(PaymentReceived {
+            merchant: *merchant_account.key,
+            payer: *payer_account.key,
+            amount,
+            tier,
+            discount: quote.discount,
+            burned: quote.burned,
+            paid: quote.paid,
+            order_reference,
+        })
+        .emit();
+        Ok(())
+    }
+}
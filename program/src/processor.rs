@@ -2,15 +2,49 @@
 
 use crate::{
     admin::AdminProcessor,
+    airdrop::AirdropProcessor,
+    launch::LaunchProcessor,
+    membership::MembershipProcessor,
     error::E9thTokenError,
-    instruction::{E9thInstruction, E9thTokenInstruction},
-    stake::StakeProcessor,
-    state::{TokenConfig, Blacklist},
+    events::{AirdropSent, BlacklistChanged, Burned, E9thEvent, Paused, RewardsClaimed, Staked, Transferred, Unstaked},
+    instruction::{E9thInstruction, E9thTokenInstruction, LEGACY_NAMESPACE},
+    merchant::MerchantProcessor,
+    migrate::MigrateProcessor,
+    payment::PaymentProcessor,
+    subscription::SubscriptionProcessor,
+    sale::SaleProcessor,
+    tier::TierProcessor,
+    state::{
+        deserialize_account_data, serialize_account_data, AirdropRoundParams, Blacklist, BurnKind, BurnStats,
+        ProgramState, StakeEntry, TokenConfig,
+    },
+    validation::{check_system_program, check_token_program, AccountRules},
+    voucher::VoucherProcessor,
+    zero_copy::{load_account, load_account_mut, TokenConfigPod},
 };
 use solana_program::{
-    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+    account_info::{next_account_info, AccountInfo},
+    clock::{Clock, DEFAULT_MS_PER_SLOT, DEFAULT_SLOTS_PER_EPOCH},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::Pack,
     pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
 };
+use spl_token::{
+    instruction::{burn, transfer},
+    state::{Account as TokenAccount, Mint},
+};
+use std::cell::{Ref, RefMut};
+
+/// Nominal epoch length, turning the config's stake period (in epochs) into a
+/// stake entry's unlock timestamp
+const SECONDS_PER_EPOCH: u64 = DEFAULT_SLOTS_PER_EPOCH * DEFAULT_MS_PER_SLOT / 1_000;
 
 pub struct Processor;
 
@@ -20,14 +54,14 @@ impl Processor {
         accounts: &[AccountInfo],
         instruction_data: &[u8],
     ) -> ProgramResult {
-        // Try to parse as new instruction first
-        if let Ok(instruction) = E9thInstruction::unpack(instruction_data) {
-            return Self::process_enhanced(program_id, accounts, instruction);
+        // The namespace byte selects the instruction family
+        if instruction_data.first() == Some(&LEGACY_NAMESPACE) {
+            let instruction = E9thTokenInstruction::unpack(instruction_data)?;
+            return Self::process_legacy(program_id, accounts, instruction);
         }
 
-        // Fall back to legacy instruction parsing
-        let instruction = E9thTokenInstruction::unpack(instruction_data)?;
-        Self::process_legacy(program_id, accounts, instruction)
+        let instruction = E9thInstruction::unpack(instruction_data)?;
+        Self::process_enhanced(program_id, accounts, instruction)
     }
 
     fn process_enhanced(
@@ -80,6 +114,140 @@ impl Processor {
                 msg!("Instruction: Enhanced Claim Rewards");
                 Self::process_enhanced_claim_rewards(program_id, accounts)
             }
+            E9thInstruction::CreateAirdropRound {
+                round_id,
+                merkle_root,
+                total_amount,
+                num_recipients,
+                expiry_timestamp,
+                gate,
+            } => {
+                msg!("Instruction: Create Airdrop Round");
+                AirdropProcessor::process_create_round(
+                    program_id,
+                    accounts,
+                    AirdropRoundParams {
+                        round_id,
+                        merkle_root,
+                        total_amount,
+                        num_recipients,
+                        expiry_timestamp,
+                        gate,
+                    },
+                )
+            }
+            E9thInstruction::ClaimAirdrop { round_id, index, amount, proof } => {
+                msg!("Instruction: Claim Airdrop");
+                AirdropProcessor::process_claim(program_id, accounts, round_id, index, amount, proof)
+            }
+            E9thInstruction::ClawbackAirdrop { round_id } => {
+                msg!("Instruction: Clawback Airdrop");
+                AirdropProcessor::process_clawback(program_id, accounts, round_id)
+            }
+            E9thInstruction::CreateSale { sale_id, terms } => {
+                msg!("Instruction: Create Sale");
+                SaleProcessor::process_create_sale(program_id, accounts, sale_id, terms)
+            }
+            E9thInstruction::BuySale {
+                sale_id,
+                amount,
+                whitelist_index,
+                allocation,
+                proof,
+            } => {
+                msg!("Instruction: Buy Sale");
+                SaleProcessor::process_buy(
+                    program_id,
+                    accounts,
+                    sale_id,
+                    amount,
+                    whitelist_index,
+                    allocation,
+                    proof,
+                )
+            }
+            E9thInstruction::WithdrawSaleProceeds { sale_id } => {
+                msg!("Instruction: Withdraw Sale Proceeds");
+                SaleProcessor::process_withdraw_proceeds(program_id, accounts, sale_id)
+            }
+            E9thInstruction::RefundSale { sale_id } => {
+                msg!("Instruction: Refund Sale");
+                SaleProcessor::process_refund(program_id, accounts, sale_id)
+            }
+            E9thInstruction::MigrateAccount { account_type, from_version } => {
+                msg!("Instruction: Migrate Account");
+                MigrateProcessor::process_migrate_account(program_id, accounts, account_type, from_version)
+            }
+            E9thInstruction::MigrateProgramState { operator, treasury, burn_rate_basis_points } => {
+                msg!("Instruction: Migrate Program State");
+                MigrateProcessor::process_migrate_program_state(
+                    program_id,
+                    accounts,
+                    operator,
+                    treasury,
+                    burn_rate_basis_points,
+                )
+            }
+            E9thInstruction::MigrateStake => {
+                msg!("Instruction: Migrate Stake");
+                MigrateProcessor::process_migrate_stake(program_id, accounts)
+            }
+            E9thInstruction::SetLegacyDisabled { disabled } => {
+                msg!("Instruction: Set Legacy Disabled");
+                MigrateProcessor::process_set_legacy_disabled(program_id, accounts, disabled)
+            }
+            E9thInstruction::SetTierThresholds { thresholds } => {
+                msg!("Instruction: Set Tier Thresholds");
+                TierProcessor::process_set_thresholds(program_id, accounts, thresholds)
+            }
+            E9thInstruction::VerifyTier => {
+                msg!("Instruction: Verify Tier");
+                TierProcessor::process_verify_tier(program_id, accounts)
+            }
+            E9thInstruction::RegisterMerchant { voucher_cost, voucher_mode, active } => {
+                msg!("Instruction: Register Merchant");
+                MerchantProcessor::process_register(program_id, accounts, voucher_cost, voucher_mode, active)
+            }
+            E9thInstruction::MintVoucher { voucher_id } => {
+                msg!("Instruction: Mint Voucher");
+                VoucherProcessor::process_mint(program_id, accounts, voucher_id)
+            }
+            E9thInstruction::RedeemVoucher => {
+                msg!("Instruction: Redeem Voucher");
+                VoucherProcessor::process_redeem(program_id, accounts)
+            }
+            E9thInstruction::ReclaimVoucher => {
+                msg!("Instruction: Reclaim Voucher");
+                VoucherProcessor::process_reclaim(program_id, accounts)
+            }
+            E9thInstruction::SetPaymentTerms { terms } => {
+                msg!("Instruction: Set Payment Terms");
+                MerchantProcessor::process_set_payment_terms(program_id, accounts, terms)
+            }
+            E9thInstruction::Pay { amount, order_reference } => {
+                msg!("Instruction: Pay");
+                PaymentProcessor::process_pay(program_id, accounts, amount, order_reference)
+            }
+            E9thInstruction::CreateSubscription { subscription_id, terms } => {
+                msg!("Instruction: Create Subscription");
+                SubscriptionProcessor::process_create(program_id, accounts, subscription_id, terms)
+            }
+            E9thInstruction::CollectSubscription => {
+                msg!("Instruction: Collect Subscription");
+                SubscriptionProcessor::process_collect(program_id, accounts)
+            }
+            E9thInstruction::CancelSubscription => {
+                msg!("Instruction: Cancel Subscription");
+                SubscriptionProcessor::process_cancel(program_id, accounts)
+            }
+            E9thInstruction::CreateLaunch { launch_id, terms } => {
+                msg!("Instruction: Create Launch");
+                LaunchProcessor::process_create(program_id, accounts, launch_id, terms)
+            }
+            E9thInstruction::ReserveLaunchSlot => {
+                msg!("Instruction: Reserve Launch Slot");
+                LaunchProcessor::process_reserve(program_id, accounts)
+            }
             // Handle legacy instructions
             E9thInstruction::LegacyInitialize { total_supply, reward_rate, min_stake_period, max_stake_period } => {
                 msg!("Instruction: Legacy Initialize");
@@ -100,17 +268,10 @@ impl Processor {
                 msg!("Instruction: Legacy Burn");
                 AdminProcessor::process_burn(program_id, accounts, amount)
             }
-            E9thInstruction::LegacyStake { amount, period } => {
-                msg!("Instruction: Legacy Stake");
-                StakeProcessor::process_stake(program_id, accounts, amount, period)
-            }
-            E9thInstruction::LegacyUnstake => {
-                msg!("Instruction: Legacy Unstake");
-                StakeProcessor::process_unstake(program_id, accounts)
-            }
-            E9thInstruction::LegacyClaimRewards => {
-                msg!("Instruction: Legacy Claim Rewards");
-                StakeProcessor::process_claim_rewards(program_id, accounts)
+            // Legacy stakes never held tokens, so staking only goes through the
+            // enhanced instructions; `MigrateStake` moves existing stakes over
+            E9thInstruction::LegacyStake { .. } | E9thInstruction::LegacyUnstake | E9thInstruction::LegacyClaimRewards => {
+                Err(E9thTokenError::LegacyStakingRetired.into())
             }
             E9thInstruction::LegacyUpdateSettings { reward_rate, min_stake_period, max_stake_period, staking_enabled } => {
                 msg!("Instruction: Legacy Update Settings");
@@ -135,6 +296,11 @@ impl Processor {
         accounts: &[AccountInfo],
         instruction: E9thTokenInstruction,
     ) -> ProgramResult {
+        // Initialize creates the program state, so there is no flag to check yet
+        if !matches!(instruction, E9thTokenInstruction::Initialize { .. }) {
+            Self::check_legacy_enabled(program_id, accounts)?;
+        }
+
         match instruction {
             E9thTokenInstruction::Initialize {
                 total_supply,
@@ -160,17 +326,8 @@ impl Processor {
                 msg!("Instruction: Legacy Burn");
                 AdminProcessor::process_burn(program_id, accounts, amount)
             }
-            E9thTokenInstruction::Stake { amount, period } => {
-                msg!("Instruction: Legacy Stake");
-                StakeProcessor::process_stake(program_id, accounts, amount, period)
-            }
-            E9thTokenInstruction::Unstake => {
-                msg!("Instruction: Legacy Unstake");
-                StakeProcessor::process_unstake(program_id, accounts)
-            }
-            E9thTokenInstruction::ClaimRewards => {
-                msg!("Instruction: Legacy Claim Rewards");
-                StakeProcessor::process_claim_rewards(program_id, accounts)
+            E9thTokenInstruction::Stake { .. } | E9thTokenInstruction::Unstake | E9thTokenInstruction::ClaimRewards => {
+                Err(E9thTokenError::LegacyStakingRetired.into())
             }
             E9thTokenInstruction::UpdateSettings {
                 reward_rate,
@@ -231,29 +388,75 @@ pub fn get_writable_account<'a>(
     Ok(account)
 }
 
-/// Helper function to validate account ownership
-pub fn validate_account_owner(
-    account: &AccountInfo,
-    expected_owner: &Pubkey,
-) -> Result<(), ProgramError> {
-    if account.owner != expected_owner {
-        return Err(E9thTokenError::InvalidAccountOwner.into());
-    }
+/// Helper function to load the token config, checking it is the program-owned
+/// `["config", mint]` PDA
+pub fn load_token_config(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+) -> Result<TokenConfig, ProgramError> {
+    Ok(TokenConfig::from(&*borrow_token_config(program_id, config_account)?))
+}
+
+/// Borrow the token config in place, with the same checks as [load_token_config]
+pub fn borrow_token_config<'b>(
+    program_id: &Pubkey,
+    config_account: &'b AccountInfo,
+) -> Result<Ref<'b, TokenConfigPod>, ProgramError> {
+    AccountRules::new().owned_by(program_id).check(config_account)?;
+    let config = load_account::<TokenConfigPod>(config_account)?;
+    check_token_config_address(program_id, config_account, &config)?;
+    Ok(config)
+}
+
+/// Mutably borrow the token config in place; field writes go straight to the account
+pub fn borrow_token_config_mut<'b>(
+    program_id: &Pubkey,
+    config_account: &'b AccountInfo,
+) -> Result<RefMut<'b, TokenConfigPod>, ProgramError> {
+    AccountRules::new().writable().owned_by(program_id).check(config_account)?;
+    let config = load_account_mut::<TokenConfigPod>(config_account)?;
+    check_token_config_address(program_id, config_account, &config)?;
+    Ok(config)
+}
+
+/// Add a burn to the config's burn stats. Every path that burns tokens records
+/// it here, so the deflation metrics cover all of them.
+pub fn record_burn(config: &mut TokenConfigPod, kind: BurnKind, amount: u64) -> ProgramResult {
+    let now = Clock::get()?.unix_timestamp as u64;
+    let mut stats = BurnStats::from(&config.burn_stats);
+    stats.record(kind, amount, now)?;
+    config.burn_stats = (&stats).into();
     Ok(())
 }
 
-/// Helper function to get current epoch (simplified - in production, get from clock sysvar)
-pub fn get_current_epoch() -> u64 {
-    // In a real implementation, this would get the current epoch from the clock sysvar
-    // For now, we'll use a placeholder
-    100
+/// The config stores its canonical bump, so re-deriving it with
+/// `create_program_address` avoids the bump search of `find_program_address`
+fn check_token_config_address(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+    config: &TokenConfigPod,
+) -> ProgramResult {
+    let expected =
+        Pubkey::create_program_address(&[b"config", config.mint.as_ref(), &[config.bump]], program_id)
+            .map_err(|_| E9thTokenError::UnexpectedAccount)?;
+    AccountRules::new().address(expected).check(config_account)
+}
+
+/// Rules for the legacy `["program_state"]` PDA
+pub fn program_state_rules(program_id: &Pubkey) -> AccountRules<'_> {
+    AccountRules::new()
+        .owned_by(program_id)
+        .seeds(&[b"program_state"], program_id)
 }
 
-/// Helper function to get current timestamp (simplified - in production, get from clock sysvar)
-pub fn get_current_timestamp() -> u64 {
-    // In a real implementation, this would get the current timestamp from the clock sysvar
-    // For now, we'll use a placeholder
-    1640995200 // 2022-01-01 00:00:00 UTC
+/// Helper function to load the legacy program state, checking it is the
+/// program-owned `["program_state"]` PDA
+pub fn load_program_state(
+    program_id: &Pubkey,
+    program_state_account: &AccountInfo,
+) -> Result<ProgramState, ProgramError> {
+    program_state_rules(program_id).check(program_state_account)?;
+    deserialize_account_data(program_state_account)
 }
 
 // Enhanced instruction implementations
@@ -267,8 +470,62 @@ impl Processor {
         treasury: Pubkey,
         burn_rate_basis_points: u16,
     ) -> ProgramResult {
-        // Implementation for enhanced initialize
-        // This would create TokenConfig, Blacklist accounts, etc.
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let blacklist_account = next_account_info(account_info_iter)?;
+        let owner_account = next_account_info(account_info_iter)?;
+        let mint_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        AccountRules::new().signer().writable().check(owner_account)?;
+        AccountRules::new().owned_by(&spl_token::id()).check(mint_account)?;
+        check_system_program(system_program)?;
+        check_token_program(token_program)?;
+        if burn_rate_basis_points > 10_000 {
+            return Err(E9thTokenError::InvalidAmount.into());
+        }
+
+        // There is one config per mint, so only the mint authority may claim it
+        let mint = Mint::unpack(&mint_account.try_borrow_data()?)
+            .map_err(|_| E9thTokenError::InvalidMint)?;
+        if *owner_account.key != owner || mint.mint_authority != COption::Some(owner) {
+            return Err(E9thTokenError::InvalidAdmin.into());
+        }
+
+        let (config_pda, bump) = Self::get_token_config_pda(program_id, mint_account.key);
+        AccountRules::new().writable().address(config_pda).check(config_account)?;
+        let (blacklist_pda, blacklist_bump) = Self::get_blacklist_pda(program_id, &config_pda);
+        AccountRules::new().writable().address(blacklist_pda).check(blacklist_account)?;
+        if !config_account.data_is_empty() || !blacklist_account.data_is_empty() {
+            return Err(E9thTokenError::AccountAlreadyInitialized.into());
+        }
+
+        let rent = Rent::get()?;
+        for (account, space, seeds) in [
+            (config_account, TokenConfig::LEN, &[b"config".as_ref(), mint_account.key.as_ref(), &[bump]]),
+            (blacklist_account, Blacklist::LEN, &[b"blacklist".as_ref(), config_pda.as_ref(), &[blacklist_bump]]),
+        ] {
+            invoke_signed(
+                &system_instruction::create_account(
+                    owner_account.key,
+                    account.key,
+                    rent.minimum_balance(space),
+                    space as u64,
+                    program_id,
+                ),
+                &[owner_account.clone(), account.clone(), system_program.clone()],
+                &[seeds],
+            )?;
+        }
+
+        let config = TokenConfig {
+            total_supply: mint.supply,
+            ..TokenConfig::new(owner, operator, treasury, *mint_account.key, burn_rate_basis_points, bump)
+        };
+        serialize_account_data(config_account, &config)?;
+        serialize_account_data(blacklist_account, &Blacklist::new(blacklist_bump))?;
+
         msg!("Enhanced initialize: owner={}, operator={}, treasury={}, burn_rate={}", 
              owner, operator, treasury, burn_rate_basis_points);
         Ok(())
@@ -280,9 +537,19 @@ impl Processor {
         accounts: &[AccountInfo],
         pause: bool,
     ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let owner_account = next_account_info(account_info_iter)?;
+
         // Only owner can pause/unpause
-        // Update TokenConfig.is_paused
-        msg!("Set pause: {}", pause);
+        let mut config = borrow_token_config_mut(program_id, config_account)?;
+        AccountRules::new().signer().check(owner_account)?;
+        if *owner_account.key != config.owner {
+            return Err(E9thTokenError::InvalidAdmin.into());
+        }
+
+        config.is_paused = pause.into();
+        E9thEvent::Paused(Paused { paused: pause }).emit();
         Ok(())
     }
 
@@ -293,24 +560,122 @@ impl Processor {
         account: Pubkey,
         add: bool,
     ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let blacklist_account = next_account_info(account_info_iter)?;
+        let authority_account = next_account_info(account_info_iter)?;
+        let config_account = next_account_info(account_info_iter)?;
+
         // Only operator or owner can modify blacklist
-        // Add or remove account from Blacklist
-        msg!("Modify blacklist: account={}, add={}", account, add);
+        let config = load_token_config(program_id, config_account)?;
+        AccountRules::new().signer().check(authority_account)?;
+        if *authority_account.key != config.operator && *authority_account.key != config.owner {
+            return Err(E9thTokenError::InvalidAdmin.into());
+        }
+        AccountRules::new().writable().check(blacklist_account)?;
+        let mut blacklist = Self::load_blacklist(program_id, config_account.key, blacklist_account)?;
+
+        if add {
+            blacklist.add_account(account);
+        } else {
+            blacklist.remove_account(&account);
+        }
+        serialize_account_data(blacklist_account, &blacklist)?;
+        E9thEvent::BlacklistChanged(BlacklistChanged { account, blacklisted: add }).emit();
         Ok(())
     }
 
-    /// Process enhanced transfer with burn logic
+    /// Process enhanced transfer with burn logic: `burn_rate_basis_points` of
+    /// `amount` is burned from the source and the rest reaches the destination
     fn process_enhanced_transfer(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
         amount: u64,
     ) -> ProgramResult {
-        // Check if paused
-        // Check if sender/receiver is blacklisted
-        // Calculate burn amount = amount * burn_rate / 10000
-        // Transfer amount - burn to recipient
-        // Burn the calculated amount
-        msg!("Enhanced transfer: amount={}", amount);
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let blacklist_account = next_account_info(account_info_iter)?;
+        let source_account = next_account_info(account_info_iter)?;
+        let destination_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let authority_account = next_account_info(account_info_iter)?;
+        let mint_account = next_account_info(account_info_iter)?;
+
+        let mut config = borrow_token_config_mut(program_id, config_account)?;
+        let blacklist = Self::load_blacklist(program_id, config_account.key, blacklist_account)?;
+        AccountRules::new().signer().check(authority_account)?;
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(authority_account.key)
+            .check(source_account)?;
+        AccountRules::new().writable().token_account(&config.mint).check(destination_account)?;
+        AccountRules::new().writable().mint(&config.mint).check(mint_account)?;
+        check_token_program(token_program)?;
+
+        if bool::from(config.is_paused) {
+            return Err(E9thTokenError::TokenPaused.into());
+        }
+        if amount == 0 {
+            return Err(E9thTokenError::InvalidAmount.into());
+        }
+        let recipient = TokenAccount::unpack(&destination_account.try_borrow_data()?)?.owner;
+        if blacklist.is_blacklisted(authority_account.key) || blacklist.is_blacklisted(&recipient) {
+            return Err(E9thTokenError::AccountBlacklisted.into());
+        }
+
+        let burned = (amount as u128 * u16::from(config.burn_rate_basis_points) as u128 / 10_000) as u64;
+        if burned > 0 {
+            invoke(
+                &burn(
+                    token_program.key,
+                    source_account.key,
+                    mint_account.key,
+                    authority_account.key,
+                    &[],
+                    burned,
+                )?,
+                &[
+                    source_account.clone(),
+                    mint_account.clone(),
+                    authority_account.clone(),
+                    token_program.clone(),
+                ],
+            )?;
+            config.total_supply = u64::from(config.total_supply).saturating_sub(burned).into();
+            record_burn(&mut config, BurnKind::TransferTax, burned)?;
+            E9thEvent::Burned(Burned {
+                mint: *mint_account.key,
+                authority: *authority_account.key,
+                amount: burned,
+            })
+            .emit();
+        }
+
+        let received = amount - burned;
+        invoke(
+            &transfer(
+                token_program.key,
+                source_account.key,
+                destination_account.key,
+                authority_account.key,
+                &[],
+                received,
+            )?,
+            &[
+                source_account.clone(),
+                destination_account.clone(),
+                authority_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        E9thEvent::Transferred(Transferred {
+            from: *authority_account.key,
+            to: recipient,
+            amount: received,
+            burned,
+        })
+        .emit();
         Ok(())
     }
 
@@ -321,10 +686,61 @@ impl Processor {
         recipients: Vec<Pubkey>,
         amounts: Vec<u64>,
     ) -> ProgramResult {
-        // Only operator or owner can airdrop
-        // Validate vectors same length
-        // Batch transfer to multiple recipients
-        msg!("Airdrop: {} recipients, {} amounts", recipients.len(), amounts.len());
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let treasury_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let treasury_account = next_account_info(account_info_iter)?;
+
+        // Only the treasury can fund an airdrop, one token account per recipient
+        let config = load_token_config(program_id, config_account)?;
+        AccountRules::new().signer().check(treasury_account)?;
+        if *treasury_account.key != config.treasury {
+            return Err(E9thTokenError::InvalidAdmin.into());
+        }
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(&config.treasury)
+            .check(treasury_token_account)?;
+        check_token_program(token_program)?;
+        if recipients.len() != amounts.len() || account_info_iter.len() != recipients.len() {
+            return Err(E9thTokenError::InvalidInstruction.into());
+        }
+        let recipient_token_accounts: Vec<_> = account_info_iter.collect();
+        for (recipient, recipient_token_account) in recipients.iter().zip(&recipient_token_accounts) {
+            AccountRules::new()
+                .writable()
+                .token_account(&config.mint)
+                .token_authority(recipient)
+                .check(recipient_token_account)?;
+        }
+        if amounts.contains(&0) {
+            return Err(E9thTokenError::InvalidAmount.into());
+        }
+
+        for ((recipient, amount), recipient_token_account) in
+            recipients.iter().zip(amounts).zip(recipient_token_accounts)
+        {
+            invoke(
+                &transfer(
+                    token_program.key,
+                    treasury_token_account.key,
+                    recipient_token_account.key,
+                    treasury_account.key,
+                    &[],
+                    amount,
+                )?,
+                &[
+                    treasury_token_account.clone(),
+                    recipient_token_account.clone(),
+                    treasury_account.clone(),
+                    token_program.clone(),
+                ],
+            )?;
+            // Batch airdrops aren't part of a merkle round, whose ids start at 1
+            E9thEvent::AirdropSent(AirdropSent { round_id: 0, recipient: *recipient, amount }).emit();
+        }
         Ok(())
     }
 
@@ -334,8 +750,121 @@ impl Processor {
         accounts: &[AccountInfo],
         amount: u64,
     ) -> ProgramResult {
-        // Enhanced staking with lock time, penalties, etc.
-        msg!("Enhanced stake: amount={}", amount);
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let stake_entry_account = next_account_info(account_info_iter)?;
+        let staker_token_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let staker_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        let (mut config, bump) = Self::check_stake_accounts(
+            program_id,
+            config_account,
+            stake_entry_account,
+            staker_token_account,
+            vault_account,
+            token_program,
+            staker_account,
+        )?;
+        // The staker pays for a new stake entry
+        AccountRules::new().writable().check(staker_account)?;
+        check_system_program(system_program)?;
+
+        if !bool::from(config.staking_enabled) {
+            return Err(E9thTokenError::InvalidStakePeriod.into());
+        }
+        if amount == 0 {
+            return Err(E9thTokenError::InvalidAmount.into());
+        }
+
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp as u64;
+        let period = u64::from(config.min_stake_period);
+        // Every stake restarts the lock, so added tokens are locked as long as a new stake
+        let lock_time = now.saturating_add(period.saturating_mul(SECONDS_PER_EPOCH));
+        let entry = if stake_entry_account.data_is_empty() {
+            let lamports = Rent::get()?.minimum_balance(StakeEntry::LEN);
+            invoke_signed(
+                &system_instruction::create_account(
+                    staker_account.key,
+                    stake_entry_account.key,
+                    lamports,
+                    StakeEntry::LEN as u64,
+                    program_id,
+                ),
+                &[
+                    staker_account.clone(),
+                    stake_entry_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[
+                    b"stake_entry",
+                    config_account.key.as_ref(),
+                    staker_account.key.as_ref(),
+                    &[bump],
+                ]],
+            )?;
+            StakeEntry {
+                last_reward_timestamp: now,
+                ..StakeEntry::new(*staker_account.key, amount, period, clock.epoch, lock_time, bump)
+            }
+        } else {
+            AccountRules::new().owned_by(program_id).check(stake_entry_account)?;
+            let mut entry: StakeEntry = deserialize_account_data(stake_entry_account)?;
+            entry.settle_rewards(now, u16::from(config.reward_rate));
+            entry.amount = entry.amount.checked_add(amount).ok_or(E9thTokenError::MathOverflow)?;
+            entry.period = period;
+            entry.lock_time = lock_time;
+            // Holding periods count from the last top-up, so an old dust stake
+            // can't lend its age to tokens added just before an airdrop claim
+            entry.start_epoch = clock.epoch;
+            entry
+        };
+
+        invoke(
+            &transfer(
+                token_program.key,
+                staker_token_account.key,
+                vault_account.key,
+                staker_account.key,
+                &[],
+                amount,
+            )?,
+            &[
+                staker_token_account.clone(),
+                vault_account.clone(),
+                staker_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+        serialize_account_data(stake_entry_account, &entry)?;
+        let total_staked = u64::from(config.total_staked).saturating_add(amount);
+        config.total_staked = total_staked.into();
+        drop(config);
+
+        E9thEvent::Staked(Staked {
+            staker: *staker_account.key,
+            amount,
+            period,
+            total_staked,
+        })
+        .emit();
+
+        // The membership accounts are optional: a stake can only raise the tier
+        if let Some(tier_config_account) = account_info_iter.next() {
+            let pass_account = next_account_info(account_info_iter)?;
+            MembershipProcessor::sync_pass(
+                program_id,
+                config_account.key,
+                staker_account.key,
+                entry.amount,
+                tier_config_account,
+                pass_account,
+                Some((staker_account, system_program)),
+            )?;
+        }
         Ok(())
     }
 
@@ -345,9 +874,85 @@ impl Processor {
         accounts: &[AccountInfo],
         amount: u64,
     ) -> ProgramResult {
-        // Enhanced unstaking with penalties for early withdrawal
-        msg!("Enhanced unstake: amount={}", amount);
-        Ok(())
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let stake_entry_account = next_account_info(account_info_iter)?;
+        let staker_token_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let staker_account = next_account_info(account_info_iter)?;
+        let tier_config_account = next_account_info(account_info_iter)?;
+        let pass_account = next_account_info(account_info_iter)?;
+
+        let (mut config, _) = Self::check_stake_accounts(
+            program_id,
+            config_account,
+            stake_entry_account,
+            staker_token_account,
+            vault_account,
+            token_program,
+            staker_account,
+        )?;
+        if stake_entry_account.data_is_empty() {
+            return Err(E9thTokenError::StakeAccountNotFound.into());
+        }
+        AccountRules::new().owned_by(program_id).check(stake_entry_account)?;
+        let mut entry: StakeEntry = deserialize_account_data(stake_entry_account)?;
+        if amount == 0 {
+            return Err(E9thTokenError::InvalidAmount.into());
+        }
+        if amount > entry.amount {
+            return Err(E9thTokenError::InsufficientFunds.into());
+        }
+
+        let now = Clock::get()?.unix_timestamp as u64;
+        if !entry.is_unlocked(now) {
+            return Err(E9thTokenError::StakeNotMature.into());
+        }
+        entry.settle_rewards(now, u16::from(config.reward_rate));
+        entry.amount -= amount;
+        serialize_account_data(stake_entry_account, &entry)?;
+
+        let total_staked = u64::from(config.total_staked).saturating_sub(amount);
+        config.total_staked = total_staked.into();
+        let (mint, bump) = (config.mint, config.bump);
+        // The config signs for the vault, so the CPI must be able to borrow it
+        drop(config);
+
+        invoke_signed(
+            &transfer(
+                token_program.key,
+                vault_account.key,
+                staker_token_account.key,
+                config_account.key,
+                &[],
+                amount,
+            )?,
+            &[
+                vault_account.clone(),
+                staker_token_account.clone(),
+                config_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[b"config", mint.as_ref(), &[bump]]],
+        )?;
+
+        E9thEvent::Unstaked(Unstaked {
+            staker: *staker_account.key,
+            amount,
+            total_staked,
+        })
+        .emit();
+
+        MembershipProcessor::sync_pass(
+            program_id,
+            config_account.key,
+            staker_account.key,
+            entry.amount,
+            tier_config_account,
+            pass_account,
+            None,
+        )
     }
 
     /// Process enhanced claim rewards instruction
@@ -355,8 +960,144 @@ impl Processor {
         program_id: &Pubkey,
         accounts: &[AccountInfo],
     ) -> ProgramResult {
-        // Enhanced reward claiming with time-based calculations
-        msg!("Enhanced claim rewards");
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let stake_entry_account = next_account_info(account_info_iter)?;
+        let staker_token_account = next_account_info(account_info_iter)?;
+        let treasury_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let staker_account = next_account_info(account_info_iter)?;
+
+        let config = load_token_config(program_id, config_account)?;
+        AccountRules::new().signer().check(staker_account)?;
+        let (entry_pda, _) = Self::get_stake_entry_pda(program_id, config_account.key, staker_account.key);
+        AccountRules::new()
+            .writable()
+            .owned_by(program_id)
+            .address(entry_pda)
+            .check(stake_entry_account)?;
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(staker_account.key)
+            .check(staker_token_account)?;
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(&config.treasury)
+            .check(treasury_token_account)?;
+        check_token_program(token_program)?;
+
+        let mut entry: StakeEntry = deserialize_account_data(stake_entry_account)?;
+        let now = Clock::get()?.unix_timestamp as u64;
+        entry.settle_rewards(now, config.reward_rate);
+        let rewards = entry.carried_rewards;
+        if rewards == 0 {
+            return Err(E9thTokenError::InvalidAmount.into());
+        }
+        // The treasury funds rewards by approving the config PDA as the delegate
+        // of its token account
+        let treasury = TokenAccount::unpack(&treasury_token_account.try_borrow_data()?)?;
+        if treasury.delegate != COption::Some(*config_account.key) || treasury.delegated_amount < rewards {
+            return Err(E9thTokenError::InsufficientFunds.into());
+        }
+        entry.carried_rewards = 0;
+        serialize_account_data(stake_entry_account, &entry)?;
+
+        invoke_signed(
+            &transfer(
+                token_program.key,
+                treasury_token_account.key,
+                staker_token_account.key,
+                config_account.key,
+                &[],
+                rewards,
+            )?,
+            &[
+                treasury_token_account.clone(),
+                staker_token_account.clone(),
+                config_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[b"config", config.mint.as_ref(), &[config.bump]]],
+        )?;
+
+        E9thEvent::RewardsClaimed(RewardsClaimed {
+            staker: *staker_account.key,
+            amount: rewards,
+        })
+        .emit();
         Ok(())
     }
+
+    /// Check the accounts shared by enhanced `Stake` and `Unstake`: the staker's
+    /// entry and token account, and the vault owned by the config PDA. Returns
+    /// the borrowed config and the stake entry's bump.
+    fn check_stake_accounts<'b>(
+        program_id: &Pubkey,
+        config_account: &'b AccountInfo,
+        stake_entry_account: &AccountInfo,
+        staker_token_account: &AccountInfo,
+        vault_account: &AccountInfo,
+        token_program: &AccountInfo,
+        staker_account: &AccountInfo,
+    ) -> Result<(RefMut<'b, TokenConfigPod>, u8), ProgramError> {
+        let config = borrow_token_config_mut(program_id, config_account)?;
+        AccountRules::new().signer().check(staker_account)?;
+        let (entry_pda, bump) = Self::get_stake_entry_pda(program_id, config_account.key, staker_account.key);
+        AccountRules::new().writable().address(entry_pda).check(stake_entry_account)?;
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(staker_account.key)
+            .check(staker_token_account)?;
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(config_account.key)
+            .check(vault_account)?;
+        check_token_program(token_program)?;
+        Ok((config, bump))
+    }
+
+    /// Load the blacklist of `config`, checking its address
+    pub fn load_blacklist(
+        program_id: &Pubkey,
+        config: &Pubkey,
+        blacklist_account: &AccountInfo,
+    ) -> Result<Blacklist, ProgramError> {
+        AccountRules::new().owned_by(program_id).check(blacklist_account)?;
+        let blacklist: Blacklist = deserialize_account_data(blacklist_account)?;
+        let expected =
+            Pubkey::create_program_address(&[b"blacklist", config.as_ref(), &[blacklist.bump]], program_id)
+                .map_err(|_| E9thTokenError::UnexpectedAccount)?;
+        AccountRules::new().address(expected).check(blacklist_account)?;
+        Ok(blacklist)
+    }
+
+    /// Reject legacy instructions once the owner has disabled them. Every legacy
+    /// instruction takes the program state first.
+    fn check_legacy_enabled(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let program_state_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let program_state = load_program_state(program_id, program_state_account)?;
+        if program_state.legacy_disabled {
+            return Err(E9thTokenError::LegacyInstructionsDisabled.into());
+        }
+        Ok(())
+    }
+
+    /// Get the token config PDA for a mint
+    pub fn get_token_config_pda(program_id: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"config", mint.as_ref()], program_id)
+    }
+
+    /// Get the blacklist PDA for a token config
+    pub fn get_blacklist_pda(program_id: &Pubkey, config: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"blacklist", config.as_ref()], program_id)
+    }
+
+    /// Get a staker's stake entry PDA for a token config
+    pub fn get_stake_entry_pda(program_id: &Pubkey, config: &Pubkey, staker: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"stake_entry", config.as_ref(), staker.as_ref()], program_id)
+    }
 }
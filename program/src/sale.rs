@@ -0,0 +1,594 @@
+//! Public sale functionality for the E9th Token Program
+
+use crate::{
+    error::E9thTokenError,
+    merkle,
+    processor::load_token_config,
+    state::{
+        deserialize_account_data, serialize_account_data, Sale, SalePurchase, SaleTerms,
+        TokenConfig,
+    },
+    validation::{check_system_program, check_token_program, AccountRules},
+};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+use spl_token::{instruction::transfer, state::Account as TokenAccount};
+
+pub struct SaleProcessor;
+
+impl SaleProcessor {
+    /// Create a sale and fund its vault with the hard cap
+    pub fn process_create_sale(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        sale_id: u64,
+        terms: SaleTerms,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let sale_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let quote_vault_account = next_account_info(account_info_iter)?;
+        let funding_account = next_account_info(account_info_iter)?;
+        let owner_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        let config = load_token_config(program_id, config_account)?;
+        Self::validate_owner(&config, owner_account)?;
+        check_system_program(system_program)?;
+        check_token_program(token_program)?;
+        Self::validate_terms(&terms)?;
+
+        let (sale_pda, bump) = Self::get_sale_pda(program_id, config_account.key, sale_id);
+        AccountRules::new().writable().address(sale_pda).check(sale_account)?;
+        if !sale_account.data_is_empty() {
+            return Err(E9thTokenError::AccountAlreadyInitialized.into());
+        }
+
+        AccountRules::new().writable().check(vault_account)?;
+        Self::validate_vault(vault_account, &config.mint, &sale_pda)?;
+        let quote_vault = if terms.is_lamport_quote() {
+            Pubkey::default()
+        } else {
+            Self::validate_vault(quote_vault_account, &terms.quote_mint, &sale_pda)?;
+            *quote_vault_account.key
+        };
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(owner_account.key)
+            .check(funding_account)?;
+
+        let lamports = Rent::get()?.minimum_balance(Sale::LEN);
+        invoke_signed(
+            &system_instruction::create_account(
+                owner_account.key,
+                sale_account.key,
+                lamports,
+                Sale::LEN as u64,
+                program_id,
+            ),
+            &[
+                owner_account.clone(),
+                sale_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"sale",
+                config_account.key.as_ref(),
+                &sale_id.to_le_bytes(),
+                &[bump],
+            ]],
+        )?;
+
+        invoke(
+            &transfer(
+                token_program.key,
+                funding_account.key,
+                vault_account.key,
+                owner_account.key,
+                &[],
+                terms.hard_cap,
+            )?,
+            &[
+                funding_account.clone(),
+                vault_account.clone(),
+                owner_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        let hard_cap = terms.hard_cap;
+        let sale = Sale::new(sale_id, terms, *vault_account.key, quote_vault, bump);
+        serialize_account_data(sale_account, &sale)?;
+
+        msg!("Sale {} created with hard cap {}", sale_id, hard_cap);
+        Ok(())
+    }
+
+    /// Buy E9TH from an active sale
+    pub fn process_buy(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        sale_id: u64,
+        amount: u64,
+        whitelist_index: u64,
+        allocation: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let sale_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let purchase_account = next_account_info(account_info_iter)?;
+        let buyer_account = next_account_info(account_info_iter)?;
+        let buyer_token_account = next_account_info(account_info_iter)?;
+        let buyer_quote_account = next_account_info(account_info_iter)?;
+        let quote_vault_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        AccountRules::new().signer().writable().check(buyer_account)?;
+        check_system_program(system_program)?;
+        check_token_program(token_program)?;
+
+        let (config, mut sale) = Self::load_sale(program_id, config_account, sale_account, sale_id)?;
+        if config.is_paused {
+            return Err(E9thTokenError::SaleNotActive.into());
+        }
+        AccountRules::new().writable().address(sale.vault).check(vault_account)?;
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(buyer_account.key)
+            .check(buyer_token_account)?;
+
+        let now = Clock::get()?.unix_timestamp as u64;
+        if !sale.terms.is_open(now) {
+            return Err(E9thTokenError::SaleNotActive.into());
+        }
+        if amount < sale.terms.min_purchase {
+            return Err(E9thTokenError::PurchaseBelowMinimum.into());
+        }
+
+        let mut purchase =
+            Self::load_or_create_purchase(program_id, sale_account, purchase_account, buyer_account, system_program)?;
+
+        let amount_bought = purchase
+            .amount_bought
+            .checked_add(amount)
+            .ok_or(E9thTokenError::MathOverflow)?;
+        if amount_bought > sale.terms.max_purchase {
+            return Err(E9thTokenError::PurchaseAboveMaximum.into());
+        }
+
+        if sale.terms.is_whitelist_phase(now) {
+            let leaf = merkle::leaf_hash(whitelist_index, buyer_account.key, allocation);
+            if !merkle::verify_proof(&proof, &sale.terms.whitelist_root, leaf) {
+                return Err(E9thTokenError::NotWhitelisted.into());
+            }
+            if amount_bought > allocation {
+                return Err(E9thTokenError::PurchaseAboveMaximum.into());
+            }
+        }
+
+        let total_sold = sale
+            .total_sold
+            .checked_add(amount)
+            .ok_or(E9thTokenError::MathOverflow)?;
+        if total_sold > sale.terms.hard_cap {
+            return Err(E9thTokenError::HardCapExceeded.into());
+        }
+
+        let cost = sale
+            .terms
+            .quote_for(amount)
+            .ok_or(E9thTokenError::MathOverflow)?;
+        if cost == 0 {
+            return Err(E9thTokenError::InvalidAmount.into());
+        }
+
+        // Collect payment
+        if sale.terms.is_lamport_quote() {
+            invoke(
+                &system_instruction::transfer(buyer_account.key, sale_account.key, cost),
+                &[
+                    buyer_account.clone(),
+                    sale_account.clone(),
+                    system_program.clone(),
+                ],
+            )?;
+        } else {
+            AccountRules::new().writable().address(sale.quote_vault).check(quote_vault_account)?;
+            invoke(
+                &transfer(
+                    token_program.key,
+                    buyer_quote_account.key,
+                    quote_vault_account.key,
+                    buyer_account.key,
+                    &[],
+                    cost,
+                )?,
+                &[
+                    buyer_quote_account.clone(),
+                    quote_vault_account.clone(),
+                    buyer_account.clone(),
+                    token_program.clone(),
+                ],
+            )?;
+        }
+
+        // Deliver E9TH
+        Self::transfer_signed(
+            config_account,
+            sale_account,
+            vault_account,
+            buyer_token_account,
+            token_program,
+            &sale,
+            amount,
+        )?;
+
+        purchase.amount_bought = amount_bought;
+        purchase.amount_paid = purchase
+            .amount_paid
+            .checked_add(cost)
+            .ok_or(E9thTokenError::MathOverflow)?;
+        serialize_account_data(purchase_account, &purchase)?;
+
+        sale.total_sold = total_sold;
+        sale.total_raised = sale
+            .total_raised
+            .checked_add(cost)
+            .ok_or(E9thTokenError::MathOverflow)?;
+        serialize_account_data(sale_account, &sale)?;
+
+        msg!("Bought {} tokens for {} from sale {}", amount, cost, sale_id);
+        Ok(())
+    }
+
+    /// Refund a buyer's payment after a failed sale, returning their E9TH to the vault
+    pub fn process_refund(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        sale_id: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let sale_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let purchase_account = next_account_info(account_info_iter)?;
+        let buyer_account = next_account_info(account_info_iter)?;
+        let buyer_token_account = next_account_info(account_info_iter)?;
+        let buyer_quote_account = next_account_info(account_info_iter)?;
+        let quote_vault_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        AccountRules::new().signer().writable().check(buyer_account)?;
+        check_token_program(token_program)?;
+
+        let (config, mut sale) = Self::load_sale(program_id, config_account, sale_account, sale_id)?;
+        AccountRules::new().writable().address(sale.vault).check(vault_account)?;
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(buyer_account.key)
+            .check(buyer_token_account)?;
+
+        let now = Clock::get()?.unix_timestamp as u64;
+        if !sale.is_failed(now) {
+            return Err(E9thTokenError::RefundNotAvailable.into());
+        }
+
+        let (purchase_pda, _) = Self::get_purchase_pda(program_id, sale_account.key, buyer_account.key);
+        AccountRules::new()
+            .writable()
+            .owned_by(program_id)
+            .address(purchase_pda)
+            .check(purchase_account)?;
+        let mut purchase: SalePurchase = deserialize_account_data(purchase_account)?;
+        if purchase.buyer != *buyer_account.key {
+            return Err(E9thTokenError::Unauthorized.into());
+        }
+        if purchase.amount_bought == 0 && purchase.amount_paid == 0 {
+            return Err(E9thTokenError::InvalidAmount.into());
+        }
+
+        // Return the purchased E9TH to the sale vault
+        invoke(
+            &transfer(
+                token_program.key,
+                buyer_token_account.key,
+                vault_account.key,
+                buyer_account.key,
+                &[],
+                purchase.amount_bought,
+            )?,
+            &[
+                buyer_token_account.clone(),
+                vault_account.clone(),
+                buyer_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        // Return the payment
+        let refund = purchase.amount_paid;
+        if sale.terms.is_lamport_quote() {
+            **sale_account.try_borrow_mut_lamports()? -= refund;
+            **buyer_account.try_borrow_mut_lamports()? += refund;
+        } else {
+            AccountRules::new().writable().address(sale.quote_vault).check(quote_vault_account)?;
+            AccountRules::new()
+                .writable()
+                .token_account(&sale.terms.quote_mint)
+                .token_authority(buyer_account.key)
+                .check(buyer_quote_account)?;
+            Self::transfer_signed(
+                config_account,
+                sale_account,
+                quote_vault_account,
+                buyer_quote_account,
+                token_program,
+                &sale,
+                refund,
+            )?;
+        }
+
+        let returned = purchase.amount_bought;
+        purchase.amount_bought = 0;
+        purchase.amount_paid = 0;
+        serialize_account_data(purchase_account, &purchase)?;
+
+        sale.total_refunded = sale
+            .total_refunded
+            .checked_add(refund)
+            .ok_or(E9thTokenError::MathOverflow)?;
+        serialize_account_data(sale_account, &sale)?;
+
+        msg!("Refunded {} and returned {} tokens for sale {}", refund, returned, sale_id);
+        Ok(())
+    }
+
+    /// Send sale proceeds and unsold E9TH to the treasury after the sale ends.
+    /// A sale that missed its soft cap keeps its proceeds for refunds.
+    pub fn process_withdraw_proceeds(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        sale_id: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let sale_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let treasury_token_account = next_account_info(account_info_iter)?;
+        let quote_vault_account = next_account_info(account_info_iter)?;
+        let treasury_proceeds_account = next_account_info(account_info_iter)?;
+        let owner_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        let (config, sale) = Self::load_sale(program_id, config_account, sale_account, sale_id)?;
+        Self::validate_owner(&config, owner_account)?;
+        check_token_program(token_program)?;
+        AccountRules::new().writable().address(sale.vault).check(vault_account)?;
+
+        let now = Clock::get()?.unix_timestamp as u64;
+        if !sale.terms.has_ended(now) {
+            return Err(E9thTokenError::SaleNotEnded.into());
+        }
+
+        // Proceeds
+        let proceeds = if !sale.soft_cap_met() {
+            0
+        } else if sale.terms.is_lamport_quote() {
+            AccountRules::new()
+                .writable()
+                .address(config.treasury)
+                .check(treasury_proceeds_account)?;
+            let rent_exempt = Rent::get()?.minimum_balance(sale_account.data_len());
+            let proceeds = sale_account.lamports().saturating_sub(rent_exempt);
+            **sale_account.try_borrow_mut_lamports()? -= proceeds;
+            **treasury_proceeds_account.try_borrow_mut_lamports()? += proceeds;
+            proceeds
+        } else {
+            AccountRules::new().writable().address(sale.quote_vault).check(quote_vault_account)?;
+            Self::validate_treasury_account(treasury_proceeds_account, &sale.terms.quote_mint, &config)?;
+            let proceeds = TokenAccount::unpack(&quote_vault_account.try_borrow_data()?)?.amount;
+            if proceeds > 0 {
+                Self::transfer_signed(
+                    config_account,
+                    sale_account,
+                    quote_vault_account,
+                    treasury_proceeds_account,
+                    token_program,
+                    &sale,
+                    proceeds,
+                )?;
+            }
+            proceeds
+        };
+
+        // Unsold tokens
+        Self::validate_treasury_account(treasury_token_account, &config.mint, &config)?;
+        let unsold = TokenAccount::unpack(&vault_account.try_borrow_data()?)?.amount;
+        if unsold > 0 {
+            Self::transfer_signed(
+                config_account,
+                sale_account,
+                vault_account,
+                treasury_token_account,
+                token_program,
+                &sale,
+                unsold,
+            )?;
+        }
+
+        msg!(
+            "Withdrew {} proceeds and {} unsold tokens from sale {}",
+            proceeds,
+            unsold,
+            sale_id
+        );
+        Ok(())
+    }
+
+    /// Get sale PDA
+    pub fn get_sale_pda(program_id: &Pubkey, config: &Pubkey, sale_id: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"sale", config.as_ref(), &sale_id.to_le_bytes()], program_id)
+    }
+
+    /// Get purchase record PDA
+    pub fn get_purchase_pda(program_id: &Pubkey, sale: &Pubkey, buyer: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"sale_purchase", sale.as_ref(), buyer.as_ref()], program_id)
+    }
+
+    fn validate_terms(terms: &SaleTerms) -> ProgramResult {
+        if terms.end_timestamp <= terms.start_timestamp
+            || terms.whitelist_end_timestamp > terms.end_timestamp
+        {
+            return Err(E9thTokenError::InvalidAccountData.into());
+        }
+        if terms.price_quote_amount == 0
+            || terms.price_token_amount == 0
+            || terms.hard_cap == 0
+            || terms.max_purchase == 0
+            || terms.min_purchase > terms.max_purchase
+            || terms.soft_cap > terms.hard_cap
+        {
+            return Err(E9thTokenError::InvalidAmount.into());
+        }
+        Ok(())
+    }
+
+    fn validate_owner(config: &TokenConfig, owner: &AccountInfo) -> ProgramResult {
+        AccountRules::new().signer().check(owner)?;
+        if *owner.key != config.owner {
+            return Err(E9thTokenError::InvalidAdmin.into());
+        }
+        Ok(())
+    }
+
+    fn validate_vault(vault_account: &AccountInfo, mint: &Pubkey, sale_pda: &Pubkey) -> ProgramResult {
+        AccountRules::new()
+            .token_account(mint)
+            .token_authority(sale_pda)
+            .check(vault_account)
+    }
+
+    fn validate_treasury_account(account: &AccountInfo, mint: &Pubkey, config: &TokenConfig) -> ProgramResult {
+        AccountRules::new()
+            .writable()
+            .token_account(mint)
+            .token_authority(&config.treasury)
+            .check(account)
+    }
+
+    /// Load the config and the sale, checking the sale is the config's `sale_id` PDA
+    fn load_sale(
+        program_id: &Pubkey,
+        config_account: &AccountInfo,
+        sale_account: &AccountInfo,
+        sale_id: u64,
+    ) -> Result<(TokenConfig, Sale), ProgramError> {
+        let config = load_token_config(program_id, config_account)?;
+        AccountRules::new().writable().owned_by(program_id).check(sale_account)?;
+        let sale: Sale = deserialize_account_data(sale_account)?;
+        let expected = Pubkey::create_program_address(
+            &[
+                b"sale",
+                config_account.key.as_ref(),
+                &sale_id.to_le_bytes(),
+                &[sale.bump],
+            ],
+            program_id,
+        )?;
+        if expected != *sale_account.key || sale.sale_id != sale_id {
+            return Err(E9thTokenError::UnexpectedAccount.into());
+        }
+        Ok((config, sale))
+    }
+
+    fn load_or_create_purchase<'a>(
+        program_id: &Pubkey,
+        sale_account: &AccountInfo<'a>,
+        purchase_account: &AccountInfo<'a>,
+        buyer_account: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+    ) -> Result<SalePurchase, ProgramError> {
+        let (purchase_pda, bump) = Self::get_purchase_pda(program_id, sale_account.key, buyer_account.key);
+        AccountRules::new().writable().address(purchase_pda).check(purchase_account)?;
+
+        if !purchase_account.data_is_empty() {
+            AccountRules::new().owned_by(program_id).check(purchase_account)?;
+            return deserialize_account_data(purchase_account);
+        }
+
+        let lamports = Rent::get()?.minimum_balance(SalePurchase::LEN);
+        invoke_signed(
+            &system_instruction::create_account(
+                buyer_account.key,
+                purchase_account.key,
+                lamports,
+                SalePurchase::LEN as u64,
+                program_id,
+            ),
+            &[
+                buyer_account.clone(),
+                purchase_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"sale_purchase",
+                sale_account.key.as_ref(),
+                buyer_account.key.as_ref(),
+                &[bump],
+            ]],
+        )?;
+        Ok(SalePurchase::new(*buyer_account.key, *sale_account.key, bump))
+    }
+
+    fn transfer_signed<'a>(
+        config_account: &AccountInfo<'a>,
+        sale_account: &AccountInfo<'a>,
+        source_account: &AccountInfo<'a>,
+        destination_account: &AccountInfo<'a>,
+        token_program: &AccountInfo<'a>,
+        sale: &Sale,
+        amount: u64,
+    ) -> ProgramResult {
+        invoke_signed(
+            &transfer(
+                token_program.key,
+                source_account.key,
+                destination_account.key,
+                sale_account.key,
+                &[],
+                amount,
+            )?,
+            &[
+                source_account.clone(),
+                destination_account.clone(),
+                sale_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[
+                b"sale",
+                config_account.key.as_ref(),
+                &sale.sale_id.to_le_bytes(),
+                &[sale.bump],
+            ]],
+        )
+    }
+}
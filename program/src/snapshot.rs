@@ -0,0 +1,157 @@
+//! Off-chain holder snapshots
+//!
+//! Builds a deterministic wallet → balance + staked list for one mint at one
+//! slot from dumped accounts: SPL token accounts of the mint and the program's
+//! [StakeEntry] accounts. With the `cli` feature the accounts are read from
+//! `getProgramAccounts` JSON ([parse_program_accounts_json]).
+//!
+//! The snapshot hash is the SHA-256 of the Borsh encoding of
+//! `(mint, slot, holders)`, with holders sorted by wallet, so partners can
+//! recompute it from the published list.
+
+use crate::{
+    processor::Processor,
+    state::{unpack_state, AccountHeader, AccountType, StakeEntry},
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{hash::hash, program_pack::Pack, pubkey::Pubkey};
+use spl_token::state::Account as TokenAccount;
+use std::collections::BTreeMap;
+
+/// One dumped account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawAccount {
+    pub pubkey: Pubkey,
+    pub owner: Pubkey,
+    pub data: Vec<u8>,
+}
+
+/// A wallet's holdings at the snapshot slot
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Holder {
+    pub wallet: Pubkey,
+    /// Tokens held across the wallet's token accounts
+    pub balance: u64,
+    /// Tokens staked in the wallet's stake entry
+    pub staked: u64,
+}
+
+/// Holders of a mint at a slot
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub mint: Pubkey,
+    pub slot: u64,
+    /// Wallets with a non-zero balance or stake, sorted by wallet
+    pub holders: Vec<Holder>,
+}
+
+impl Snapshot {
+    /// Build the snapshot of `mint` from `accounts`. Token accounts of other
+    /// mints and stake entries of other configs are ignored.
+    pub fn build(
+        program_id: &Pubkey,
+        mint: &Pubkey,
+        slot: u64,
+        accounts: &[RawAccount],
+    ) -> Result<Self, String> {
+        let (config, _) = Processor::get_token_config_pda(program_id, mint);
+        let mut holdings: BTreeMap<Pubkey, (u64, u64)> = BTreeMap::new();
+        let mut seen = BTreeMap::new();
+
+        for account in accounts {
+            // The same account may appear in several dumps
+            if let Some(previous) = seen.insert(account.pubkey, &account.data) {
+                if *previous != account.data {
+                    return Err(format!("account {} differs between dumps", account.pubkey));
+                }
+                continue;
+            }
+
+            if account.owner == spl_token::id() && account.data.len() == TokenAccount::LEN {
+                let token_account = TokenAccount::unpack(&account.data)
+                    .map_err(|e| format!("token account {}: {}", account.pubkey, e))?;
+                if token_account.mint != *mint {
+                    continue;
+                }
+                let balance = &mut holdings.entry(token_account.owner).or_default().0;
+                *balance = balance
+                    .checked_add(token_account.amount)
+                    .ok_or_else(|| format!("balance of {} overflows u64", token_account.owner))?;
+            } else if account.owner == *program_id && is_stake_entry(&account.data) {
+                let entry: StakeEntry = unpack_state(&account.data)
+                    .map_err(|e| format!("stake entry {}: {}", account.pubkey, e))?;
+                let (expected, _) = Processor::get_stake_entry_pda(program_id, &config, &entry.staker);
+                if account.pubkey != expected {
+                    continue;
+                }
+                holdings.entry(entry.staker).or_default().1 = entry.amount;
+            }
+        }
+
+        let holders = holdings
+            .into_iter()
+            .filter(|(_, (balance, staked))| *balance > 0 || *staked > 0)
+            .map(|(wallet, (balance, staked))| Holder { wallet, balance, staked })
+            .collect();
+        Ok(Self { mint: *mint, slot, holders })
+    }
+
+    /// SHA-256 of the Borsh-encoded snapshot
+    pub fn hash(&self) -> [u8; 32] {
+        // Writing into a Vec can't fail
+        hash(&self.try_to_vec().unwrap()).to_bytes()
+    }
+
+    /// Sum of all balances
+    pub fn total_balance(&self) -> u128 {
+        self.holders.iter().map(|holder| holder.balance as u128).sum()
+    }
+
+    /// Sum of all stakes
+    pub fn total_staked(&self) -> u128 {
+        self.holders.iter().map(|holder| holder.staked as u128).sum()
+    }
+}
+
+fn is_stake_entry(data: &[u8]) -> bool {
+    matches!(
+        AccountHeader::read(data),
+        Some(AccountHeader { account_type: AccountType::StakeEntry, .. })
+    )
+}
+
+/// Read `getProgramAccounts` results (base64 encoded), optionally wrapped in a
+/// JSON-RPC response. Returns the accounts and the context slot, if the dump
+/// was taken `withContext`.
+#[cfg(feature = "cli")]
+pub fn parse_program_accounts_json(json: &str) -> Result<(Vec<RawAccount>, Option<u64>), String> {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde_json::Value;
+    use std::str::FromStr;
+
+    fn account(value: &Value) -> Result<RawAccount, String> {
+        let pubkey = value["pubkey"].as_str().ok_or("account is missing `pubkey`")?;
+        let account = &value["account"];
+        let owner = account["owner"].as_str().ok_or("account is missing `owner`")?;
+        let data = match &account["data"] {
+            Value::Array(data) if data.get(1).and_then(Value::as_str) == Some("base64") => {
+                data[0].as_str().ok_or("account data is not a string")?
+            }
+            _ => return Err(format!("account {} data must be base64 encoded", pubkey)),
+        };
+        Ok(RawAccount {
+            pubkey: Pubkey::from_str(pubkey).map_err(|_| format!("invalid pubkey `{}`", pubkey))?,
+            owner: Pubkey::from_str(owner).map_err(|_| format!("invalid owner `{}`", owner))?,
+            data: STANDARD
+                .decode(data)
+                .map_err(|e| format!("account {} data: {}", pubkey, e))?,
+        })
+    }
+
+    let value: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let value = value.get("result").unwrap_or(&value);
+    let slot = value["context"]["slot"].as_u64();
+    let accounts = value.get("value").unwrap_or(value);
+    let accounts = accounts.as_array().ok_or("expected an array of accounts")?;
+    Ok((accounts.iter().map(account).collect::<Result<_, _>>()?, slot))
+}
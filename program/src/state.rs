@@ -1,12 +1,74 @@
 //! Account state definitions for the E9th Token Program
 
-use borsh::{BorshDeserialize, BorshSerialize};
+use crate::error::E9thTokenError;
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
 use solana_program::{
     account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey,
 };
 
+/// Discriminator stored in the first byte of every program account.
+/// Values are part of the account layout and must never be reordered.
+#[repr(u8)]
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountType {
+    Uninitialized,
+    TokenConfig,
+    Blacklist,
+    StakeEntry,
+    AirdropRound,
+    Sale,
+    SalePurchase,
+    ProgramState,
+    StakeAccount,
+    TierConfig,
+    MembershipPass,
+    Merchant,
+    Voucher,
+    Subscription,
+    Launch,
+}
+
+/// Header at the start of every program account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountHeader {
+    /// Which state type follows the header
+    pub account_type: AccountType,
+    /// Layout version of that state type
+    pub version: u8,
+}
+
+impl AccountHeader {
+    pub const LEN: usize = 2;
+
+    /// Read the header of `data`, if it has one with a known account type
+    pub fn read(data: &[u8]) -> Option<Self> {
+        let account_type = AccountType::try_from_slice(data.get(..1)?).ok()?;
+        Some(Self { account_type, version: data[1..].first().copied()? })
+    }
+}
+
+/// State types stored behind an [AccountHeader]
+pub trait AccountState: BorshSerialize + BorshDeserialize {
+    const ACCOUNT_TYPE: AccountType;
+    /// Current layout version. Bump it and handle the previous layout in
+    /// `upgrade` whenever fields are added.
+    const VERSION: u8;
+
+    /// Account size needed for this value, including the header
+    fn account_len(&self) -> usize;
+
+    /// Decode the body of a `version` layout into the current one. Version 0 is
+    /// the original layout, stored without a header.
+    fn upgrade(version: u8, body: &[u8]) -> Result<Self, ProgramError> {
+        if version > Self::VERSION {
+            return Err(E9thTokenError::UnsupportedAccountVersion.into());
+        }
+        Self::deserialize(&mut &body[..]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+}
+
 /// Token configuration account
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone)]
 pub struct TokenConfig {
     /// Multisig or super admin
     pub owner: Pubkey,
@@ -24,7 +86,7 @@ pub struct TokenConfig {
     pub total_supply: u64,
     /// Staking enabled
     pub staking_enabled: bool,
-    /// Reward rate per epoch (in basis points)
+    /// Annual reward rate (in basis points), accrued per second
     pub reward_rate: u16,
     /// Minimum stake period (in epochs)
     pub min_stake_period: u64,
@@ -34,10 +96,41 @@ pub struct TokenConfig {
     pub total_staked: u64,
     /// Bump seed for PDA
     pub bump: u8,
+    /// Whether legacy instructions are rejected (mirrored into the program state)
+    pub legacy_disabled: bool,
+    /// Cumulative burns by source
+    pub burn_stats: BurnStats,
+}
+
+/// Cumulative burned amounts, split by what burned them
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BurnStats {
+    /// Burned by the transfer tax (`burn_rate_basis_points`)
+    pub transfer_tax: u64,
+    /// Burned by the admin's manual `Burn`
+    pub manual: u64,
+    /// Burned as early unstake penalties (no instruction burns penalties yet)
+    pub penalty: u64,
+    /// Burned by buyback-and-burn (no instruction burns buybacks yet)
+    pub buyback: u64,
+    /// Unix timestamp of the latest burn (0 = never)
+    pub last_burn_timestamp: u64,
+    /// Burned by holders for utility, such as burn vouchers
+    pub utility: u64,
+}
+
+/// What burned tokens, selecting the [BurnStats] counter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BurnKind {
+    TransferTax,
+    Manual,
+    Penalty,
+    Buyback,
+    Utility,
 }
 
 /// Blacklist account
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone)]
 pub struct Blacklist {
     /// List of blacklisted accounts
     pub accounts: Vec<Pubkey>,
@@ -46,7 +139,7 @@ pub struct Blacklist {
 }
 
 /// Enhanced stake entry
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone)]
 pub struct StakeEntry {
     /// Staker's public key
     pub staker: Pubkey,
@@ -54,7 +147,7 @@ pub struct StakeEntry {
     pub amount: u64,
     /// Last reward timestamp
     pub last_reward_timestamp: u64,
-    /// Stake start epoch
+    /// Epoch of the latest stake into the entry, which holding periods count from
     pub start_epoch: u64,
     /// Stake period (in epochs)
     pub period: u64,
@@ -64,10 +157,357 @@ pub struct StakeEntry {
     pub lock_time: u64,
     /// Bump seed for PDA
     pub bump: u8,
+    /// Rewards accrued by a migrated legacy stake, paid on top of time-based rewards
+    pub carried_rewards: u64,
+}
+
+/// Optional anti-sybil requirements for airdrop claims
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, Default, PartialEq)]
+pub struct AirdropGate {
+    /// Minimum E9TH the claimant must have staked (0 = disabled). Only escrowed
+    /// stake counts: a wallet balance can be borrowed for the claim.
+    pub min_balance: u64,
+    /// Minimum epochs since the claimant last added to its stake (0 = disabled)
+    pub min_holding_epochs: u64,
+    /// Key that must sign an ed25519 attestation for the claim (default = disabled)
+    pub verifier: Pubkey,
+}
+
+/// What `CreateAirdropRound` sets on a new round
+#[derive(Debug, Clone, PartialEq)]
+pub struct AirdropRoundParams {
+    pub round_id: u64,
+    pub merkle_root: [u8; 32],
+    pub total_amount: u64,
+    pub num_recipients: u64,
+    pub expiry_timestamp: u64,
+    pub gate: AirdropGate,
+}
+
+/// Merkle airdrop round
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone)]
+pub struct AirdropRound {
+    /// Round identifier (part of the PDA seeds)
+    pub round_id: u64,
+    /// Merkle root over (index, claimant, amount) leaves
+    pub merkle_root: [u8; 32],
+    /// Total tokens allocated to the round
+    pub total_amount: u64,
+    /// Total tokens claimed so far
+    pub total_claimed: u64,
+    /// Number of leaves in the tree
+    pub num_recipients: u64,
+    /// Unix timestamp after which unclaimed tokens can be clawed back
+    pub expiry_timestamp: u64,
+    /// Token account holding the round's tokens (owned by the round PDA)
+    pub vault: Pubkey,
+    /// Whether unclaimed tokens were returned to the treasury
+    pub clawed_back: bool,
+    /// Bump seed for PDA
+    pub bump: u8,
+    /// Anti-sybil requirements checked on every claim
+    pub gate: AirdropGate,
+    /// Claim status bitmap, one bit per leaf index
+    pub claimed_bitmap: Vec<u8>,
+}
+
+/// Public sale terms, fixed when the sale is created
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq)]
+pub struct SaleTerms {
+    /// Sale opening time (unix timestamp)
+    pub start_timestamp: u64,
+    /// Sale closing time (unix timestamp)
+    pub end_timestamp: u64,
+    /// End of the whitelist-only phase (at or before start disables the phase)
+    pub whitelist_end_timestamp: u64,
+    /// Merkle root over (index, wallet, allocation) whitelist leaves
+    pub whitelist_root: [u8; 32],
+    /// Quote token mint, or the default pubkey to pay in lamports
+    pub quote_mint: Pubkey,
+    /// Quote units paid per `price_token_amount` E9TH base units
+    pub price_quote_amount: u64,
+    /// E9TH base units received per `price_quote_amount` quote units
+    pub price_token_amount: u64,
+    /// Minimum E9TH per purchase
+    pub min_purchase: u64,
+    /// Maximum E9TH per wallet across the whole sale
+    pub max_purchase: u64,
+    /// Maximum E9TH sold across all wallets
+    pub hard_cap: u64,
+    /// Minimum E9TH sold for the sale to succeed (0 disables refunds)
+    pub soft_cap: u64,
+}
+
+/// Public sale account
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone)]
+pub struct Sale {
+    /// Sale identifier (part of the PDA seeds)
+    pub sale_id: u64,
+    /// Sale terms
+    pub terms: SaleTerms,
+    /// E9TH token account owned by the sale PDA
+    pub vault: Pubkey,
+    /// Quote token account owned by the sale PDA (default when paid in lamports)
+    pub quote_vault: Pubkey,
+    /// Total E9TH sold
+    pub total_sold: u64,
+    /// Total quote units raised
+    pub total_raised: u64,
+    /// Total quote units refunded after a failed sale
+    pub total_refunded: u64,
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+/// Per-wallet purchase record for a sale
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone)]
+pub struct SalePurchase {
+    /// Buyer's public key
+    pub buyer: Pubkey,
+    /// Sale account
+    pub sale: Pubkey,
+    /// Total E9TH bought
+    pub amount_bought: u64,
+    /// Total quote units paid
+    pub amount_paid: u64,
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+/// Utility tier of a wallet, from its held plus staked E9TH. Ordered, so
+/// `tier >= UtilityTier::Silver` reads as "Silver or better".
+#[repr(u8)]
+#[derive(
+    BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub enum UtilityTier {
+    None,
+    Bronze,
+    Silver,
+    Gold,
+}
+
+/// Minimum held plus staked E9TH for each utility tier
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, Copy, Default, PartialEq)]
+pub struct TierThresholds {
+    pub bronze: u64,
+    pub silver: u64,
+    pub gold: u64,
+}
+
+/// Utility tier thresholds of a token config
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone)]
+pub struct TierConfig {
+    /// Token config the thresholds apply to
+    pub config: Pubkey,
+    /// Current thresholds
+    pub thresholds: TierThresholds,
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+/// Non-transferable membership pass, one per holder and config (PDA:
+/// ["membership", config, holder]). Issued when the holder's escrowed stake
+/// reaches a tier and kept in line with it on every enhanced stake, unstake
+/// and stake migration. Unlike `VerifyTier`, wallet balances don't count: no
+/// instruction runs when they change, so the pass couldn't follow them.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone)]
+pub struct MembershipPass {
+    /// Token config the pass belongs to
+    pub config: Pubkey,
+    /// Wallet the pass was issued to
+    pub holder: Pubkey,
+    /// Tier of the holder's escrowed stake (`None` once it drops below Bronze)
+    pub tier: UtilityTier,
+    /// Escrowed stake the tier was computed from
+    pub staked: u64,
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+/// What happens to the E9TH a holder puts up for a voucher
+#[repr(u8)]
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoucherMode {
+    /// Held in the merchant's voucher vault and returned on redemption
+    Lock,
+    /// Burned when the voucher is minted
+    Burn,
+}
+
+/// How a merchant's `Pay` receipts are split, in basis points. The holder's
+/// tier discount comes off the list price first; the burn share is then taken
+/// from what the holder pays and the rest goes to the merchant.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PaymentTerms {
+    /// Share of each payment burned
+    pub burn_basis_points: u16,
+    /// Discount for Bronze holders
+    pub bronze_discount_basis_points: u16,
+    /// Discount for Silver holders
+    pub silver_discount_basis_points: u16,
+    /// Discount for Gold holders
+    pub gold_discount_basis_points: u16,
+}
+
+/// Split of one payment under a merchant's [PaymentTerms]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaymentQuote {
+    /// Taken off the list price for the holder's tier
+    pub discount: u64,
+    /// Burned from what the holder pays
+    pub burned: u64,
+    /// Transferred to the merchant
+    pub paid: u64,
+}
+
+/// Merchant registered by the operator (PDA: ["merchant", config, authority])
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone)]
+pub struct Merchant {
+    /// Token config the merchant is registered with
+    pub config: Pubkey,
+    /// Merchant's signing authority
+    pub authority: Pubkey,
+    /// Token account holding locked voucher E9TH (owned by the merchant PDA)
+    pub vault: Pubkey,
+    /// E9TH a holder locks or burns per voucher
+    pub voucher_cost: u64,
+    /// Whether voucher E9TH is locked or burned
+    pub voucher_mode: VoucherMode,
+    /// Whether holders can mint new vouchers
+    pub active: bool,
+    /// Vouchers minted so far
+    pub vouchers_issued: u64,
+    /// Vouchers redeemed so far
+    pub vouchers_redeemed: u64,
+    /// Bump seed for PDA
+    pub bump: u8,
+    /// Discounts and burn share applied to `Pay`
+    pub payment_terms: PaymentTerms,
+}
+
+/// Discount voucher minted by a holder for one merchant (PDA:
+/// ["voucher", merchant, holder, voucher_id])
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone)]
+pub struct Voucher {
+    /// Merchant the voucher is redeemable with
+    pub merchant: Pubkey,
+    /// Holder who minted the voucher
+    pub holder: Pubkey,
+    /// Holder-chosen identifier (part of the PDA seeds)
+    pub voucher_id: u64,
+    /// E9TH locked or burned for the voucher
+    pub amount: u64,
+    /// Whether `amount` was locked or burned
+    pub mode: VoucherMode,
+    /// Unix timestamp of the mint
+    pub issued_at: u64,
+    /// Whether the merchant has redeemed the voucher
+    pub redeemed: bool,
+    /// Unix timestamp of the redemption (0 = not redeemed)
+    pub redeemed_at: u64,
+    /// Bump seed for PDA
+    pub bump: u8,
+    /// Unix timestamp from which the voucher can't be redeemed, and a locked
+    /// voucher's E9TH can be reclaimed by the holder
+    pub expires_at: u64,
+    /// Whether the holder took the locked E9TH back with `ReclaimVoucher`
+    pub reclaimed: bool,
+}
+
+/// What a subscriber pre-approves a merchant to pull, fixed at creation
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriptionTerms {
+    /// E9TH pulled per period
+    pub amount: u64,
+    /// Seconds between collections
+    pub period_seconds: u64,
+    /// Number of periods the merchant may collect
+    pub max_periods: u64,
+}
+
+/// Recurring payment a subscriber pre-approved for one merchant (PDA:
+/// ["subscription", merchant, subscriber, subscription_id]). The PDA is the
+/// SPL delegate of the subscriber's vault token account, so collections are
+/// signed by the program.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone)]
+pub struct Subscription {
+    /// Merchant that collects the payments
+    pub merchant: Pubkey,
+    /// Wallet that owns the vault
+    pub subscriber: Pubkey,
+    /// Subscriber-chosen identifier (part of the PDA seeds)
+    pub subscription_id: u64,
+    /// Subscriber's token account the subscription is the delegate of
+    pub vault: Pubkey,
+    /// Amount, period and number of periods
+    pub terms: SubscriptionTerms,
+    /// Periods collected so far
+    pub periods_collected: u64,
+    /// Unix timestamp from which the next period can be collected
+    pub next_collection_at: u64,
+    /// Whether the subscriber cancelled
+    pub cancelled: bool,
+    /// Bump seed for PDA
+    pub bump: u8,
+}
+
+/// How a launch orders its early-access reservations
+#[repr(u8)]
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaunchPriority {
+    /// In reservation order; reservations stop once the launch is full
+    FirstCome,
+    /// By escrowed stake entry, then reservation order; a larger stake takes
+    /// the lowest ranked slot of a full launch
+    StakeWeight,
+}
+
+/// Launch terms, fixed when the operator creates the launch
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LaunchTerms {
+    /// Number of early-access slots
+    pub capacity: u32,
+    /// Public opening (unix timestamp); reservations close at this time
+    pub open_timestamp: u64,
+    /// Minimum utility tier of the wallet's staked E9TH to reserve
+    pub min_tier: UtilityTier,
+    /// How reservations are ordered
+    pub priority: LaunchPriority,
+}
+
+/// Early-access slot held by a wallet
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct LaunchReservation {
+    pub wallet: Pubkey,
+    /// Stake at reservation time (0 for first-come launches); not updated if
+    /// the wallet unstakes later, so partners re-check the stake entry
+    pub weight: u64,
+    /// Reservation order across the launch, ties go to the lower sequence
+    pub sequence: u64,
+}
+
+/// Product launch with an early-access queue (PDA: ["launch", config,
+/// launch_id]). `reservations` is kept in rank order, so the account data is
+/// the queue as partners read it.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone)]
+pub struct Launch {
+    /// Token config the launch belongs to
+    pub config: Pubkey,
+    /// Launch identifier (part of the PDA seeds)
+    pub launch_id: u64,
+    /// Capacity, opening time and access rules
+    pub terms: LaunchTerms,
+    /// Sequence number of the next reservation
+    pub next_sequence: u64,
+    /// Bump seed for PDA
+    pub bump: u8,
+    /// Reserved slots, highest ranked first
+    pub reservations: Vec<LaunchReservation>,
 }
 
 /// Program state account (legacy - keeping for compatibility)
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone)]
 pub struct ProgramState {
     /// Admin authority
     pub admin: Pubkey,
@@ -87,10 +527,43 @@ pub struct ProgramState {
     pub total_staked: u64,
     /// Bump seed for PDA
     pub bump: u8,
+    /// Whether legacy instructions are rejected, set through the token config
+    pub legacy_disabled: bool,
+    /// Whether `MigrateProgramState` created the token config, which legacy
+    /// `Burn` must then update
+    pub migrated: bool,
+}
+
+impl BurnStats {
+    pub const LEN: usize = 8 + 8 + 8 + 8 + 8 + 8; // 48 bytes
+
+    /// Total burned across all sources
+    pub fn total(&self) -> u64 {
+        self.transfer_tax
+            .saturating_add(self.manual)
+            .saturating_add(self.penalty)
+            .saturating_add(self.buyback)
+            .saturating_add(self.utility)
+    }
+
+    /// Add a burn of `amount` to the `kind` counter
+    pub fn record(&mut self, kind: BurnKind, amount: u64, timestamp: u64) -> Result<(), ProgramError> {
+        let counter = match kind {
+            BurnKind::TransferTax => &mut self.transfer_tax,
+            BurnKind::Manual => &mut self.manual,
+            BurnKind::Penalty => &mut self.penalty,
+            BurnKind::Buyback => &mut self.buyback,
+            BurnKind::Utility => &mut self.utility,
+        };
+        *counter = counter.checked_add(amount).ok_or(E9thTokenError::MathOverflow)?;
+        self.last_burn_timestamp = timestamp;
+        Ok(())
+    }
 }
 
 impl TokenConfig {
-    pub const LEN: usize = 32 + 32 + 32 + 1 + 2 + 32 + 8 + 1 + 2 + 8 + 8 + 8 + 1; // 201 bytes
+    pub const LEN: usize =
+        AccountHeader::LEN + 32 + 32 + 32 + 1 + 2 + 32 + 8 + 1 + 2 + 8 + 8 + 8 + 1 + 1 + BurnStats::LEN; // 218 bytes
 
     pub fn new(
         owner: Pubkey,
@@ -109,17 +582,38 @@ impl TokenConfig {
             mint,
             total_supply: 0,
             staking_enabled: true,
-            reward_rate: 100, // 1% per epoch
+            reward_rate: 100, // 1% per year
             min_stake_period: 1,
             max_stake_period: 365,
             total_staked: 0,
             bump,
+            legacy_disabled: false,
+            burn_stats: BurnStats::default(),
+        }
+    }
+
+    /// Carry a legacy program state's staking settings and supply over to a new
+    /// config. `total_staked` starts at zero and grows as stakes are migrated.
+    pub fn from_program_state(
+        state: &ProgramState,
+        operator: Pubkey,
+        treasury: Pubkey,
+        burn_rate_basis_points: u16,
+        bump: u8,
+    ) -> Self {
+        Self {
+            total_supply: state.total_supply,
+            staking_enabled: state.staking_enabled,
+            reward_rate: state.reward_rate,
+            min_stake_period: state.min_stake_period,
+            max_stake_period: state.max_stake_period,
+            ..Self::new(state.admin, operator, treasury, state.mint, burn_rate_basis_points, bump)
         }
     }
 }
 
 impl Blacklist {
-    pub const LEN: usize = 4 + (32 * 100) + 1; // 4 bytes for Vec length + 100 accounts max + bump
+    pub const LEN: usize = AccountHeader::LEN + 4 + (32 * 100) + 1; // header + Vec length + 100 accounts max + bump
 
     pub fn new(bump: u8) -> Self {
         Self {
@@ -143,9 +637,409 @@ impl Blacklist {
     }
 }
 
+impl AirdropGate {
+    pub const LEN: usize = 8 + 8 + 32; // 48 bytes
+
+    /// Whether claimants must present an active stake entry
+    pub fn requires_stake(&self) -> bool {
+        self.min_balance > 0 || self.min_holding_epochs > 0
+    }
+
+    /// Whether claimants must present a verifier attestation
+    pub fn requires_attestation(&self) -> bool {
+        self.verifier != Pubkey::default()
+    }
+}
+
+impl AirdropRound {
+    /// Size without the claim bitmap bytes
+    pub const BASE_LEN: usize =
+        AccountHeader::LEN + 8 + 32 + 8 + 8 + 8 + 8 + 32 + 1 + 1 + AirdropGate::LEN + 4; // 160 bytes
+
+    /// Most leaves a round can have. The round account is created through a
+    /// CPI, which caps it at 10 KiB; this keeps the claim bitmap at 8 KiB.
+    pub const MAX_RECIPIENTS: u64 = 65_536;
+
+    /// Account size for a round with `num_recipients` leaves
+    pub fn space(num_recipients: u64) -> usize {
+        Self::BASE_LEN + Self::bitmap_len(num_recipients)
+    }
+
+    fn bitmap_len(num_recipients: u64) -> usize {
+        num_recipients.div_ceil(8) as usize
+    }
+
+    pub fn new(params: AirdropRoundParams, vault: Pubkey, bump: u8) -> Self {
+        Self {
+            round_id: params.round_id,
+            merkle_root: params.merkle_root,
+            total_amount: params.total_amount,
+            total_claimed: 0,
+            num_recipients: params.num_recipients,
+            expiry_timestamp: params.expiry_timestamp,
+            vault,
+            clawed_back: false,
+            bump,
+            gate: params.gate,
+            claimed_bitmap: vec![0; Self::bitmap_len(params.num_recipients)],
+        }
+    }
+
+    /// Check if the leaf at `index` has been claimed
+    pub fn is_claimed(&self, index: u64) -> bool {
+        let byte = (index / 8) as usize;
+        let bit = 1u8 << (index % 8);
+        self.claimed_bitmap.get(byte).is_some_and(|b| b & bit != 0)
+    }
+
+    /// Mark the leaf at `index` as claimed
+    pub fn set_claimed(&mut self, index: u64) {
+        let byte = (index / 8) as usize;
+        let bit = 1u8 << (index % 8);
+        if let Some(b) = self.claimed_bitmap.get_mut(byte) {
+            *b |= bit;
+        }
+    }
+
+    /// Check if the claim window has closed
+    pub fn is_expired(&self, current_timestamp: u64) -> bool {
+        current_timestamp >= self.expiry_timestamp
+    }
+}
+
+impl SaleTerms {
+    pub const LEN: usize = 8 + 8 + 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8; // 136 bytes
+
+    /// Whether purchases are paid in lamports rather than an SPL token
+    pub fn is_lamport_quote(&self) -> bool {
+        self.quote_mint == Pubkey::default()
+    }
+
+    /// Quote units owed for `amount` E9TH base units, rounded up
+    pub fn quote_for(&self, amount: u64) -> Option<u64> {
+        if self.price_token_amount == 0 {
+            return None;
+        }
+        let numerator = (amount as u128).checked_mul(self.price_quote_amount as u128)?;
+        let cost = numerator.div_ceil(self.price_token_amount as u128);
+        u64::try_from(cost).ok()
+    }
+
+    /// Check if the sale accepts purchases at `current_timestamp`
+    pub fn is_open(&self, current_timestamp: u64) -> bool {
+        current_timestamp >= self.start_timestamp && current_timestamp < self.end_timestamp
+    }
+
+    /// Check if only whitelisted wallets may buy at `current_timestamp`
+    pub fn is_whitelist_phase(&self, current_timestamp: u64) -> bool {
+        current_timestamp < self.whitelist_end_timestamp
+    }
+
+    /// Check if the sale has closed
+    pub fn has_ended(&self, current_timestamp: u64) -> bool {
+        current_timestamp >= self.end_timestamp
+    }
+}
+
+impl Sale {
+    pub const LEN: usize = AccountHeader::LEN + 8 + SaleTerms::LEN + 32 + 32 + 8 + 8 + 8 + 1; // 235 bytes
+
+    pub fn new(sale_id: u64, terms: SaleTerms, vault: Pubkey, quote_vault: Pubkey, bump: u8) -> Self {
+        Self {
+            sale_id,
+            terms,
+            vault,
+            quote_vault,
+            total_sold: 0,
+            total_raised: 0,
+            total_refunded: 0,
+            bump,
+        }
+    }
+
+    /// Check if enough was sold for the sale to succeed
+    pub fn soft_cap_met(&self) -> bool {
+        self.total_sold >= self.terms.soft_cap
+    }
+
+    /// Check if the sale ended below its soft cap, enabling refunds
+    pub fn is_failed(&self, current_timestamp: u64) -> bool {
+        self.terms.has_ended(current_timestamp) && !self.soft_cap_met()
+    }
+}
+
+impl SalePurchase {
+    pub const LEN: usize = AccountHeader::LEN + 32 + 32 + 8 + 8 + 1; // 83 bytes
+
+    pub fn new(buyer: Pubkey, sale: Pubkey, bump: u8) -> Self {
+        Self {
+            buyer,
+            sale,
+            amount_bought: 0,
+            amount_paid: 0,
+            bump,
+        }
+    }
+}
+
+impl TierThresholds {
+    pub const LEN: usize = 8 + 8 + 8; // 24 bytes
+
+    /// Thresholds must be non-zero and strictly increasing
+    pub fn is_valid(&self) -> bool {
+        self.bronze > 0 && self.bronze < self.silver && self.silver < self.gold
+    }
+
+    /// Tier reached by `amount` held plus staked
+    pub fn tier_for(&self, amount: u64) -> UtilityTier {
+        if amount >= self.gold {
+            UtilityTier::Gold
+        } else if amount >= self.silver {
+            UtilityTier::Silver
+        } else if amount >= self.bronze {
+            UtilityTier::Bronze
+        } else {
+            UtilityTier::None
+        }
+    }
+}
+
+impl TierConfig {
+    pub const LEN: usize = AccountHeader::LEN + 32 + TierThresholds::LEN + 1; // 59 bytes
+
+    pub fn new(config: Pubkey, thresholds: TierThresholds, bump: u8) -> Self {
+        Self { config, thresholds, bump }
+    }
+}
+
+impl MembershipPass {
+    pub const LEN: usize = AccountHeader::LEN + 32 + 32 + 1 + 8 + 1; // 76 bytes
+
+    pub fn new(config: Pubkey, holder: Pubkey, tier: UtilityTier, staked: u64, bump: u8) -> Self {
+        Self { config, holder, tier, staked, bump }
+    }
+}
+
+impl PaymentTerms {
+    pub const LEN: usize = 2 + 2 + 2 + 2; // 8 bytes
+
+    /// Every share must be at most 100%
+    pub fn is_valid(&self) -> bool {
+        [
+            self.burn_basis_points,
+            self.bronze_discount_basis_points,
+            self.silver_discount_basis_points,
+            self.gold_discount_basis_points,
+        ]
+        .iter()
+        .all(|&basis_points| basis_points <= 10_000)
+    }
+
+    /// Discount for a holder of `tier`
+    pub fn discount_basis_points(&self, tier: UtilityTier) -> u16 {
+        match tier {
+            UtilityTier::None => 0,
+            UtilityTier::Bronze => self.bronze_discount_basis_points,
+            UtilityTier::Silver => self.silver_discount_basis_points,
+            UtilityTier::Gold => self.gold_discount_basis_points,
+        }
+    }
+
+    /// Split a payment of list price `amount` by a holder of `tier`. Both
+    /// shares round down, in the holder's and then the merchant's favour.
+    pub fn quote(&self, amount: u64, tier: UtilityTier) -> PaymentQuote {
+        // Capped at 100% so an unchecked share can't underflow the price
+        let share = |value: u64, basis_points: u16| {
+            (value as u128 * basis_points.min(10_000) as u128 / 10_000) as u64
+        };
+        let discount = share(amount, self.discount_basis_points(tier));
+        let price = amount - discount;
+        let burned = share(price, self.burn_basis_points);
+        PaymentQuote { discount, burned, paid: price - burned }
+    }
+}
+
+impl Merchant {
+    pub const LEN: usize = AccountHeader::LEN + 32 + 32 + 32 + 8 + 1 + 1 + 8 + 8 + 1 + PaymentTerms::LEN; // 133 bytes
+
+    pub fn new(
+        config: Pubkey,
+        authority: Pubkey,
+        vault: Pubkey,
+        voucher_cost: u64,
+        voucher_mode: VoucherMode,
+        bump: u8,
+    ) -> Self {
+        Self {
+            config,
+            authority,
+            vault,
+            voucher_cost,
+            voucher_mode,
+            active: true,
+            vouchers_issued: 0,
+            vouchers_redeemed: 0,
+            bump,
+            payment_terms: PaymentTerms::default(),
+        }
+    }
+}
+
+impl Voucher {
+    pub const LEN: usize = AccountHeader::LEN + 32 + 32 + 8 + 8 + 1 + 8 + 1 + 8 + 1 + 8 + 1; // 110 bytes
+
+    /// How long a voucher stays redeemable after it is minted
+    pub const VALIDITY: u64 = 90 * 24 * 60 * 60;
+
+    pub fn new(
+        merchant: Pubkey,
+        holder: Pubkey,
+        voucher_id: u64,
+        amount: u64,
+        mode: VoucherMode,
+        issued_at: u64,
+        bump: u8,
+    ) -> Self {
+        Self {
+            merchant,
+            holder,
+            voucher_id,
+            amount,
+            mode,
+            issued_at,
+            redeemed: false,
+            redeemed_at: 0,
+            bump,
+            expires_at: issued_at.saturating_add(Self::VALIDITY),
+            reclaimed: false,
+        }
+    }
+}
+
+impl SubscriptionTerms {
+    pub const LEN: usize = 8 + 8 + 8; // 24 bytes
+
+    /// Every term must be non-zero
+    pub fn is_valid(&self) -> bool {
+        self.amount > 0 && self.period_seconds > 0 && self.max_periods > 0
+    }
+
+    /// Delegated allowance covering every period
+    pub fn total_allowance(&self) -> Option<u64> {
+        self.amount.checked_mul(self.max_periods)
+    }
+}
+
+impl Subscription {
+    pub const LEN: usize = AccountHeader::LEN + 32 + 32 + 8 + 32 + SubscriptionTerms::LEN + 8 + 8 + 1 + 1; // 148 bytes
+
+    /// The first period can be collected from `first_collection_at`
+    pub fn new(
+        merchant: Pubkey,
+        subscriber: Pubkey,
+        subscription_id: u64,
+        vault: Pubkey,
+        terms: SubscriptionTerms,
+        first_collection_at: u64,
+        bump: u8,
+    ) -> Self {
+        Self {
+            merchant,
+            subscriber,
+            subscription_id,
+            vault,
+            terms,
+            periods_collected: 0,
+            next_collection_at: first_collection_at,
+            cancelled: false,
+            bump,
+        }
+    }
+
+    /// Record the collection of one period at `current_timestamp`. Periods
+    /// follow a fixed schedule, so a period collected late doesn't delay the next.
+    pub fn collect(&mut self, current_timestamp: u64) -> Result<(), ProgramError> {
+        if self.cancelled {
+            return Err(E9thTokenError::SubscriptionCancelled.into());
+        }
+        if self.periods_collected >= self.terms.max_periods {
+            return Err(E9thTokenError::SubscriptionComplete.into());
+        }
+        if current_timestamp < self.next_collection_at {
+            return Err(E9thTokenError::SubscriptionPeriodNotElapsed.into());
+        }
+        self.periods_collected += 1;
+        self.next_collection_at = self
+            .next_collection_at
+            .checked_add(self.terms.period_seconds)
+            .ok_or(E9thTokenError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+impl LaunchTerms {
+    pub const LEN: usize = 4 + 8 + 1 + 1; // 14 bytes
+}
+
+impl LaunchReservation {
+    pub const LEN: usize = 32 + 8 + 8; // 48 bytes
+}
+
+impl Launch {
+    /// Size without the reservations
+    pub const BASE_LEN: usize = AccountHeader::LEN + 32 + 8 + LaunchTerms::LEN + 8 + 1 + 4; // 69 bytes
+
+    /// Largest capacity whose account can still be created through CPI
+    pub const MAX_CAPACITY: u32 = 200;
+
+    /// Account size for a launch with `capacity` slots
+    pub fn space(capacity: u32) -> usize {
+        Self::BASE_LEN + capacity as usize * LaunchReservation::LEN
+    }
+
+    pub fn new(config: Pubkey, launch_id: u64, terms: LaunchTerms, bump: u8) -> Self {
+        Self {
+            config,
+            launch_id,
+            terms,
+            next_sequence: 0,
+            bump,
+            reservations: Vec::new(),
+        }
+    }
+
+    /// Rank of `wallet` in the queue (0 is first), if it holds a slot
+    pub fn position(&self, wallet: &Pubkey) -> Option<usize> {
+        self.reservations.iter().position(|reservation| reservation.wallet == *wallet)
+    }
+
+    /// Reserve a slot for `wallet` with `weight`, returning its rank and the
+    /// wallet it bumped from a full launch, if any
+    pub fn reserve(&mut self, wallet: Pubkey, weight: u64) -> Result<(usize, Option<Pubkey>), ProgramError> {
+        if self.position(&wallet).is_some() {
+            return Err(E9thTokenError::AlreadyReserved.into());
+        }
+        let bumped = if self.reservations.len() < self.terms.capacity as usize {
+            None
+        } else {
+            match self.reservations.last() {
+                // Equal weights keep the earlier reservation
+                Some(lowest) if weight > lowest.weight => self.reservations.pop().map(|lowest| lowest.wallet),
+                _ => return Err(E9thTokenError::LaunchFull.into()),
+            }
+        };
+
+        let sequence = self.next_sequence;
+        self.next_sequence = sequence.checked_add(1).ok_or(E9thTokenError::MathOverflow)?;
+        // After every reservation of at least the same weight, which all came earlier
+        let index = self.reservations.partition_point(|reservation| reservation.weight >= weight);
+        self.reservations.insert(index, LaunchReservation { wallet, weight, sequence });
+        Ok((index, bumped))
+    }
+}
 
 impl ProgramState {
-    pub const LEN: usize = 32 + 32 + 8 + 1 + 2 + 8 + 8 + 8 + 1; // 99 bytes
+    pub const LEN: usize = AccountHeader::LEN + 32 + 32 + 8 + 1 + 2 + 8 + 8 + 8 + 1 + 1 + 1; // 104 bytes
 
     pub fn new(admin: Pubkey, mint: Pubkey, bump: u8) -> Self {
         Self {
@@ -158,13 +1052,15 @@ impl ProgramState {
             max_stake_period: 365,
             total_staked: 0,
             bump,
+            legacy_disabled: false,
+            migrated: false,
         }
     }
 }
 
 
 /// Stake account state (legacy - keeping for compatibility)
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone)]
 pub struct StakeAccount {
     /// Owner of the stake
     pub owner: Pubkey,
@@ -181,7 +1077,7 @@ pub struct StakeAccount {
 }
 
 impl StakeEntry {
-    pub const LEN: usize = 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1; // 89 bytes
+    pub const LEN: usize = AccountHeader::LEN + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 8; // 91 bytes
 
     pub fn new(
         staker: Pubkey,
@@ -200,20 +1096,46 @@ impl StakeEntry {
             rewards_claimed: 0,
             lock_time,
             bump,
+            carried_rewards: 0,
         }
     }
 
-    /// Calculate pending rewards based on time
+    /// Convert a mature legacy stake, carrying the rewards a legacy claim would
+    /// pay now. Time-based rewards accrue from `current_timestamp`.
+    pub fn from_legacy(
+        stake: &StakeAccount,
+        current_epoch: u64,
+        current_timestamp: u64,
+        reward_rate: u16,
+        bump: u8,
+    ) -> Self {
+        Self {
+            last_reward_timestamp: current_timestamp,
+            carried_rewards: stake.calculate_rewards(current_epoch, reward_rate),
+            ..Self::new(stake.owner, stake.amount, stake.period, stake.start_epoch, current_timestamp, bump)
+        }
+    }
+
+    /// Calculate pending rewards based on time, plus any carried legacy rewards
     pub fn calculate_rewards(&self, current_timestamp: u64, reward_rate: u16) -> u64 {
         if current_timestamp <= self.last_reward_timestamp {
-            return 0;
+            return self.carried_rewards;
         }
 
+        // Divide once at the end so small stakes and short periods don't round to zero
         let time_elapsed = current_timestamp - self.last_reward_timestamp;
-        let reward_per_second = (self.amount as u128 * reward_rate as u128) / (10000 * 365 * 24 * 60 * 60); // per second
-        let total_rewards = reward_per_second * time_elapsed as u128;
-        
-        total_rewards.saturating_sub(self.rewards_claimed as u128) as u64
+        let total_rewards =
+            self.amount as u128 * reward_rate as u128 * time_elapsed as u128 / (10000 * 365 * 24 * 60 * 60);
+
+        (total_rewards.saturating_sub(self.rewards_claimed as u128) as u64).saturating_add(self.carried_rewards)
+    }
+
+    /// Move pending rewards into `carried_rewards` and restart accrual at
+    /// `current_timestamp`, so a change of `amount` doesn't reprice past time
+    pub fn settle_rewards(&mut self, current_timestamp: u64, reward_rate: u16) {
+        self.carried_rewards = self.calculate_rewards(current_timestamp, reward_rate);
+        self.rewards_claimed = 0;
+        self.last_reward_timestamp = current_timestamp.max(self.last_reward_timestamp);
     }
 
     /// Check if stake is mature
@@ -228,7 +1150,7 @@ impl StakeEntry {
 }
 
 impl StakeAccount {
-    pub const LEN: usize = 32 + 8 + 8 + 8 + 8 + 1; // 65 bytes
+    pub const LEN: usize = AccountHeader::LEN + 32 + 8 + 8 + 8 + 8 + 1; // 67 bytes
 
     pub fn new(owner: Pubkey, amount: u64, period: u64, start_epoch: u64, bump: u8) -> Self {
         Self {
@@ -265,21 +1187,119 @@ impl StakeAccount {
     }
 }
 
-/// Helper function to deserialize account data
-pub fn deserialize_account_data<T: BorshDeserialize>(
+/// Implement [AccountState] for fixed-size state types
+macro_rules! impl_fixed_account_state {
+    ($($ty:ident = $version:literal),* $(,)?) => {
+        $(
+            impl AccountState for $ty {
+                const ACCOUNT_TYPE: AccountType = AccountType::$ty;
+                const VERSION: u8 = $version;
+
+                fn account_len(&self) -> usize {
+                    Self::LEN
+                }
+
+                fn upgrade(version: u8, body: &[u8]) -> Result<Self, ProgramError> {
+                    if version > Self::VERSION {
+                        return Err(E9thTokenError::UnsupportedAccountVersion.into());
+                    }
+                    // Layouts only append fields, so older bodies decode with
+                    // the new fields zeroed
+                    let mut body = body.to_vec();
+                    body.resize(body.len().max(Self::LEN - AccountHeader::LEN), 0);
+                    Self::deserialize(&mut &body[..]).map_err(|_| ProgramError::InvalidAccountData)
+                }
+            }
+        )*
+    };
+}
+
+// Version 1 is the first layout behind a header. The original layouts of the
+// types that predate headers lack the fields appended since (`legacy_disabled`,
+// `burn_stats`, `carried_rewards`, `migrated`), which upgrade zeroed.
+impl_fixed_account_state!(
+    TokenConfig = 1,
+    Blacklist = 1,
+    StakeEntry = 1,
+    Sale = 1,
+    SalePurchase = 1,
+    ProgramState = 1,
+    StakeAccount = 1,
+    TierConfig = 1,
+    MembershipPass = 1,
+    Merchant = 1,
+    Voucher = 1,
+    Subscription = 1,
+);
+
+impl AccountState for Launch {
+    const ACCOUNT_TYPE: AccountType = AccountType::Launch;
+    const VERSION: u8 = 1;
+
+    fn account_len(&self) -> usize {
+        Self::space(self.terms.capacity)
+    }
+}
+
+impl AccountState for AirdropRound {
+    const ACCOUNT_TYPE: AccountType = AccountType::AirdropRound;
+    const VERSION: u8 = 1;
+
+    fn account_len(&self) -> usize {
+        Self::space(self.num_recipients)
+    }
+}
+
+/// Helper function to deserialize account data, checking its header
+pub fn deserialize_account_data<T: AccountState>(
     account_info: &AccountInfo,
 ) -> Result<T, ProgramError> {
-    let data = account_info.try_borrow_data()?;
-    T::try_from_slice(&data).map_err(|_| ProgramError::InvalidAccountData)
+    unpack_state(&account_info.try_borrow_data()?)
+}
+
+/// Decode `T` from account data that may be larger than the serialized state.
+/// The bytes after the state must be zero padding.
+pub fn unpack_state<T: AccountState>(data: &[u8]) -> Result<T, ProgramError> {
+    check_account_header::<T>(data)?;
+    let mut body = &data[AccountHeader::LEN..];
+    let value = T::deserialize(&mut body).map_err(|_| ProgramError::InvalidAccountData)?;
+    if body.iter().any(|byte| *byte != 0) {
+        return Err(E9thTokenError::StaleAccountData.into());
+    }
+    Ok(value)
 }
 
-/// Helper function to serialize account data
-pub fn serialize_account_data<T: BorshSerialize>(
+/// Check that `data` starts with the current header of `T`
+pub fn check_account_header<T: AccountState>(data: &[u8]) -> Result<(), ProgramError> {
+    let header = AccountHeader::read(data).ok_or(ProgramError::InvalidAccountData)?;
+    if header.account_type != T::ACCOUNT_TYPE {
+        return Err(E9thTokenError::AccountTypeMismatch.into());
+    }
+    if header.version != T::VERSION {
+        // Older layouts must go through MigrateAccount first
+        return Err(E9thTokenError::UnsupportedAccountVersion.into());
+    }
+    Ok(())
+}
+
+/// Helper function to serialize account data behind its header
+pub fn serialize_account_data<T: AccountState>(
     account_info: &AccountInfo,
     data: &T,
 ) -> Result<(), ProgramError> {
-    let mut account_data = account_info.try_borrow_mut_data()?;
-    let serialized = data.try_to_vec().map_err(|_| ProgramError::InvalidAccountData)?;
-    account_data[..serialized.len()].copy_from_slice(&serialized);
+    pack_state(data, &mut account_info.try_borrow_mut_data()?)
+}
+
+/// Encode `value` with its header at the start of `data` and zero the rest, so
+/// a state that shrank (e.g. a blacklist entry removed) leaves no stale bytes
+pub fn pack_state<T: AccountState>(value: &T, data: &mut [u8]) -> Result<(), ProgramError> {
+    let mut serialized = vec![T::ACCOUNT_TYPE as u8, T::VERSION];
+    value.serialize(&mut serialized).map_err(|_| ProgramError::InvalidAccountData)?;
+    if serialized.len() > data.len() {
+        return Err(E9thTokenError::AccountDataTooSmall.into());
+    }
+    let (state, tail) = data.split_at_mut(serialized.len());
+    state.copy_from_slice(&serialized);
+    tail.fill(0);
     Ok(())
 }
@@ -0,0 +1,305 @@
+//! Recurring subscriptions for the E9th Token Program
+//!
+//! A subscriber pre-approves a merchant to pull a fixed amount of E9TH every
+//! period, for up to a maximum number of periods. The subscription PDA is made
+//! the SPL delegate of a vault token account the subscriber owns, for the full
+//! allowance; a token account has one delegate, so each subscription needs its
+//! own vault. Anyone can collect a period once it is due, and the subscriber
+//! can cancel at any time, which revokes the delegation.
+
+use crate::{
+    error::E9thTokenError,
+    events::{E9thEvent, SubscriptionCancelled, SubscriptionCollected, SubscriptionCreated},
+    merchant::MerchantProcessor,
+    processor::load_token_config,
+    state::{deserialize_account_data, serialize_account_data, Subscription, SubscriptionTerms},
+    validation::{check_system_program, check_token_program, AccountRules},
+};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+use spl_token::{
+    instruction::{approve, revoke, transfer},
+    state::Account as TokenAccount,
+};
+
+pub struct SubscriptionProcessor;
+
+impl SubscriptionProcessor {
+    /// Create a subscription and delegate its allowance from the subscriber's vault
+    pub fn process_create(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        subscription_id: u64,
+        terms: SubscriptionTerms,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let merchant_account = next_account_info(account_info_iter)?;
+        let subscription_account = next_account_info(account_info_iter)?;
+        let subscriber_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        let config = load_token_config(program_id, config_account)?;
+        AccountRules::new().signer().writable().check(subscriber_account)?;
+        check_token_program(token_program)?;
+        check_system_program(system_program)?;
+        if !terms.is_valid() {
+            return Err(E9thTokenError::InvalidSubscriptionTerms.into());
+        }
+        let allowance = terms.total_allowance().ok_or(E9thTokenError::MathOverflow)?;
+
+        let merchant = MerchantProcessor::load_merchant(program_id, config_account.key, merchant_account)?;
+        if !merchant.active {
+            return Err(E9thTokenError::MerchantInactive.into());
+        }
+
+        let (subscription_pda, bump) = Self::get_subscription_pda(
+            program_id,
+            merchant_account.key,
+            subscriber_account.key,
+            subscription_id,
+        );
+        AccountRules::new().writable().address(subscription_pda).check(subscription_account)?;
+        if !subscription_account.data_is_empty() {
+            return Err(E9thTokenError::AccountAlreadyInitialized.into());
+        }
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(subscriber_account.key)
+            .check(vault_account)?;
+
+        let now = Clock::get()?.unix_timestamp as u64;
+        let lamports = Rent::get()?.minimum_balance(Subscription::LEN);
+        invoke_signed(
+            &system_instruction::create_account(
+                subscriber_account.key,
+                subscription_account.key,
+                lamports,
+                Subscription::LEN as u64,
+                program_id,
+            ),
+            &[
+                subscriber_account.clone(),
+                subscription_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"subscription",
+                merchant_account.key.as_ref(),
+                subscriber_account.key.as_ref(),
+                &subscription_id.to_le_bytes(),
+                &[bump],
+            ]],
+        )?;
+        invoke(
+            &approve(
+                token_program.key,
+                vault_account.key,
+                subscription_account.key,
+                subscriber_account.key,
+                &[],
+                allowance,
+            )?,
+            &[
+                vault_account.clone(),
+                subscription_account.clone(),
+                subscriber_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        // The first period is due straight away
+        let subscription = Subscription::new(
+            *merchant_account.key,
+            *subscriber_account.key,
+            subscription_id,
+            *vault_account.key,
+            terms,
+            now,
+            bump,
+        );
+        serialize_account_data(subscription_account, &subscription)?;
+
+        E9thEvent::SubscriptionCreated(SubscriptionCreated {
+            subscription: *subscription_account.key,
+            merchant: *merchant_account.key,
+            subscriber: *subscriber_account.key,
+            terms,
+        })
+        .emit();
+        Ok(())
+    }
+
+    /// Pull one due period from the vault to the merchant (permissionless)
+    pub fn process_collect(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let merchant_account = next_account_info(account_info_iter)?;
+        let subscription_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let merchant_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        let config = load_token_config(program_id, config_account)?;
+        if config.is_paused {
+            return Err(E9thTokenError::TokenPaused.into());
+        }
+        check_token_program(token_program)?;
+        let merchant = MerchantProcessor::load_merchant(program_id, config_account.key, merchant_account)?;
+        if !merchant.active {
+            return Err(E9thTokenError::MerchantInactive.into());
+        }
+
+        let mut subscription = Self::load_subscription(program_id, subscription_account)?;
+        if subscription.merchant != *merchant_account.key {
+            return Err(E9thTokenError::UnexpectedAccount.into());
+        }
+        AccountRules::new().writable().address(subscription.vault).check(vault_account)?;
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(&merchant.authority)
+            .check(merchant_token_account)?;
+
+        subscription.collect(Clock::get()?.unix_timestamp as u64)?;
+        invoke_signed(
+            &transfer(
+                token_program.key,
+                vault_account.key,
+                merchant_token_account.key,
+                subscription_account.key,
+                &[],
+                subscription.terms.amount,
+            )?,
+            &[
+                vault_account.clone(),
+                merchant_token_account.clone(),
+                subscription_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[
+                b"subscription",
+                subscription.merchant.as_ref(),
+                subscription.subscriber.as_ref(),
+                &subscription.subscription_id.to_le_bytes(),
+                &[subscription.bump],
+            ]],
+        )?;
+        serialize_account_data(subscription_account, &subscription)?;
+
+        E9thEvent::SubscriptionCollected(SubscriptionCollected {
+            subscription: *subscription_account.key,
+            merchant: subscription.merchant,
+            subscriber: subscription.subscriber,
+            amount: subscription.terms.amount,
+            periods_collected: subscription.periods_collected,
+        })
+        .emit();
+        Ok(())
+    }
+
+    /// Cancel a subscription (subscriber only) and revoke its delegation
+    pub fn process_cancel(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let subscription_account = next_account_info(account_info_iter)?;
+        let subscriber_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        let mut subscription = Self::load_subscription(program_id, subscription_account)?;
+        AccountRules::new().signer().check(subscriber_account)?;
+        if *subscriber_account.key != subscription.subscriber {
+            return Err(E9thTokenError::Unauthorized.into());
+        }
+        if subscription.cancelled {
+            return Err(E9thTokenError::SubscriptionCancelled.into());
+        }
+        check_token_program(token_program)?;
+        AccountRules::new().writable().address(subscription.vault).check(vault_account)?;
+
+        // The vault may have been closed or re-delegated since; only revoke our own delegation
+        let delegated = vault_account.owner == &spl_token::id()
+            && TokenAccount::unpack(&vault_account.try_borrow_data()?)
+                .map(|vault| vault.delegate == COption::Some(*subscription_account.key))
+                .unwrap_or(false);
+        if delegated {
+            invoke(
+                &revoke(token_program.key, vault_account.key, subscriber_account.key, &[])?,
+                &[
+                    vault_account.clone(),
+                    subscriber_account.clone(),
+                    token_program.clone(),
+                ],
+            )?;
+        }
+
+        subscription.cancelled = true;
+        serialize_account_data(subscription_account, &subscription)?;
+
+        E9thEvent::SubscriptionCancelled(SubscriptionCancelled {
+            subscription: *subscription_account.key,
+            subscriber: subscription.subscriber,
+            periods_collected: subscription.periods_collected,
+        })
+        .emit();
+        msg!(
+            "Subscription {} cancelled after {} of {} periods",
+            subscription.subscription_id,
+            subscription.periods_collected,
+            subscription.terms.max_periods
+        );
+        Ok(())
+    }
+
+    /// Load a subscription, checking its address
+    pub fn load_subscription(
+        program_id: &Pubkey,
+        subscription_account: &AccountInfo,
+    ) -> Result<Subscription, ProgramError> {
+        AccountRules::new().writable().owned_by(program_id).check(subscription_account)?;
+        let subscription: Subscription = deserialize_account_data(subscription_account)?;
+        let expected = Pubkey::create_program_address(
+            &[
+                b"subscription",
+                subscription.merchant.as_ref(),
+                subscription.subscriber.as_ref(),
+                &subscription.subscription_id.to_le_bytes(),
+                &[subscription.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| E9thTokenError::UnexpectedAccount)?;
+        if expected != *subscription_account.key {
+            return Err(E9thTokenError::UnexpectedAccount.into());
+        }
+        Ok(subscription)
+    }
+
+    /// Get a subscriber's subscription PDA for a merchant
+    pub fn get_subscription_pda(
+        program_id: &Pubkey,
+        merchant: &Pubkey,
+        subscriber: &Pubkey,
+        subscription_id: u64,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[b"subscription", merchant.as_ref(), subscriber.as_ref(), &subscription_id.to_le_bytes()],
+            program_id,
+        )
+    }
+}
@@ -0,0 +1,200 @@
+//! Utility tiers for the E9th Token Program
+//!
+//! A wallet's tier comes from the E9TH it holds plus what it has staked,
+//! measured against the config's [TierThresholds]. `VerifyTier` only reads
+//! accounts, so partner programs can CPI into it (or clients simulate it) and
+//! read the tier back from the return data. Membership passes are tiered on the
+//! escrowed stake alone (see [crate::membership]), so a pass can read lower
+//! than `VerifyTier` for a wallet that also holds E9TH.
+
+use crate::{
+    error::E9thTokenError,
+    processor::{load_token_config, Processor},
+    state::{
+        deserialize_account_data, serialize_account_data, TierConfig, TierThresholds, TokenConfig,
+        UtilityTier,
+    },
+    validation::{check_system_program, AccountRules},
+    zero_copy::{load_account, StakeEntryPod},
+};
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke_signed, set_return_data},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+use spl_token::state::Account as TokenAccount;
+
+pub struct TierProcessor;
+
+impl TierProcessor {
+    /// Create or replace the config's tier thresholds (owner only)
+    pub fn process_set_thresholds(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        thresholds: TierThresholds,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let tier_config_account = next_account_info(account_info_iter)?;
+        let owner_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        let config = load_token_config(program_id, config_account)?;
+        AccountRules::new().signer().writable().check(owner_account)?;
+        if *owner_account.key != config.owner {
+            return Err(E9thTokenError::InvalidAdmin.into());
+        }
+        check_system_program(system_program)?;
+        if !thresholds.is_valid() {
+            return Err(E9thTokenError::InvalidTierThresholds.into());
+        }
+
+        let (tier_config_pda, bump) = Self::get_tier_config_pda(program_id, config_account.key);
+        AccountRules::new().writable().address(tier_config_pda).check(tier_config_account)?;
+
+        let tier_config = if tier_config_account.data_is_empty() {
+            let lamports = Rent::get()?.minimum_balance(TierConfig::LEN);
+            invoke_signed(
+                &system_instruction::create_account(
+                    owner_account.key,
+                    tier_config_account.key,
+                    lamports,
+                    TierConfig::LEN as u64,
+                    program_id,
+                ),
+                &[
+                    owner_account.clone(),
+                    tier_config_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[b"tier_config", config_account.key.as_ref(), &[bump]]],
+            )?;
+            TierConfig::new(*config_account.key, thresholds, bump)
+        } else {
+            AccountRules::new().owned_by(program_id).check(tier_config_account)?;
+            let mut tier_config: TierConfig = deserialize_account_data(tier_config_account)?;
+            tier_config.thresholds = thresholds;
+            tier_config
+        };
+        serialize_account_data(tier_config_account, &tier_config)?;
+
+        msg!(
+            "Tier thresholds set: bronze {}, silver {}, gold {}",
+            thresholds.bronze,
+            thresholds.silver,
+            thresholds.gold
+        );
+        Ok(())
+    }
+
+    /// Compute the wallet's tier and return it as return data: the Borsh
+    /// encoded [UtilityTier](crate::state::UtilityTier), one byte
+    pub fn process_verify_tier(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let tier_config_account = next_account_info(account_info_iter)?;
+        let wallet_account = next_account_info(account_info_iter)?;
+        let token_account = next_account_info(account_info_iter)?;
+        let stake_account = next_account_info(account_info_iter)?;
+
+        let config = load_token_config(program_id, config_account)?;
+        let tier_config = Self::load_tier_config(program_id, config_account.key, tier_config_account)?;
+        let amount = Self::wallet_holdings(
+            program_id,
+            config_account,
+            &config,
+            wallet_account,
+            token_account,
+            stake_account,
+        )?;
+        let tier = tier_config.thresholds.tier_for(amount);
+
+        set_return_data(&tier.try_to_vec()?);
+        msg!("Wallet {} holds {}: tier {:?}", wallet_account.key, amount, tier);
+        Ok(())
+    }
+
+    /// Load the tier config of `config`
+    pub fn load_tier_config(
+        program_id: &Pubkey,
+        config: &Pubkey,
+        tier_config_account: &AccountInfo,
+    ) -> Result<TierConfig, ProgramError> {
+        let (tier_config_pda, _) = Self::get_tier_config_pda(program_id, config);
+        AccountRules::new().address(tier_config_pda).check(tier_config_account)?;
+        if tier_config_account.data_is_empty() {
+            return Err(E9thTokenError::AccountNotInitialized.into());
+        }
+        AccountRules::new().owned_by(program_id).check(tier_config_account)?;
+        deserialize_account_data(tier_config_account)
+    }
+
+    /// E9TH the wallet holds in `token_account` plus its stake. The stake entry
+    /// must be the wallet's PDA but may not exist yet, which counts as no stake.
+    pub fn wallet_holdings(
+        program_id: &Pubkey,
+        config_account: &AccountInfo,
+        config: &TokenConfig,
+        wallet_account: &AccountInfo,
+        token_account: &AccountInfo,
+        stake_account: &AccountInfo,
+    ) -> Result<u64, ProgramError> {
+        AccountRules::new()
+            .token_account(&config.mint)
+            .token_authority(wallet_account.key)
+            .check(token_account)?;
+        let held = TokenAccount::unpack(&token_account.try_borrow_data()?)?.amount;
+        let staked = Self::staked_amount(program_id, config_account, wallet_account, stake_account)?;
+
+        // Saturating: a total above u64::MAX is Gold either way
+        Ok(held.saturating_add(staked))
+    }
+
+    /// E9TH a wallet has staked; a stake entry that was never created is 0
+    pub fn staked_amount(
+        program_id: &Pubkey,
+        config_account: &AccountInfo,
+        wallet_account: &AccountInfo,
+        stake_account: &AccountInfo,
+    ) -> Result<u64, ProgramError> {
+        let (stake_pda, _) =
+            Processor::get_stake_entry_pda(program_id, config_account.key, wallet_account.key);
+        AccountRules::new().address(stake_pda).check(stake_account)?;
+        if stake_account.data_is_empty() {
+            return Ok(0);
+        }
+        AccountRules::new().owned_by(program_id).check(stake_account)?;
+        Ok(u64::from(load_account::<StakeEntryPod>(stake_account)?.amount))
+    }
+
+    /// Tier of `holdings`, or [UtilityTier::None] while the config has no
+    /// tier thresholds yet
+    pub fn tier_or_none(
+        program_id: &Pubkey,
+        config: &Pubkey,
+        tier_config_account: &AccountInfo,
+        holdings: u64,
+    ) -> Result<UtilityTier, ProgramError> {
+        if tier_config_account.data_is_empty() {
+            let (tier_config_pda, _) = Self::get_tier_config_pda(program_id, config);
+            AccountRules::new().address(tier_config_pda).check(tier_config_account)?;
+            return Ok(UtilityTier::None);
+        }
+        Ok(Self::load_tier_config(program_id, config, tier_config_account)?
+            .thresholds
+            .tier_for(holdings))
+    }
+
+    /// Get the tier config PDA for a token config
+    pub fn get_tier_config_pda(program_id: &Pubkey, config: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"tier_config", config.as_ref()], program_id)
+    }
+}
@@ -0,0 +1,146 @@
+//! Declarative account validation for the E9th Token Program
+//!
+//! Handlers describe each account with [AccountRules] and check it in one call,
+//! so every instruction rejects spoofed accounts the same way.
+
+use crate::error::E9thTokenError;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    program_pack::Pack, pubkey::Pubkey, system_program,
+};
+use spl_token::state::Account as TokenAccount;
+
+/// Requirements an account must meet
+#[derive(Debug, Default, Clone)]
+pub struct AccountRules<'a> {
+    signer: bool,
+    writable: bool,
+    owner: Option<&'a Pubkey>,
+    address: Option<Pubkey>,
+    mint: Option<&'a Pubkey>,
+    token_mint: Option<&'a Pubkey>,
+    token_authority: Option<&'a Pubkey>,
+}
+
+impl<'a> AccountRules<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The account must sign the transaction
+    pub fn signer(mut self) -> Self {
+        self.signer = true;
+        self
+    }
+
+    /// The account must be passed as writable
+    pub fn writable(mut self) -> Self {
+        self.writable = true;
+        self
+    }
+
+    /// The account must be owned by `owner` (usually the program id)
+    pub fn owned_by(mut self, owner: &'a Pubkey) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    /// The account must have this exact address
+    pub fn address(mut self, address: Pubkey) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// The account must be the canonical PDA of `seeds` under `program_id`.
+    /// Derive the PDA yourself and use [Self::address] when you also need the bump.
+    pub fn seeds(self, seeds: &[&[u8]], program_id: &Pubkey) -> Self {
+        self.address(Pubkey::find_program_address(seeds, program_id).0)
+    }
+
+    /// The account must be the SPL token mint `mint`
+    pub fn mint(mut self, mint: &'a Pubkey) -> Self {
+        self.mint = Some(mint);
+        self
+    }
+
+    /// The account must be an SPL token account for `mint`
+    pub fn token_account(mut self, mint: &'a Pubkey) -> Self {
+        self.token_mint = Some(mint);
+        self
+    }
+
+    /// The token account must be owned (as in token authority) by `authority`
+    pub fn token_authority(mut self, authority: &'a Pubkey) -> Self {
+        self.token_authority = Some(authority);
+        self
+    }
+
+    /// Check `account` against every rule
+    pub fn check(&self, account: &AccountInfo) -> ProgramResult {
+        if self.signer && !account.is_signer {
+            return Err(E9thTokenError::Unauthorized.into());
+        }
+        if self.writable && !account.is_writable {
+            return Err(E9thTokenError::AccountNotWritable.into());
+        }
+        if let Some(address) = self.address {
+            if *account.key != address {
+                return Err(E9thTokenError::UnexpectedAccount.into());
+            }
+        }
+        if let Some(owner) = self.owner {
+            if account.owner != owner {
+                return Err(E9thTokenError::InvalidAccountOwner.into());
+            }
+        }
+        if let Some(mint) = self.mint {
+            if account.key != mint {
+                return Err(E9thTokenError::InvalidMint.into());
+            }
+            if *account.owner != spl_token::id() {
+                return Err(E9thTokenError::InvalidAccountOwner.into());
+            }
+        }
+        if self.token_mint.is_some() || self.token_authority.is_some() {
+            self.check_token_account(account)?;
+        }
+        Ok(())
+    }
+
+    fn check_token_account(&self, account: &AccountInfo) -> ProgramResult {
+        if *account.owner != spl_token::id() {
+            return Err(E9thTokenError::InvalidAccountOwner.into());
+        }
+        let token_account = TokenAccount::unpack(&account.try_borrow_data()?)
+            .map_err(|_| E9thTokenError::InvalidTokenAccount)?;
+        if let Some(mint) = self.token_mint {
+            if token_account.mint != *mint {
+                return Err(E9thTokenError::InvalidMint.into());
+            }
+        }
+        if let Some(authority) = self.token_authority {
+            if token_account.owner != *authority {
+                return Err(E9thTokenError::InvalidTokenAccount.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check that a CPI target is the expected program
+pub fn check_program(account: &AccountInfo, program_id: &Pubkey) -> ProgramResult {
+    if account.key != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+/// Check that a CPI target is the SPL token program
+pub fn check_token_program(account: &AccountInfo) -> ProgramResult {
+    check_program(account, &spl_token::id())
+}
+
+/// Check that a CPI target is the system program
+pub fn check_system_program(account: &AccountInfo) -> ProgramResult {
+    check_program(account, &system_program::id())
+}
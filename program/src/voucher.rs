@@ -0,0 +1,378 @@
+//! Discount vouchers for the E9th Token Program
+//!
+//! A holder mints a voucher for a registered merchant by locking or burning the
+//! merchant's voucher cost in E9TH, as set by its [VoucherMode]. The merchant
+//! redeems the voucher with its signature until it expires, which marks it used
+//! and, for locked vouchers, returns the locked E9TH to the holder. A holder
+//! whose locked voucher expired, or whose merchant was deactivated, reclaims the
+//! E9TH without the merchant.
+
+use crate::{
+    error::E9thTokenError,
+    events::{Burned, E9thEvent, VoucherIssued, VoucherReclaimed, VoucherRedeemed},
+    merchant::MerchantProcessor,
+    processor::{borrow_token_config_mut, load_token_config, record_burn},
+    state::{deserialize_account_data, serialize_account_data, BurnKind, Merchant, Voucher, VoucherMode},
+    validation::{check_system_program, check_token_program, AccountRules},
+};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+use spl_token::instruction::{burn, transfer};
+
+pub struct VoucherProcessor;
+
+impl VoucherProcessor {
+    /// Mint a voucher for a merchant by locking or burning its voucher cost
+    pub fn process_mint(program_id: &Pubkey, accounts: &[AccountInfo], voucher_id: u64) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let merchant_account = next_account_info(account_info_iter)?;
+        let voucher_account = next_account_info(account_info_iter)?;
+        let holder_account = next_account_info(account_info_iter)?;
+        let holder_token_account = next_account_info(account_info_iter)?;
+        let mint_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        let config = load_token_config(program_id, config_account)?;
+        if config.is_paused {
+            return Err(E9thTokenError::TokenPaused.into());
+        }
+        AccountRules::new().signer().writable().check(holder_account)?;
+        check_token_program(token_program)?;
+        check_system_program(system_program)?;
+
+        AccountRules::new().writable().check(merchant_account)?;
+        let mut merchant = MerchantProcessor::load_merchant(program_id, config_account.key, merchant_account)?;
+        if !merchant.active {
+            return Err(E9thTokenError::MerchantInactive.into());
+        }
+
+        let (voucher_pda, bump) =
+            Self::get_voucher_pda(program_id, merchant_account.key, holder_account.key, voucher_id);
+        AccountRules::new().writable().address(voucher_pda).check(voucher_account)?;
+        if !voucher_account.data_is_empty() {
+            return Err(E9thTokenError::AccountAlreadyInitialized.into());
+        }
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(holder_account.key)
+            .check(holder_token_account)?;
+        AccountRules::new().writable().mint(&config.mint).check(mint_account)?;
+        AccountRules::new().writable().address(merchant.vault).check(vault_account)?;
+
+        let now = Clock::get()?.unix_timestamp as u64;
+        let lamports = Rent::get()?.minimum_balance(Voucher::LEN);
+        invoke_signed(
+            &system_instruction::create_account(
+                holder_account.key,
+                voucher_account.key,
+                lamports,
+                Voucher::LEN as u64,
+                program_id,
+            ),
+            &[
+                holder_account.clone(),
+                voucher_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"voucher",
+                merchant_account.key.as_ref(),
+                holder_account.key.as_ref(),
+                &voucher_id.to_le_bytes(),
+                &[bump],
+            ]],
+        )?;
+
+        let amount = merchant.voucher_cost;
+        match merchant.voucher_mode {
+            VoucherMode::Lock => invoke(
+                &transfer(
+                    token_program.key,
+                    holder_token_account.key,
+                    vault_account.key,
+                    holder_account.key,
+                    &[],
+                    amount,
+                )?,
+                &[
+                    holder_token_account.clone(),
+                    vault_account.clone(),
+                    holder_account.clone(),
+                    token_program.clone(),
+                ],
+            )?,
+            VoucherMode::Burn => {
+                invoke(
+                    &burn(
+                        token_program.key,
+                        holder_token_account.key,
+                        mint_account.key,
+                        holder_account.key,
+                        &[],
+                        amount,
+                    )?,
+                    &[
+                        holder_token_account.clone(),
+                        mint_account.clone(),
+                        holder_account.clone(),
+                        token_program.clone(),
+                    ],
+                )?;
+                let mut config = borrow_token_config_mut(program_id, config_account)?;
+                config.total_supply = u64::from(config.total_supply).saturating_sub(amount).into();
+                record_burn(&mut config, BurnKind::Utility, amount)?;
+                E9thEvent::Burned(Burned {
+                    mint: *mint_account.key,
+                    authority: *holder_account.key,
+                    amount,
+                })
+                .emit();
+            }
+        }
+
+        merchant.vouchers_issued = merchant
+            .vouchers_issued
+            .checked_add(1)
+            .ok_or(E9thTokenError::MathOverflow)?;
+        serialize_account_data(merchant_account, &merchant)?;
+        let voucher = Voucher::new(
+            *merchant_account.key,
+            *holder_account.key,
+            voucher_id,
+            amount,
+            merchant.voucher_mode,
+            now,
+            bump,
+        );
+        serialize_account_data(voucher_account, &voucher)?;
+
+        E9thEvent::VoucherIssued(VoucherIssued {
+            merchant: *merchant_account.key,
+            holder: *holder_account.key,
+            voucher_id,
+            amount,
+            mode: merchant.voucher_mode,
+        })
+        .emit();
+        Ok(())
+    }
+
+    /// Redeem a voucher (merchant only), returning locked E9TH to the holder
+    pub fn process_redeem(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let merchant_account = next_account_info(account_info_iter)?;
+        let voucher_account = next_account_info(account_info_iter)?;
+        let authority_account = next_account_info(account_info_iter)?;
+        let holder_token_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        let config = load_token_config(program_id, config_account)?;
+        if config.is_paused {
+            return Err(E9thTokenError::TokenPaused.into());
+        }
+        check_token_program(token_program)?;
+        AccountRules::new().writable().check(merchant_account)?;
+        let mut merchant = MerchantProcessor::load_merchant(program_id, config_account.key, merchant_account)?;
+        AccountRules::new().signer().check(authority_account)?;
+        if *authority_account.key != merchant.authority {
+            return Err(E9thTokenError::Unauthorized.into());
+        }
+
+        let mut voucher = Self::load_voucher(program_id, merchant_account.key, voucher_account)?;
+        if voucher.redeemed {
+            return Err(E9thTokenError::VoucherAlreadyRedeemed.into());
+        }
+        if voucher.reclaimed {
+            return Err(E9thTokenError::VoucherReclaimed.into());
+        }
+        let now = Clock::get()?.unix_timestamp as u64;
+        if now >= voucher.expires_at {
+            return Err(E9thTokenError::VoucherExpired.into());
+        }
+
+        if voucher.mode == VoucherMode::Lock {
+            AccountRules::new()
+                .writable()
+                .token_account(&config.mint)
+                .token_authority(&voucher.holder)
+                .check(holder_token_account)?;
+            AccountRules::new().writable().address(merchant.vault).check(vault_account)?;
+            Self::release_locked(
+                config_account,
+                merchant_account,
+                vault_account,
+                holder_token_account,
+                token_program,
+                &merchant,
+                voucher.amount,
+            )?;
+        }
+
+        voucher.redeemed = true;
+        voucher.redeemed_at = now;
+        serialize_account_data(voucher_account, &voucher)?;
+        merchant.vouchers_redeemed = merchant
+            .vouchers_redeemed
+            .checked_add(1)
+            .ok_or(E9thTokenError::MathOverflow)?;
+        serialize_account_data(merchant_account, &merchant)?;
+
+        E9thEvent::VoucherRedeemed(VoucherRedeemed {
+            merchant: *merchant_account.key,
+            holder: voucher.holder,
+            voucher_id: voucher.voucher_id,
+        })
+        .emit();
+        msg!("Voucher {} of {} redeemed", voucher.voucher_id, voucher.holder);
+        Ok(())
+    }
+
+    /// Return a locked voucher's E9TH to its holder (holder only), once the
+    /// voucher expired or its merchant was deactivated. Not blocked by a pause,
+    /// so holders can always get their tokens out.
+    pub fn process_reclaim(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_account = next_account_info(account_info_iter)?;
+        let merchant_account = next_account_info(account_info_iter)?;
+        let voucher_account = next_account_info(account_info_iter)?;
+        let holder_account = next_account_info(account_info_iter)?;
+        let holder_token_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        let config = load_token_config(program_id, config_account)?;
+        check_token_program(token_program)?;
+        let merchant = MerchantProcessor::load_merchant(program_id, config_account.key, merchant_account)?;
+        let mut voucher = Self::load_voucher(program_id, merchant_account.key, voucher_account)?;
+        AccountRules::new().signer().check(holder_account)?;
+        if *holder_account.key != voucher.holder {
+            return Err(E9thTokenError::Unauthorized.into());
+        }
+        if voucher.redeemed {
+            return Err(E9thTokenError::VoucherAlreadyRedeemed.into());
+        }
+        if voucher.reclaimed {
+            return Err(E9thTokenError::VoucherReclaimed.into());
+        }
+        let now = Clock::get()?.unix_timestamp as u64;
+        if voucher.mode != VoucherMode::Lock || (merchant.active && now < voucher.expires_at) {
+            return Err(E9thTokenError::VoucherNotReclaimable.into());
+        }
+        AccountRules::new()
+            .writable()
+            .token_account(&config.mint)
+            .token_authority(holder_account.key)
+            .check(holder_token_account)?;
+        AccountRules::new().writable().address(merchant.vault).check(vault_account)?;
+
+        Self::release_locked(
+            config_account,
+            merchant_account,
+            vault_account,
+            holder_token_account,
+            token_program,
+            &merchant,
+            voucher.amount,
+        )?;
+        voucher.reclaimed = true;
+        serialize_account_data(voucher_account, &voucher)?;
+
+        E9thEvent::VoucherReclaimed(VoucherReclaimed {
+            merchant: *merchant_account.key,
+            holder: voucher.holder,
+            voucher_id: voucher.voucher_id,
+            amount: voucher.amount,
+        })
+        .emit();
+        msg!("Voucher {} of {} reclaimed", voucher.voucher_id, voucher.holder);
+        Ok(())
+    }
+
+    /// Load a voucher of `merchant`, checking its address
+    pub fn load_voucher(
+        program_id: &Pubkey,
+        merchant: &Pubkey,
+        voucher_account: &AccountInfo,
+    ) -> Result<Voucher, ProgramError> {
+        AccountRules::new().writable().owned_by(program_id).check(voucher_account)?;
+        let voucher: Voucher = deserialize_account_data(voucher_account)?;
+        let expected = Pubkey::create_program_address(
+            &[
+                b"voucher",
+                voucher.merchant.as_ref(),
+                voucher.holder.as_ref(),
+                &voucher.voucher_id.to_le_bytes(),
+                &[voucher.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| E9thTokenError::UnexpectedAccount)?;
+        if expected != *voucher_account.key || voucher.merchant != *merchant {
+            return Err(E9thTokenError::UnexpectedAccount.into());
+        }
+        Ok(voucher)
+    }
+
+    /// Get a holder's voucher PDA for a merchant
+    pub fn get_voucher_pda(
+        program_id: &Pubkey,
+        merchant: &Pubkey,
+        holder: &Pubkey,
+        voucher_id: u64,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[b"voucher", merchant.as_ref(), holder.as_ref(), &voucher_id.to_le_bytes()],
+            program_id,
+        )
+    }
+
+    /// Transfer locked E9TH out of the merchant's vault, signed by the merchant PDA
+    fn release_locked<'a>(
+        config_account: &AccountInfo<'a>,
+        merchant_account: &AccountInfo<'a>,
+        vault_account: &AccountInfo<'a>,
+        destination_account: &AccountInfo<'a>,
+        token_program: &AccountInfo<'a>,
+        merchant: &Merchant,
+        amount: u64,
+    ) -> ProgramResult {
+        invoke_signed(
+            &transfer(
+                token_program.key,
+                vault_account.key,
+                destination_account.key,
+                merchant_account.key,
+                &[],
+                amount,
+            )?,
+            &[
+                vault_account.clone(),
+                destination_account.clone(),
+                merchant_account.clone(),
+                token_program.clone(),
+            ],
+            &[&[
+                b"merchant",
+                config_account.key.as_ref(),
+                merchant.authority.as_ref(),
+                &[merchant.bump],
+            ]],
+        )
+    }
+}
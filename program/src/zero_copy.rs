@@ -0,0 +1,270 @@
+//! Zero-copy access to hot-path account state
+//!
+//! [TokenConfigPod] and [StakeEntryPod] match the Borsh layout of [TokenConfig]
+//! and [StakeEntry] byte for byte, so handlers can read or update single fields
+//! in place instead of decoding and re-encoding the whole account.
+
+use crate::state::{
+    check_account_header, AccountHeader, AccountState, BurnStats, StakeEntry, TokenConfig,
+};
+use bytemuck::{Pod, Zeroable};
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use std::{
+    cell::{Ref, RefMut},
+    mem::size_of,
+    ops::Range,
+};
+
+/// Little-endian `u64` with alignment 1
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct PodU64([u8; 8]);
+
+impl From<u64> for PodU64 {
+    fn from(value: u64) -> Self {
+        Self(value.to_le_bytes())
+    }
+}
+
+impl From<PodU64> for u64 {
+    fn from(value: PodU64) -> Self {
+        u64::from_le_bytes(value.0)
+    }
+}
+
+/// Little-endian `u16` with alignment 1
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct PodU16([u8; 2]);
+
+impl From<u16> for PodU16 {
+    fn from(value: u16) -> Self {
+        Self(value.to_le_bytes())
+    }
+}
+
+impl From<PodU16> for u16 {
+    fn from(value: PodU16) -> Self {
+        u16::from_le_bytes(value.0)
+    }
+}
+
+/// Borsh-compatible `bool` (one byte, 0 or 1)
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct PodBool(u8);
+
+impl From<bool> for PodBool {
+    fn from(value: bool) -> Self {
+        Self(value as u8)
+    }
+}
+
+impl From<PodBool> for bool {
+    fn from(value: PodBool) -> Self {
+        value.0 != 0
+    }
+}
+
+/// Fixed-layout view of an account body, stored right after the [AccountHeader]
+pub trait ZeroCopyState: Pod {
+    /// The Borsh state type this view mirrors
+    type State: AccountState;
+
+    /// Byte range of the body within the account data
+    fn body_range() -> Range<usize> {
+        AccountHeader::LEN..AccountHeader::LEN + size_of::<Self>()
+    }
+}
+
+/// View `data` as `T`, checking the header of `T::State`
+pub fn load<T: ZeroCopyState>(data: &[u8]) -> Result<&T, ProgramError> {
+    check_account_header::<T::State>(data)?;
+    data.get(T::body_range())
+        .map(bytemuck::from_bytes)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+/// Mutable variant of [load]
+pub fn load_mut<T: ZeroCopyState>(data: &mut [u8]) -> Result<&mut T, ProgramError> {
+    check_account_header::<T::State>(data)?;
+    data.get_mut(T::body_range())
+        .map(bytemuck::from_bytes_mut)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+/// Borrow an account's data as `T`
+pub fn load_account<'b, T: ZeroCopyState>(
+    account: &'b AccountInfo,
+) -> Result<Ref<'b, T>, ProgramError> {
+    let data = account.try_borrow_data()?;
+    load::<T>(&data)?;
+    Ok(Ref::map(data, |data| bytemuck::from_bytes(&data[T::body_range()])))
+}
+
+/// Mutably borrow an account's data as `T`; writes go straight to the account
+pub fn load_account_mut<'b, T: ZeroCopyState>(
+    account: &'b AccountInfo,
+) -> Result<RefMut<'b, T>, ProgramError> {
+    let mut data = account.try_borrow_mut_data()?;
+    load_mut::<T>(&mut data)?;
+    Ok(RefMut::map(data, |data| bytemuck::from_bytes_mut(&mut data[T::body_range()])))
+}
+
+/// Zero-copy layout of [TokenConfig]
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Pod, Zeroable)]
+pub struct TokenConfigPod {
+    pub owner: Pubkey,
+    pub operator: Pubkey,
+    pub treasury: Pubkey,
+    pub is_paused: PodBool,
+    pub burn_rate_basis_points: PodU16,
+    pub mint: Pubkey,
+    pub total_supply: PodU64,
+    pub staking_enabled: PodBool,
+    pub reward_rate: PodU16,
+    pub min_stake_period: PodU64,
+    pub max_stake_period: PodU64,
+    pub total_staked: PodU64,
+    pub bump: u8,
+    pub legacy_disabled: PodBool,
+    pub burn_stats: BurnStatsPod,
+}
+
+impl ZeroCopyState for TokenConfigPod {
+    type State = TokenConfig;
+}
+
+impl From<&TokenConfig> for TokenConfigPod {
+    fn from(config: &TokenConfig) -> Self {
+        Self {
+            owner: config.owner,
+            operator: config.operator,
+            treasury: config.treasury,
+            is_paused: config.is_paused.into(),
+            burn_rate_basis_points: config.burn_rate_basis_points.into(),
+            mint: config.mint,
+            total_supply: config.total_supply.into(),
+            staking_enabled: config.staking_enabled.into(),
+            reward_rate: config.reward_rate.into(),
+            min_stake_period: config.min_stake_period.into(),
+            max_stake_period: config.max_stake_period.into(),
+            total_staked: config.total_staked.into(),
+            bump: config.bump,
+            legacy_disabled: config.legacy_disabled.into(),
+            burn_stats: (&config.burn_stats).into(),
+        }
+    }
+}
+
+impl From<&TokenConfigPod> for TokenConfig {
+    fn from(config: &TokenConfigPod) -> Self {
+        Self {
+            owner: config.owner,
+            operator: config.operator,
+            treasury: config.treasury,
+            is_paused: config.is_paused.into(),
+            burn_rate_basis_points: config.burn_rate_basis_points.into(),
+            mint: config.mint,
+            total_supply: config.total_supply.into(),
+            staking_enabled: config.staking_enabled.into(),
+            reward_rate: config.reward_rate.into(),
+            min_stake_period: config.min_stake_period.into(),
+            max_stake_period: config.max_stake_period.into(),
+            total_staked: config.total_staked.into(),
+            bump: config.bump,
+            legacy_disabled: config.legacy_disabled.into(),
+            burn_stats: (&config.burn_stats).into(),
+        }
+    }
+}
+
+/// Zero-copy layout of [BurnStats]
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Pod, Zeroable)]
+pub struct BurnStatsPod {
+    pub transfer_tax: PodU64,
+    pub manual: PodU64,
+    pub penalty: PodU64,
+    pub buyback: PodU64,
+    pub last_burn_timestamp: PodU64,
+    pub utility: PodU64,
+}
+
+impl From<&BurnStats> for BurnStatsPod {
+    fn from(stats: &BurnStats) -> Self {
+        Self {
+            transfer_tax: stats.transfer_tax.into(),
+            manual: stats.manual.into(),
+            penalty: stats.penalty.into(),
+            buyback: stats.buyback.into(),
+            last_burn_timestamp: stats.last_burn_timestamp.into(),
+            utility: stats.utility.into(),
+        }
+    }
+}
+
+impl From<&BurnStatsPod> for BurnStats {
+    fn from(stats: &BurnStatsPod) -> Self {
+        Self {
+            transfer_tax: stats.transfer_tax.into(),
+            manual: stats.manual.into(),
+            penalty: stats.penalty.into(),
+            buyback: stats.buyback.into(),
+            last_burn_timestamp: stats.last_burn_timestamp.into(),
+            utility: stats.utility.into(),
+        }
+    }
+}
+
+/// Zero-copy layout of [StakeEntry]
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Pod, Zeroable)]
+pub struct StakeEntryPod {
+    pub staker: Pubkey,
+    pub amount: PodU64,
+    pub last_reward_timestamp: PodU64,
+    pub start_epoch: PodU64,
+    pub period: PodU64,
+    pub rewards_claimed: PodU64,
+    pub lock_time: PodU64,
+    pub bump: u8,
+    pub carried_rewards: PodU64,
+}
+
+impl ZeroCopyState for StakeEntryPod {
+    type State = StakeEntry;
+}
+
+impl From<&StakeEntry> for StakeEntryPod {
+    fn from(entry: &StakeEntry) -> Self {
+        Self {
+            staker: entry.staker,
+            amount: entry.amount.into(),
+            last_reward_timestamp: entry.last_reward_timestamp.into(),
+            start_epoch: entry.start_epoch.into(),
+            period: entry.period.into(),
+            rewards_claimed: entry.rewards_claimed.into(),
+            lock_time: entry.lock_time.into(),
+            bump: entry.bump,
+            carried_rewards: entry.carried_rewards.into(),
+        }
+    }
+}
+
+impl From<&StakeEntryPod> for StakeEntry {
+    fn from(entry: &StakeEntryPod) -> Self {
+        Self {
+            staker: entry.staker,
+            amount: entry.amount.into(),
+            last_reward_timestamp: entry.last_reward_timestamp.into(),
+            start_epoch: entry.start_epoch.into(),
+            period: entry.period.into(),
+            rewards_claimed: entry.rewards_claimed.into(),
+            lock_time: entry.lock_time.into(),
+            bump: entry.bump,
+            carried_rewards: entry.carried_rewards.into(),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! Sample values shared by the test binaries
+
+// Each binary only uses some of them
+#![allow(dead_code)]
+
+use e9th_token_program::state::{
+    LaunchPriority, LaunchTerms, SaleTerms, SubscriptionTerms, TierThresholds, UtilityTier,
+};
+use solana_program::pubkey::Pubkey;
+
+pub fn sample_sale_terms() -> SaleTerms {
+    SaleTerms {
+        start_timestamp: 1_000,
+        end_timestamp: 2_000,
+        whitelist_end_timestamp: 1_500,
+        whitelist_root: [3u8; 32],
+        quote_mint: Pubkey::default(),
+        // 5 lamports per 100_000 base units
+        price_quote_amount: 5,
+        price_token_amount: 100_000,
+        min_purchase: 100_000,
+        max_purchase: 10_000_000,
+        hard_cap: 1_000_000_000,
+        soft_cap: 250_000_000,
+    }
+}
+
+pub fn sample_tier_thresholds() -> TierThresholds {
+    TierThresholds { bronze: 1_000, silver: 10_000, gold: 100_000 }
+}
+
+pub fn sample_subscription_terms() -> SubscriptionTerms {
+    SubscriptionTerms { amount: 250, period_seconds: 30 * 24 * 60 * 60, max_periods: 12 }
+}
+
+pub fn sample_launch_terms() -> LaunchTerms {
+    LaunchTerms {
+        capacity: 3,
+        open_timestamp: 1_700_000_000,
+        min_tier: UtilityTier::Bronze,
+        priority: LaunchPriority::StakeWeight,
+    }
+}
@@ -0,0 +1,3107 @@
+//! Processor tests that call `Processor::process` directly with spoofable accounts
+//!
+//! They live in their own binary: `solana-program-test` replaces the global
+//! syscall stubs, which breaks direct processor calls in the same process.
+//! [TestSyscalls] stands in for the runtime instead, with a per-thread clock
+//! and CPIs to the system and token programs applied to the passed accounts.
+
+mod common;
+
+use borsh::BorshSerialize;
+use common::*;
+use e9th_token_program::{
+    airdrop::AirdropProcessor,
+    error::E9thTokenError,
+    events::{
+        AirdropSent, BlacklistChanged, Burned, E9thEvent, LaunchSlotReserved, MembershipChanged, Paused,
+        RewardsClaimed, Staked, Transferred, Unstaked, VoucherReclaimed,
+    },
+    instruction,
+    launch::LaunchProcessor,
+    membership::MembershipProcessor,
+    merchant::MerchantProcessor,
+    merkle::{self, MerkleTree},
+    payment::MAX_ORDER_REFERENCE_LEN,
+    processor::{borrow_token_config_mut, load_token_config, Processor},
+    sale::SaleProcessor,
+    stake::StakeProcessor,
+    state::{
+        deserialize_account_data, pack_state, serialize_account_data, unpack_state, AccountHeader, AccountState,
+        AccountType, AirdropGate, AirdropRound, AirdropRoundParams, Blacklist, Launch, LaunchPriority, LaunchTerms,
+        MembershipPass, Merchant, PaymentTerms, ProgramState, Sale, SalePurchase, SaleTerms, StakeAccount,
+        StakeEntry, Subscription, SubscriptionTerms, TierConfig, TierThresholds, TokenConfig, UtilityTier, Voucher,
+        VoucherMode,
+    },
+    subscription::SubscriptionProcessor,
+    tier::TierProcessor,
+    validation::{check_token_program, AccountRules},
+    voucher::VoucherProcessor,
+    zero_copy::{self, StakeEntryPod, TokenConfigPod},
+};
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    ed25519_program,
+    entrypoint::{ProgramResult, SUCCESS},
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::Pack,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    program_utils::limited_deserialize,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction::SystemInstruction,
+    system_program,
+    sysvar::instructions::{
+        construct_instructions_data, store_current_index, BorrowedAccountMeta, BorrowedInstruction,
+    },
+};
+use spl_token::{
+    instruction::TokenInstruction,
+    state::{Account as TokenAccountState, Mint},
+};
+use std::{cell::RefCell, sync::Once};
+
+thread_local! {
+    static CLOCK: RefCell<Clock> = RefCell::new(Clock { epoch: 100, unix_timestamp: 1_650_000_000, ..Clock::default() });
+    static INVOKED: RefCell<Vec<Instruction>> = const { RefCell::new(Vec::new()) };
+    static ASSIGNED: RefCell<Vec<(Pubkey, Pubkey)>> = const { RefCell::new(Vec::new()) };
+    static EVENTS: RefCell<Vec<E9thEvent>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Set the clock seen by processors on this test's thread
+fn set_clock(unix_timestamp: i64, epoch: u64) {
+    CLOCK.with(|clock| *clock.borrow_mut() = Clock { epoch, unix_timestamp, ..Clock::default() });
+}
+
+/// CPIs made by the last [process_with] call
+fn invoked() -> Vec<Instruction> {
+    INVOKED.with(|invoked| invoked.borrow().clone())
+}
+
+/// Events emitted by the last [process_with] call
+fn events() -> Vec<E9thEvent> {
+    EVENTS.with(|events| events.borrow().clone())
+}
+
+struct TestSyscalls;
+
+impl SyscallStubs for TestSyscalls {
+    fn sol_log(&self, _message: &str) {}
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        for field in fields {
+            if let Ok(Some(event)) = E9thEvent::decode(field) {
+                EVENTS.with(|events| events.borrow_mut().push(event));
+            }
+        }
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = CLOCK.with(|clock| clock.borrow().clone());
+        unsafe { *(var_addr as *mut Clock) = clock };
+        SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        SUCCESS
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        INVOKED.with(|invoked| invoked.borrow_mut().push(instruction.clone()));
+        let account = |index: usize| {
+            let key = instruction.accounts[index].pubkey;
+            account_infos.iter().find(|info| *info.key == key).ok_or(ProgramError::NotEnoughAccountKeys)
+        };
+        if instruction.program_id == system_program::id() {
+            match limited_deserialize(&instruction.data, 1024).map_err(|_| ProgramError::InvalidInstructionData)? {
+                SystemInstruction::CreateAccount { lamports, space, owner } => {
+                    move_lamports(account(0)?, account(1)?, lamports)?;
+                    let created = account(1)?;
+                    *created.data.borrow_mut() = Box::leak(vec![0; space as usize].into_boxed_slice());
+                    ASSIGNED.with(|assigned| assigned.borrow_mut().push((*created.key, owner)));
+                }
+                SystemInstruction::Transfer { lamports } => move_lamports(account(0)?, account(1)?, lamports)?,
+                _ => {}
+            }
+        } else if instruction.program_id == spl_token::id() {
+            match TokenInstruction::unpack(&instruction.data)? {
+                TokenInstruction::Transfer { amount } => {
+                    debit_token(account(0)?, account(2)?.key, amount)?;
+                    update_token(account(1)?, |token| token.amount += amount)?;
+                }
+                TokenInstruction::Burn { amount } => {
+                    debit_token(account(0)?, account(2)?.key, amount)?;
+                    let mint = account(1)?;
+                    let state = Mint::unpack(&mint.data.borrow());
+                    if let Ok(mut state) = state {
+                        state.supply -= amount;
+                        state.pack_into_slice(&mut mint.data.borrow_mut());
+                    }
+                }
+                TokenInstruction::MintTo { amount } => update_token(account(1)?, |token| token.amount += amount)?,
+                TokenInstruction::Approve { amount } => {
+                    let delegate = *account(1)?.key;
+                    update_token(account(0)?, |token| {
+                        token.delegate = COption::Some(delegate);
+                        token.delegated_amount = amount;
+                    })?
+                }
+                TokenInstruction::Revoke => update_token(account(0)?, |token| {
+                    token.delegate = COption::None;
+                    token.delegated_amount = 0;
+                })?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn move_lamports(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    let remaining = from.lamports().checked_sub(lamports).ok_or(ProgramError::InsufficientFunds)?;
+    **from.try_borrow_mut_lamports()? = remaining;
+    **to.try_borrow_mut_lamports()? += lamports;
+    Ok(())
+}
+
+fn update_token(account: &AccountInfo, update: impl FnOnce(&mut TokenAccountState)) -> ProgramResult {
+    let mut token = TokenAccountState::unpack(&account.data.borrow())?;
+    update(&mut token);
+    token.pack_into_slice(&mut account.data.borrow_mut());
+    Ok(())
+}
+
+/// Take `amount` out of a token account as its owner or delegate
+fn debit_token(account: &AccountInfo, authority: &Pubkey, amount: u64) -> ProgramResult {
+    let mut token = TokenAccountState::unpack(&account.data.borrow())?;
+    if token.delegate == COption::Some(*authority) && token.owner != *authority {
+        token.delegated_amount =
+            token.delegated_amount.checked_sub(amount).ok_or(spl_token::error::TokenError::InsufficientFunds)?;
+    } else if token.owner != *authority {
+        return Err(spl_token::error::TokenError::OwnerMismatch.into());
+    }
+    token.amount = token.amount.checked_sub(amount).ok_or(spl_token::error::TokenError::InsufficientFunds)?;
+    token.pack_into_slice(&mut account.data.borrow_mut());
+    Ok(())
+}
+
+/// Account backing for calling processors directly with spoofable accounts
+struct TestAccount {
+    key: Pubkey,
+    owner: Pubkey,
+    lamports: u64,
+    data: Vec<u8>,
+    is_signer: bool,
+    is_writable: bool,
+}
+
+impl TestAccount {
+    fn new(meta: &AccountMeta, owner: Pubkey, data: Vec<u8>) -> Self {
+        Self {
+            key: meta.pubkey,
+            owner,
+            lamports: 1_000_000_000,
+            data,
+            is_signer: meta.is_signer,
+            is_writable: meta.is_writable,
+        }
+    }
+
+    /// An account that doesn't exist yet
+    fn empty(meta: &AccountMeta) -> Self {
+        Self { lamports: 0, ..Self::wallet(meta) }
+    }
+
+    /// A system account with lamports to pay for what it creates
+    fn wallet(meta: &AccountMeta) -> Self {
+        Self::new(meta, system_program::id(), vec![])
+    }
+
+    fn with_state<T: AccountState>(meta: &AccountMeta, owner: Pubkey, value: &T) -> Self {
+        let mut account = Self::new(meta, owner, vec![0; value.account_len()]);
+        serialize_account_data(&account.info(), value).unwrap();
+        account
+    }
+
+    fn token_account(meta: &AccountMeta, mint: Pubkey, authority: Pubkey) -> Self {
+        Self::token_account_with(meta, mint, authority, 1_000)
+    }
+
+    fn token_account_with(meta: &AccountMeta, mint: Pubkey, authority: Pubkey, amount: u64) -> Self {
+        let mut data = vec![0; TokenAccountState::LEN];
+        TokenAccountState {
+            mint,
+            owner: authority,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        }
+        .pack_into_slice(&mut data);
+        Self::new(meta, spl_token::id(), data)
+    }
+
+    /// Approve `delegate` to move `amount` out of this token account
+    fn delegated(mut self, delegate: Pubkey, amount: u64) -> Self {
+        let mut token = TokenAccountState::unpack(&self.data).unwrap();
+        token.delegate = COption::Some(delegate);
+        token.delegated_amount = amount;
+        token.pack_into_slice(&mut self.data);
+        self
+    }
+
+    fn mint(meta: &AccountMeta, mint_authority: Pubkey, supply: u64) -> Self {
+        let mut data = vec![0; Mint::LEN];
+        Mint {
+            mint_authority: COption::Some(mint_authority),
+            supply,
+            decimals: 9,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        }
+        .pack_into_slice(&mut data);
+        Self::new(meta, spl_token::id(), data)
+    }
+
+    /// The same account passed again with `meta`'s flags
+    fn reuse(&self, meta: &AccountMeta) -> Self {
+        Self {
+            key: self.key,
+            owner: self.owner,
+            lamports: self.lamports,
+            data: self.data.clone(),
+            is_signer: meta.is_signer,
+            is_writable: meta.is_writable,
+        }
+    }
+
+    fn token_amount(&self) -> u64 {
+        TokenAccountState::unpack(&self.data).unwrap().amount
+    }
+
+    fn info(&mut self) -> AccountInfo<'_> {
+        AccountInfo::new(
+            &self.key,
+            self.is_signer,
+            self.is_writable,
+            &mut self.lamports,
+            &mut self.data,
+            &self.owner,
+            false,
+            0,
+        )
+    }
+}
+
+/// Run an instruction against `accounts`, keeping what the processor and its
+/// CPIs wrote (including accounts created and assigned along the way)
+fn process_with(program_id: &Pubkey, accounts: &mut [TestAccount], data: &[u8]) -> Result<(), ProgramError> {
+    static STUBS: Once = Once::new();
+    STUBS.call_once(|| {
+        set_syscall_stubs(Box::new(TestSyscalls));
+    });
+    INVOKED.with(|invoked| invoked.borrow_mut().clear());
+    EVENTS.with(|events| events.borrow_mut().clear());
+
+    let (result, written) = {
+        let infos: Vec<AccountInfo> = accounts.iter_mut().map(TestAccount::info).collect();
+        let result = Processor::process(program_id, &infos, data);
+        let written: Vec<Vec<u8>> = infos.iter().map(|info| info.data.borrow().to_vec()).collect();
+        (result, written)
+    };
+    for (account, data) in accounts.iter_mut().zip(written) {
+        account.data = data;
+    }
+    for (key, owner) in ASSIGNED.with(|assigned| assigned.take()) {
+        for account in accounts.iter_mut().filter(|account| account.key == key) {
+            account.owner = owner;
+        }
+    }
+    result
+}
+
+/// One account of an otherwise valid instruction, tampered with
+#[derive(Clone, Copy, Debug)]
+enum Spoof {
+    /// Moved to an unrelated address, so it isn't the expected PDA or account
+    Address(usize),
+    /// Passed without its signature
+    Unsigned(usize),
+    /// Owned by another program
+    Owner(usize),
+}
+
+/// Apply each spoof to a fresh copy of the `valid` accounts and check the
+/// handler refuses it with the paired error
+fn assert_spoofs_rejected(
+    program_id: &Pubkey,
+    valid: impl Fn() -> Vec<TestAccount>,
+    data: &[u8],
+    spoofs: &[(Spoof, E9thTokenError)],
+) {
+    for (spoof, error) in spoofs {
+        let mut accounts = valid();
+        match *spoof {
+            Spoof::Address(index) => accounts[index].key = Pubkey::new_unique(),
+            Spoof::Unsigned(index) => accounts[index].is_signer = false,
+            Spoof::Owner(index) => accounts[index].owner = Pubkey::new_unique(),
+        }
+        assert_eq!(process_with(program_id, &mut accounts, data), Err((*error).into()), "{:?}", spoof);
+    }
+}
+
+#[test]
+fn test_zero_copy_state() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let (config_pda, bump) = Processor::get_token_config_pda(&program_id, &mint);
+    let key = Pubkey::new_unique();
+    let mut config = TokenConfig::new(key, key, key, mint, 250, bump);
+    config.total_staked = 5_000;
+    config.legacy_disabled = true;
+
+    // The pod layouts are the Borsh layouts
+    assert_eq!(AccountHeader::LEN + std::mem::size_of::<TokenConfigPod>(), TokenConfig::LEN);
+    assert_eq!(AccountHeader::LEN + std::mem::size_of::<StakeEntryPod>(), StakeEntry::LEN);
+    let mut data = vec![0u8; TokenConfig::LEN];
+    pack_state(&config, &mut data).unwrap();
+    let view = zero_copy::load::<TokenConfigPod>(&data).unwrap();
+    assert_eq!(*view, TokenConfigPod::from(&config));
+    assert_eq!(TokenConfig::from(view).try_to_vec().unwrap(), config.try_to_vec().unwrap());
+
+    // In-place writes are what Borsh decodes
+    let view = zero_copy::load_mut::<TokenConfigPod>(&mut data).unwrap();
+    view.total_staked = 7_500.into();
+    view.is_paused = true.into();
+    let loaded: TokenConfig = unpack_state(&data).unwrap();
+    assert_eq!(loaded.total_staked, 7_500);
+    assert!(loaded.is_paused);
+
+    let mut entry = StakeEntry::new(key, 1_000, 30, 100, 1_700_000_000, 3);
+    entry.carried_rewards = 42;
+    let mut entry_data = vec![0u8; StakeEntry::LEN];
+    pack_state(&entry, &mut entry_data).unwrap();
+    let view = zero_copy::load::<StakeEntryPod>(&entry_data).unwrap();
+    assert_eq!(u64::from(view.amount), 1_000);
+    assert_eq!(u64::from(view.carried_rewards), 42);
+    assert_eq!(StakeEntry::from(view).try_to_vec().unwrap(), entry.try_to_vec().unwrap());
+
+    // Views check the header and size like unpack_state
+    assert_eq!(
+        zero_copy::load::<StakeEntryPod>(&data).unwrap_err(),
+        E9thTokenError::AccountTypeMismatch.into()
+    );
+    assert_eq!(
+        zero_copy::load::<TokenConfigPod>(&data[..TokenConfig::LEN - 1]).unwrap_err(),
+        ProgramError::InvalidAccountData
+    );
+
+    // The config loads through the stored bump and rejects other addresses
+    let meta = AccountMeta::new(config_pda, false);
+    let mut account = TestAccount::with_state(&meta, program_id, &config);
+    let loaded = load_token_config(&program_id, &account.info()).unwrap();
+    assert_eq!(loaded.mint, mint);
+    borrow_token_config_mut(&program_id, &account.info()).unwrap().total_staked = 1.into();
+    assert_eq!(load_token_config(&program_id, &account.info()).unwrap().total_staked, 1);
+    let mut spoofed = TestAccount::with_state(&AccountMeta::new(key, false), program_id, &config);
+    assert_eq!(
+        load_token_config(&program_id, &spoofed.info()).unwrap_err(),
+        E9thTokenError::UnexpectedAccount.into()
+    );
+    let mut readonly = TestAccount::with_state(&AccountMeta::new_readonly(config_pda, false), program_id, &config);
+    assert_eq!(
+        borrow_token_config_mut(&program_id, &readonly.info()).unwrap_err(),
+        E9thTokenError::AccountNotWritable.into()
+    );
+}
+
+#[test]
+fn test_account_rules_reject_spoofed_accounts() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+    let (pda, _) = Pubkey::find_program_address(&[b"seed"], &program_id);
+    let meta = AccountMeta::new(pda, true);
+
+    let mut account = TestAccount::new(&meta, program_id, vec![]);
+    let rules = AccountRules::new().signer().writable().owned_by(&program_id).seeds(&[b"seed"], &program_id);
+    assert_eq!(rules.check(&account.info()), Ok(()));
+
+    account.is_signer = false;
+    assert_eq!(rules.check(&account.info()), Err(E9thTokenError::Unauthorized.into()));
+    account.is_signer = true;
+    account.is_writable = false;
+    assert_eq!(rules.check(&account.info()), Err(E9thTokenError::AccountNotWritable.into()));
+    account.is_writable = true;
+    account.owner = Pubkey::new_unique();
+    assert_eq!(rules.check(&account.info()), Err(E9thTokenError::InvalidAccountOwner.into()));
+    account.owner = program_id;
+    account.key = Pubkey::new_unique();
+    assert_eq!(rules.check(&account.info()), Err(E9thTokenError::UnexpectedAccount.into()));
+
+    // Token accounts: owning program, mint and authority
+    let token_meta = AccountMeta::new(Pubkey::new_unique(), false);
+    let mut token = TestAccount::token_account(&token_meta, mint, wallet);
+    let token_rules = AccountRules::new().token_account(&mint).token_authority(&wallet);
+    assert_eq!(token_rules.check(&token.info()), Ok(()));
+    let other_mint = Pubkey::new_unique();
+    let wrong_mint = AccountRules::new().token_account(&other_mint);
+    assert_eq!(wrong_mint.check(&token.info()), Err(E9thTokenError::InvalidMint.into()));
+    let other_wallet = Pubkey::new_unique();
+    let wrong_authority = AccountRules::new().token_authority(&other_wallet);
+    assert_eq!(wrong_authority.check(&token.info()), Err(E9thTokenError::InvalidTokenAccount.into()));
+    token.owner = program_id;
+    assert_eq!(token_rules.check(&token.info()), Err(E9thTokenError::InvalidAccountOwner.into()));
+
+    // Mints
+    let mut mint_account = TestAccount::new(&AccountMeta::new(mint, false), spl_token::id(), vec![]);
+    assert_eq!(AccountRules::new().mint(&mint).check(&mint_account.info()), Ok(()));
+    assert_eq!(
+        AccountRules::new().mint(&other_mint).check(&mint_account.info()),
+        Err(E9thTokenError::InvalidMint.into())
+    );
+
+    // CPI targets
+    let mut fake_program = TestAccount::new(&AccountMeta::new_readonly(Pubkey::new_unique(), false), program_id, vec![]);
+    assert_eq!(check_token_program(&fake_program.info()), Err(ProgramError::IncorrectProgramId));
+    let mut token_program = TestAccount::new(&AccountMeta::new_readonly(spl_token::id(), false), program_id, vec![]);
+    assert_eq!(check_token_program(&token_program.info()), Ok(()));
+}
+
+#[test]
+fn test_legacy_update_settings_rejects_spoofed_accounts() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let (_, bump) = StakeProcessor::get_program_state_pda(&program_id).unwrap();
+    let state = ProgramState::new(admin, mint, bump);
+
+    // The program state PDA and its admin
+    let ix = instruction::legacy::update_settings(&program_id, &admin, Some(5), None, None, None);
+    let accounts = |state: &ProgramState| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, state),
+            TestAccount::new(&ix.accounts[1], system_program::id(), vec![]),
+        ]
+    };
+    let mut valid = accounts(&state);
+    assert_eq!(process_with(&program_id, &mut valid, &ix.data), Ok(()));
+    let updated: ProgramState = deserialize_account_data(&valid[0].info()).unwrap();
+    assert_eq!(updated.reward_rate, 5);
+    assert_spoofs_rejected(
+        &program_id,
+        || accounts(&state),
+        &ix.data,
+        &[
+            (Spoof::Address(0), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(0), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Unsigned(1), E9thTokenError::Unauthorized),
+        ],
+    );
+    let mut readonly_state = accounts(&state);
+    readonly_state[0].is_writable = false;
+    assert_eq!(
+        process_with(&program_id, &mut readonly_state, &ix.data),
+        Err(E9thTokenError::AccountNotWritable.into())
+    );
+    let mut impostor = accounts(&ProgramState::new(Pubkey::new_unique(), mint, bump));
+    assert_eq!(
+        process_with(&program_id, &mut impostor, &ix.data),
+        Err(E9thTokenError::InvalidAdmin.into())
+    );
+}
+
+#[test]
+fn test_legacy_mint_rejects_spoofed_accounts() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let (program_state_pda, bump) = StakeProcessor::get_program_state_pda(&program_id).unwrap();
+    let state = ProgramState::new(admin, mint, bump);
+
+    // The state's mint, a destination token account for it and the token program
+    let ix = instruction::legacy::mint(&program_id, &admin, &mint, &admin, 10);
+    assert_eq!(ix.accounts[0].pubkey, program_state_pda);
+    let accounts = || {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &state),
+            TestAccount::new(&ix.accounts[1], system_program::id(), vec![]),
+            TestAccount::new(&ix.accounts[2], spl_token::id(), vec![]),
+            TestAccount::token_account(&ix.accounts[3], mint, admin),
+            TestAccount::new(&ix.accounts[4], Pubkey::default(), vec![]),
+        ]
+    };
+    assert_spoofs_rejected(
+        &program_id,
+        accounts,
+        &ix.data,
+        &[
+            (Spoof::Address(0), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(0), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Unsigned(1), E9thTokenError::Unauthorized),
+            (Spoof::Address(2), E9thTokenError::InvalidMint),
+            (Spoof::Owner(3), E9thTokenError::InvalidAccountOwner),
+        ],
+    );
+    let mut wrong_destination = accounts();
+    wrong_destination[3] = TestAccount::token_account(&ix.accounts[3], Pubkey::new_unique(), admin);
+    assert_eq!(
+        process_with(&program_id, &mut wrong_destination, &ix.data),
+        Err(E9thTokenError::InvalidMint.into())
+    );
+    let mut fake_token_program = accounts();
+    fake_token_program[4].key = Pubkey::new_unique();
+    assert_eq!(
+        process_with(&program_id, &mut fake_token_program, &ix.data),
+        Err(ProgramError::IncorrectProgramId)
+    );
+}
+
+#[test]
+fn test_legacy_burn_rejects_spoofed_accounts() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let (_, bump) = StakeProcessor::get_program_state_pda(&program_id).unwrap();
+    let state = ProgramState::new(admin, mint, bump);
+
+    // The optional token config must be the program's, for the same mint
+    let ix = instruction::legacy::burn(&program_id, &admin, &mint, 10, true);
+    let accounts = |config_meta: &AccountMeta, config: &TokenConfig| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &state),
+            TestAccount::new(&ix.accounts[1], system_program::id(), vec![]),
+            TestAccount::new(&ix.accounts[2], spl_token::id(), vec![]),
+            TestAccount::token_account(&ix.accounts[3], mint, admin),
+            TestAccount::new(&ix.accounts[4], Pubkey::default(), vec![]),
+            TestAccount::with_state(config_meta, program_id, config),
+        ]
+    };
+    let other_mint = Pubkey::new_unique();
+    let (other_config, other_bump) = Processor::get_token_config_pda(&program_id, &other_mint);
+    let mut other_meta = ix.accounts[5].clone();
+    other_meta.pubkey = other_config;
+    let mut wrong_config = accounts(&other_meta, &TokenConfig::new(admin, admin, admin, other_mint, 0, other_bump));
+    assert_eq!(
+        process_with(&program_id, &mut wrong_config, &ix.data),
+        Err(E9thTokenError::InvalidMint.into())
+    );
+    let mut misplaced_config = accounts(&other_meta, &TokenConfig::new(admin, admin, admin, mint, 0, other_bump));
+    assert_eq!(
+        process_with(&program_id, &mut misplaced_config, &ix.data),
+        Err(E9thTokenError::UnexpectedAccount.into())
+    );
+    let config = TokenConfig::new(admin, admin, admin, mint, 0, Processor::get_token_config_pda(&program_id, &mint).1);
+    assert_spoofs_rejected(
+        &program_id,
+        || accounts(&ix.accounts[5], &config),
+        &ix.data,
+        &[
+            (Spoof::Address(0), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(0), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Unsigned(1), E9thTokenError::Unauthorized),
+            (Spoof::Owner(5), E9thTokenError::InvalidAccountOwner),
+        ],
+    );
+}
+
+#[test]
+fn test_legacy_staking_is_retired() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let user = Pubkey::new_unique();
+    let (_, bump) = StakeProcessor::get_program_state_pda(&program_id).unwrap();
+    let state = ProgramState::new(Pubkey::new_unique(), mint, bump);
+    let stake = StakeAccount::new(user, 1_000, 1, 0, 0);
+
+    // Even with a mature stake and valid accounts, nothing is staked, paid or logged
+    for ix in [
+        instruction::legacy::stake(&program_id, &user, &mint, 1_000, 30),
+        instruction::legacy::unstake(&program_id, &user, &mint),
+        instruction::legacy::claim_rewards(&program_id, &user, &mint),
+    ] {
+        let mut accounts = vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &state),
+            TestAccount::with_state(&ix.accounts[1], program_id, &stake),
+            TestAccount::new(&ix.accounts[2], system_program::id(), vec![]),
+            TestAccount::token_account(&ix.accounts[3], mint, user),
+            TestAccount::new(&ix.accounts[4], Pubkey::default(), vec![]),
+        ];
+        let data = accounts[1].data.clone();
+        assert_eq!(
+            process_with(&program_id, &mut accounts, &ix.data),
+            Err(E9thTokenError::LegacyStakingRetired.into())
+        );
+        assert_eq!(accounts[1].data, data);
+        assert!(events().is_empty() && invoked().is_empty());
+    }
+}
+
+#[test]
+fn test_utility_tiers() {
+    let thresholds = sample_tier_thresholds();
+    assert!(thresholds.is_valid());
+    assert!(!TierThresholds { bronze: 0, ..thresholds }.is_valid());
+    assert!(!TierThresholds { silver: 1_000, ..thresholds }.is_valid());
+    assert!(!TierThresholds { gold: 5_000, ..thresholds }.is_valid());
+    assert_eq!(thresholds.tier_for(999), UtilityTier::None);
+    assert_eq!(thresholds.tier_for(1_000), UtilityTier::Bronze);
+    assert_eq!(thresholds.tier_for(99_999), UtilityTier::Silver);
+    assert_eq!(thresholds.tier_for(u64::MAX), UtilityTier::Gold);
+    assert!(UtilityTier::Gold > UtilityTier::Silver && UtilityTier::Bronze > UtilityTier::None);
+    // The return data is the Borsh-encoded tier
+    assert_eq!(UtilityTier::Silver.try_to_vec().unwrap(), vec![2]);
+
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+    let (config_pda, bump) = Processor::get_token_config_pda(&program_id, &mint);
+    let config = TokenConfig::new(owner, owner, owner, mint, 0, bump);
+    let (tier_config_pda, tier_bump) = TierProcessor::get_tier_config_pda(&program_id, &config_pda);
+    let tier_config = TierConfig::new(config_pda, thresholds, tier_bump);
+
+    // SetTierThresholds: owner only, valid thresholds, replaces existing ones
+    let raised = TierThresholds { bronze: 2_000, silver: 20_000, gold: 200_000 };
+    let ix = instruction::set_tier_thresholds(&program_id, &mint, &owner, raised);
+    assert_eq!(ix.accounts[1].pubkey, tier_config_pda);
+    let accounts = || {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &config),
+            TestAccount::with_state(&ix.accounts[1], program_id, &tier_config),
+            TestAccount::new(&ix.accounts[2], system_program::id(), vec![]),
+            TestAccount::new(&ix.accounts[3], Pubkey::default(), vec![]),
+        ]
+    };
+    let mut valid = accounts();
+    assert_eq!(process_with(&program_id, &mut valid, &ix.data), Ok(()));
+    let updated: TierConfig = deserialize_account_data(&valid[1].info()).unwrap();
+    assert_eq!((updated.config, updated.thresholds, updated.bump), (config_pda, raised, tier_bump));
+    let mut impostor = accounts();
+    impostor[2].key = Pubkey::new_unique();
+    assert_eq!(
+        process_with(&program_id, &mut impostor, &ix.data),
+        Err(E9thTokenError::InvalidAdmin.into())
+    );
+    let invalid = instruction::set_tier_thresholds(
+        &program_id,
+        &mint,
+        &owner,
+        TierThresholds { silver: 200_000, ..raised },
+    );
+    assert_eq!(
+        process_with(&program_id, &mut accounts(), &invalid.data),
+        Err(E9thTokenError::InvalidTierThresholds.into())
+    );
+
+    // VerifyTier: held plus staked, with the stake entry optional
+    let ix = instruction::verify_tier(&program_id, &mint, &wallet);
+    let (stake_pda, stake_bump) = Processor::get_stake_entry_pda(&program_id, &config_pda, &wallet);
+    assert_eq!(ix.accounts[4].pubkey, stake_pda);
+    let accounts = |stake: Option<&StakeEntry>| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &config),
+            TestAccount::with_state(&ix.accounts[1], program_id, &tier_config),
+            TestAccount::new(&ix.accounts[2], system_program::id(), vec![]),
+            TestAccount::token_account(&ix.accounts[3], mint, wallet),
+            match stake {
+                Some(stake) => TestAccount::with_state(&ix.accounts[4], program_id, stake),
+                None => TestAccount::new(&ix.accounts[4], system_program::id(), vec![]),
+            },
+        ]
+    };
+    let holdings = |accounts: &mut [TestAccount]| {
+        let infos: Vec<AccountInfo> = accounts.iter_mut().map(TestAccount::info).collect();
+        TierProcessor::wallet_holdings(&program_id, &infos[0], &config, &infos[2], &infos[3], &infos[4])
+    };
+    let stake = StakeEntry::new(wallet, 9_500, 30, 0, 0, stake_bump);
+    assert_eq!(holdings(&mut accounts(None)), Ok(1_000));
+    assert_eq!(holdings(&mut accounts(Some(&stake))), Ok(10_500));
+    assert_eq!(process_with(&program_id, &mut accounts(Some(&stake)), &ix.data), Ok(()));
+
+    let mut no_tiers = accounts(None);
+    no_tiers[1] = TestAccount::new(&ix.accounts[1], system_program::id(), vec![]);
+    assert_eq!(
+        process_with(&program_id, &mut no_tiers, &ix.data),
+        Err(E9thTokenError::AccountNotInitialized.into())
+    );
+    let mut borrowed_tokens = accounts(None);
+    borrowed_tokens[3] = TestAccount::token_account(&ix.accounts[3], mint, Pubkey::new_unique());
+    assert_eq!(
+        process_with(&program_id, &mut borrowed_tokens, &ix.data),
+        Err(E9thTokenError::InvalidTokenAccount.into())
+    );
+    let mut borrowed_stake = accounts(Some(&stake));
+    borrowed_stake[4].key = Processor::get_stake_entry_pda(&program_id, &config_pda, &owner).0;
+    assert_eq!(
+        process_with(&program_id, &mut borrowed_stake, &ix.data),
+        Err(E9thTokenError::UnexpectedAccount.into())
+    );
+}
+
+/// Accounts for an enhanced `Stake` by `user` with the membership accounts,
+/// holding 50_000 tokens against an empty vault and no stake entry yet
+fn enhanced_stake_accounts(program_id: &Pubkey, ix: &Instruction, config: &TokenConfig, user: Pubkey) -> Vec<TestAccount> {
+    let (config_pda, _) = Processor::get_token_config_pda(program_id, &config.mint);
+    let (_, tier_bump) = TierProcessor::get_tier_config_pda(program_id, &config_pda);
+    let tier_config = TierConfig::new(config_pda, sample_tier_thresholds(), tier_bump);
+    vec![
+        TestAccount::with_state(&ix.accounts[0], *program_id, config),
+        TestAccount::empty(&ix.accounts[1]),
+        TestAccount::token_account_with(&ix.accounts[2], config.mint, user, 50_000),
+        TestAccount::token_account_with(&ix.accounts[3], config.mint, config_pda, 0),
+        TestAccount::new(&ix.accounts[4], Pubkey::default(), vec![]),
+        TestAccount::wallet(&ix.accounts[5]),
+        TestAccount::new(&ix.accounts[6], Pubkey::default(), vec![]),
+        TestAccount::with_state(&ix.accounts[7], *program_id, &tier_config),
+        TestAccount::empty(&ix.accounts[8]),
+    ]
+}
+
+/// The `Unstake` accounts matching [enhanced_stake_accounts] after staking
+fn enhanced_unstake_accounts(ix: &Instruction, staked: &[TestAccount]) -> Vec<TestAccount> {
+    [0, 1, 2, 3, 4, 5, 7, 8]
+        .iter()
+        .zip(&ix.accounts)
+        .map(|(&index, meta)| staked[index].reuse(meta))
+        .collect()
+}
+
+#[test]
+fn test_enhanced_staking() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let user = Pubkey::new_unique();
+    let config = sample_token_config(&program_id, &mint, &owner, &owner, &owner);
+    let unlocked_at = 1_650_000_000 + 2 * 24 * 60 * 60;
+
+    // Stake moves the tokens into the vault and locks them for the minimum period
+    let ix = instruction::stake(&program_id, &mint, &user, 20_000);
+    let mut staked = enhanced_stake_accounts(&program_id, &ix, &config, user);
+    assert_eq!(process_with(&program_id, &mut staked, &ix.data), Ok(()));
+    let entry: StakeEntry = deserialize_account_data(&staked[1].info()).unwrap();
+    assert_eq!((entry.staker, entry.amount, entry.start_epoch), (user, 20_000, 100));
+    assert_eq!((entry.period, entry.lock_time), (1, unlocked_at));
+    assert_eq!((staked[2].token_amount(), staked[3].token_amount()), (30_000, 20_000));
+    assert_eq!(load_token_config(&program_id, &staked[0].info()).unwrap().total_staked, 20_000);
+    assert!(events().contains(&E9thEvent::Staked(Staked { staker: user, amount: 20_000, period: 1, total_staked: 20_000 })));
+
+    // Adding to the entry restarts both the holding period and the lock
+    set_clock(1_650_000_100, 101);
+    let ix = instruction::stake(&program_id, &mint, &user, 5_000);
+    assert_eq!(process_with(&program_id, &mut staked, &ix.data), Ok(()));
+    let entry: StakeEntry = deserialize_account_data(&staked[1].info()).unwrap();
+    assert_eq!((entry.amount, entry.start_epoch, entry.lock_time), (25_000, 101, unlocked_at + 100));
+    assert_eq!(staked[3].token_amount(), 25_000);
+
+    // Unstaking waits for the lock and can't take more than the entry holds
+    let ix = instruction::unstake(&program_id, &mint, &user, 10_000);
+    let mut unstaked = enhanced_unstake_accounts(&ix, &staked);
+    assert_eq!(
+        process_with(&program_id, &mut unstaked, &ix.data),
+        Err(E9thTokenError::StakeNotMature.into())
+    );
+    set_clock(unlocked_at as i64 + 100, 103);
+    let too_much = instruction::unstake(&program_id, &mint, &user, 25_001);
+    assert_eq!(
+        process_with(&program_id, &mut unstaked, &too_much.data),
+        Err(E9thTokenError::InsufficientFunds.into())
+    );
+    assert_eq!(process_with(&program_id, &mut unstaked, &ix.data), Ok(()));
+    let entry: StakeEntry = deserialize_account_data(&unstaked[1].info()).unwrap();
+    assert_eq!(entry.amount, 15_000);
+    assert_eq!((unstaked[2].token_amount(), unstaked[3].token_amount()), (35_000, 15_000));
+    assert_eq!(load_token_config(&program_id, &unstaked[0].info()).unwrap().total_staked, 15_000);
+    assert!(events().contains(&E9thEvent::Unstaked(Unstaked { staker: user, amount: 10_000, total_staked: 15_000 })));
+
+    // Unstaking needs an entry, and staking needs staking enabled
+    let fresh = enhanced_stake_accounts(&program_id, &instruction::stake(&program_id, &mint, &user, 1), &config, user);
+    let mut never_staked = enhanced_unstake_accounts(&ix, &fresh);
+    assert_eq!(
+        process_with(&program_id, &mut never_staked, &ix.data),
+        Err(E9thTokenError::StakeAccountNotFound.into())
+    );
+    let mut disabled = config.clone();
+    disabled.staking_enabled = false;
+    let ix = instruction::stake(&program_id, &mint, &user, 20_000);
+    assert_eq!(
+        process_with(&program_id, &mut enhanced_stake_accounts(&program_id, &ix, &disabled, user), &ix.data),
+        Err(E9thTokenError::InvalidStakePeriod.into())
+    );
+    set_clock(1_650_000_000, 100);
+}
+
+#[test]
+fn test_membership_passes() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let user = Pubkey::new_unique();
+    let config = sample_token_config(&program_id, &mint, &owner, &owner, &owner);
+    let (config_pda, _) = Processor::get_token_config_pda(&program_id, &mint);
+    let (pass_pda, _) = MembershipProcessor::get_membership_pass_pda(&program_id, &config_pda, &user);
+
+    // Stake issues the pass for the escrowed entry
+    let ix = instruction::stake(&program_id, &mint, &user, 20_000);
+    assert_eq!(ix.accounts[8].pubkey, pass_pda);
+    let mut staked = enhanced_stake_accounts(&program_id, &ix, &config, user);
+    assert_eq!(process_with(&program_id, &mut staked, &ix.data), Ok(()));
+    let pass: MembershipPass = deserialize_account_data(&staked[8].info()).unwrap();
+    assert_eq!((pass.holder, pass.tier, pass.staked), (user, UtilityTier::Silver, 20_000));
+    assert_eq!(
+        MembershipProcessor::load_pass_tier(&program_id, &config_pda, &user, &staked[8].info()),
+        Ok(UtilityTier::Silver)
+    );
+    assert!(events().contains(&E9thEvent::MembershipChanged(MembershipChanged {
+        holder: user,
+        tier: UtilityTier::Silver,
+        staked: 20_000,
+    })));
+
+    // Below Bronze no pass is issued, and without the membership accounts the
+    // stake goes through untouched
+    let small = instruction::stake(&program_id, &mint, &user, 500);
+    let mut not_issued = enhanced_stake_accounts(&program_id, &small, &config, user);
+    assert_eq!(process_with(&program_id, &mut not_issued, &small.data), Ok(()));
+    assert!(not_issued[8].data.is_empty());
+    let mut plain = enhanced_stake_accounts(&program_id, &ix, &config, user);
+    plain.truncate(7);
+    assert_eq!(process_with(&program_id, &mut plain, &ix.data), Ok(()));
+
+    // Another holder's pass can't be written
+    let mut borrowed = enhanced_stake_accounts(&program_id, &ix, &config, user);
+    borrowed[8].key = MembershipProcessor::get_membership_pass_pda(&program_id, &config_pda, &owner).0;
+    assert_eq!(
+        process_with(&program_id, &mut borrowed, &ix.data),
+        Err(E9thTokenError::UnexpectedAccount.into())
+    );
+
+    // Unstake always takes the pass and lowers it to what stays staked
+    set_clock(1_660_000_000, 120);
+    let ix = instruction::unstake(&program_id, &mint, &user, 19_500);
+    let mut without_pass = enhanced_unstake_accounts(&ix, &staked);
+    without_pass.pop();
+    assert_eq!(
+        process_with(&program_id, &mut without_pass, &ix.data),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+    let mut lowered = enhanced_unstake_accounts(&ix, &staked);
+    assert_eq!(process_with(&program_id, &mut lowered, &ix.data), Ok(()));
+    let pass: MembershipPass = deserialize_account_data(&lowered[7].info()).unwrap();
+    assert_eq!((pass.tier, pass.staked), (UtilityTier::None, 500));
+    set_clock(1_650_000_000, 100);
+}
+
+#[test]
+fn test_merchant_vouchers() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let operator = Pubkey::new_unique();
+    let merchant_authority = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let (config_pda, bump) = Processor::get_token_config_pda(&program_id, &mint);
+    let config = TokenConfig::new(operator, operator, operator, mint, 0, bump);
+    let (merchant_pda, merchant_bump) =
+        MerchantProcessor::get_merchant_pda(&program_id, &config_pda, &merchant_authority);
+    let merchant = |vault, mode| Merchant::new(config_pda, merchant_authority, vault, 500, mode, merchant_bump);
+
+    // RegisterMerchant: operator only, updates the voucher terms in place
+    let ix = instruction::register_merchant(
+        &program_id,
+        &mint,
+        &operator,
+        &merchant_authority,
+        750,
+        VoucherMode::Burn,
+        false,
+    );
+    assert_eq!(ix.accounts[1].pubkey, merchant_pda);
+    let vault = ix.accounts[3].pubkey;
+    let registered = Merchant { vouchers_issued: 3, vouchers_redeemed: 1, ..merchant(vault, VoucherMode::Lock) };
+    let accounts = |merchant: &Merchant| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &config),
+            TestAccount::with_state(&ix.accounts[1], program_id, merchant),
+            TestAccount::new(&ix.accounts[2], system_program::id(), vec![]),
+            TestAccount::token_account(&ix.accounts[3], mint, merchant_pda),
+            TestAccount::new(&ix.accounts[4], system_program::id(), vec![]),
+            TestAccount::new(&ix.accounts[5], Pubkey::default(), vec![]),
+        ]
+    };
+    let mut valid = accounts(&registered);
+    assert_eq!(process_with(&program_id, &mut valid, &ix.data), Ok(()));
+    let updated = MerchantProcessor::load_merchant(&program_id, &config_pda, &valid[1].info()).unwrap();
+    assert_eq!(
+        (updated.voucher_cost, updated.voucher_mode, updated.active, updated.vouchers_issued),
+        (750, VoucherMode::Burn, false, 3)
+    );
+    let mut impostor = accounts(&registered);
+    impostor[4].key = merchant_authority;
+    assert_eq!(
+        process_with(&program_id, &mut impostor, &ix.data),
+        Err(E9thTokenError::InvalidAdmin.into())
+    );
+    let free = instruction::register_merchant(
+        &program_id,
+        &mint,
+        &operator,
+        &merchant_authority,
+        0,
+        VoucherMode::Lock,
+        true,
+    );
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&registered), &free.data),
+        Err(E9thTokenError::InvalidAmount.into())
+    );
+    // The vault can't be swapped out from under outstanding locked vouchers
+    let moved = Merchant { vault: Pubkey::new_unique(), ..registered.clone() };
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&moved), &ix.data),
+        Err(E9thTokenError::UnexpectedAccount.into())
+    );
+    let settled = Merchant { vouchers_redeemed: 3, ..moved };
+    assert_eq!(process_with(&program_id, &mut accounts(&settled), &ix.data), Ok(()));
+
+    // MintVoucher: only for active merchants of an unpaused token, once per id
+    let ix = instruction::mint_voucher(&program_id, &mint, &holder, &merchant_authority, 7);
+    let (voucher_pda, voucher_bump) = VoucherProcessor::get_voucher_pda(&program_id, &merchant_pda, &holder, 7);
+    assert_eq!((ix.accounts[2].pubkey, ix.accounts[6].pubkey), (voucher_pda, vault));
+    let voucher = Voucher::new(merchant_pda, holder, 7, 500, VoucherMode::Lock, 1_700_000_000, voucher_bump);
+    let accounts = |config: &TokenConfig, merchant: &Merchant, voucher: Option<&Voucher>| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, config),
+            TestAccount::with_state(&ix.accounts[1], program_id, merchant),
+            match voucher {
+                Some(voucher) => TestAccount::with_state(&ix.accounts[2], program_id, voucher),
+                None => TestAccount::new(&ix.accounts[2], system_program::id(), vec![]),
+            },
+            TestAccount::new(&ix.accounts[3], system_program::id(), vec![]),
+            TestAccount::token_account(&ix.accounts[4], mint, holder),
+            TestAccount::new(&ix.accounts[5], spl_token::id(), vec![]),
+            TestAccount::token_account(&ix.accounts[6], mint, merchant_pda),
+            TestAccount::new(&ix.accounts[7], Pubkey::default(), vec![]),
+            TestAccount::new(&ix.accounts[8], Pubkey::default(), vec![]),
+        ]
+    };
+    let active = merchant(vault, VoucherMode::Lock);
+    let inactive = Merchant { active: false, ..active.clone() };
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&config, &inactive, None), &ix.data),
+        Err(E9thTokenError::MerchantInactive.into())
+    );
+    let paused = TokenConfig { is_paused: true, ..config.clone() };
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&paused, &active, None), &ix.data),
+        Err(E9thTokenError::TokenPaused.into())
+    );
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&config, &active, Some(&voucher)), &ix.data),
+        Err(E9thTokenError::AccountAlreadyInitialized.into())
+    );
+    let mut wrong_vault = accounts(&config, &active, None);
+    wrong_vault[6].key = Pubkey::new_unique();
+    assert_eq!(
+        process_with(&program_id, &mut wrong_vault, &ix.data),
+        Err(E9thTokenError::UnexpectedAccount.into())
+    );
+
+    // RedeemVoucher: the merchant's authority redeems each voucher once
+    let ix = instruction::redeem_voucher(&program_id, &mint, &merchant_authority, &holder, 7);
+    assert_eq!(ix.accounts[2].pubkey, voucher_pda);
+    let accounts = |voucher: &Voucher| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &config),
+            TestAccount::with_state(&ix.accounts[1], program_id, &active),
+            TestAccount::with_state(&ix.accounts[2], program_id, voucher),
+            TestAccount::new(&ix.accounts[3], system_program::id(), vec![]),
+            TestAccount::token_account(&ix.accounts[4], mint, holder),
+            TestAccount::token_account(&ix.accounts[5], mint, merchant_pda),
+            TestAccount::new(&ix.accounts[6], Pubkey::default(), vec![]),
+        ]
+    };
+    let redeemed = Voucher { redeemed: true, redeemed_at: 1_700_000_100, ..voucher.clone() };
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&redeemed), &ix.data),
+        Err(E9thTokenError::VoucherAlreadyRedeemed.into())
+    );
+    let mut wrong_signer = accounts(&voucher);
+    wrong_signer[3].key = holder;
+    assert_eq!(
+        process_with(&program_id, &mut wrong_signer, &ix.data),
+        Err(E9thTokenError::Unauthorized.into())
+    );
+    // A voucher issued by another merchant can't be redeemed here
+    let other_merchant = MerchantProcessor::get_merchant_pda(&program_id, &config_pda, &operator).0;
+    let (other_voucher, other_bump) = VoucherProcessor::get_voucher_pda(&program_id, &other_merchant, &holder, 7);
+    let mut foreign = accounts(&Voucher { merchant: other_merchant, bump: other_bump, ..voucher.clone() });
+    foreign[2].key = other_voucher;
+    assert_eq!(
+        process_with(&program_id, &mut foreign, &ix.data),
+        Err(E9thTokenError::UnexpectedAccount.into())
+    );
+    let reclaimed = Voucher { reclaimed: true, ..voucher.clone() };
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&reclaimed), &ix.data),
+        Err(E9thTokenError::VoucherReclaimed.into())
+    );
+    set_clock(voucher.expires_at as i64, 100);
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&voucher), &ix.data),
+        Err(E9thTokenError::VoucherExpired.into())
+    );
+
+    // ReclaimVoucher: the holder takes locked E9TH back once the voucher expired
+    // or the merchant was deactivated, even while paused
+    let ix = instruction::reclaim_voucher(&program_id, &mint, &merchant_authority, &holder, 7);
+    assert_eq!((ix.accounts[2].pubkey, ix.accounts[5].pubkey), (voucher_pda, vault));
+    let accounts = |config: &TokenConfig, merchant: &Merchant, voucher: &Voucher| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, config),
+            TestAccount::with_state(&ix.accounts[1], program_id, merchant),
+            TestAccount::with_state(&ix.accounts[2], program_id, voucher),
+            TestAccount::new(&ix.accounts[3], system_program::id(), vec![]),
+            TestAccount::token_account(&ix.accounts[4], mint, holder),
+            TestAccount::token_account(&ix.accounts[5], mint, merchant_pda),
+            TestAccount::new(&ix.accounts[6], Pubkey::default(), vec![]),
+        ]
+    };
+    let mut expired = accounts(&paused, &active, &voucher);
+    assert_eq!(process_with(&program_id, &mut expired, &ix.data), Ok(()));
+    assert_eq!((expired[4].token_amount(), expired[5].token_amount()), (1_500, 500));
+    let stored: Voucher = deserialize_account_data(&expired[2].info()).unwrap();
+    assert!(stored.reclaimed && !stored.redeemed);
+    assert_eq!(
+        events(),
+        vec![E9thEvent::VoucherReclaimed(VoucherReclaimed {
+            merchant: merchant_pda,
+            holder,
+            voucher_id: 7,
+            amount: 500,
+        })]
+    );
+    assert_eq!(
+        process_with(&program_id, &mut expired, &ix.data),
+        Err(E9thTokenError::VoucherReclaimed.into())
+    );
+    let mut impostor = accounts(&config, &active, &voucher);
+    impostor[3].key = merchant_authority;
+    assert_eq!(
+        process_with(&program_id, &mut impostor, &ix.data),
+        Err(E9thTokenError::Unauthorized.into())
+    );
+    set_clock(voucher.expires_at as i64 - 1, 100);
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&config, &active, &voucher), &ix.data),
+        Err(E9thTokenError::VoucherNotReclaimable.into())
+    );
+    assert_eq!(process_with(&program_id, &mut accounts(&config, &inactive, &voucher), &ix.data), Ok(()));
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&config, &inactive, &redeemed), &ix.data),
+        Err(E9thTokenError::VoucherAlreadyRedeemed.into())
+    );
+    // Burned vouchers have nothing in the vault to reclaim
+    let burned = Voucher { mode: VoucherMode::Burn, ..voucher.clone() };
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&config, &inactive, &burned), &ix.data),
+        Err(E9thTokenError::VoucherNotReclaimable.into())
+    );
+    set_clock(1_650_000_000, 100);
+}
+
+#[test]
+fn test_voucher_handlers_reject_spoofed_accounts() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let operator = Pubkey::new_unique();
+    let merchant_authority = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let (config_pda, bump) = Processor::get_token_config_pda(&program_id, &mint);
+    let config = TokenConfig::new(operator, operator, operator, mint, 0, bump);
+    let paused = TokenConfig { is_paused: true, ..config.clone() };
+    let (merchant_pda, merchant_bump) =
+        MerchantProcessor::get_merchant_pda(&program_id, &config_pda, &merchant_authority);
+    let vault = spl_associated_token_account::get_associated_token_address(&merchant_pda, &mint);
+    let merchant = Merchant::new(config_pda, merchant_authority, vault, 500, VoucherMode::Lock, merchant_bump);
+    let (_, voucher_bump) = VoucherProcessor::get_voucher_pda(&program_id, &merchant_pda, &holder, 7);
+    let voucher = Voucher::new(merchant_pda, holder, 7, 500, VoucherMode::Lock, 1_650_000_000, voucher_bump);
+
+    // MintVoucher: the merchant, the holder's own voucher PDA and the merchant's vault
+    let ix = instruction::mint_voucher(&program_id, &mint, &holder, &merchant_authority, 7);
+    let accounts_with = |config: &TokenConfig| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, config),
+            TestAccount::with_state(&ix.accounts[1], program_id, &merchant),
+            TestAccount::empty(&ix.accounts[2]),
+            TestAccount::wallet(&ix.accounts[3]),
+            TestAccount::token_account(&ix.accounts[4], mint, holder),
+            TestAccount::new(&ix.accounts[5], spl_token::id(), vec![]),
+            TestAccount::token_account(&ix.accounts[6], mint, merchant_pda),
+            TestAccount::new(&ix.accounts[7], Pubkey::default(), vec![]),
+            TestAccount::new(&ix.accounts[8], Pubkey::default(), vec![]),
+        ]
+    };
+    let accounts = || accounts_with(&config);
+    assert_eq!(process_with(&program_id, &mut accounts(), &ix.data), Ok(()));
+    assert_spoofs_rejected(
+        &program_id,
+        accounts,
+        &ix.data,
+        &[
+            (Spoof::Owner(0), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(1), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(1), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(2), E9thTokenError::UnexpectedAccount),
+            (Spoof::Unsigned(3), E9thTokenError::Unauthorized),
+            (Spoof::Owner(4), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(5), E9thTokenError::InvalidMint),
+            (Spoof::Address(6), E9thTokenError::UnexpectedAccount),
+        ],
+    );
+    assert_eq!(
+        process_with(&program_id, &mut accounts_with(&paused), &ix.data),
+        Err(E9thTokenError::TokenPaused.into())
+    );
+
+    // RedeemVoucher: the merchant's authority, the voucher and the vault
+    let ix = instruction::redeem_voucher(&program_id, &mint, &merchant_authority, &holder, 7);
+    let accounts_with = |config: &TokenConfig| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, config),
+            TestAccount::with_state(&ix.accounts[1], program_id, &merchant),
+            TestAccount::with_state(&ix.accounts[2], program_id, &voucher),
+            TestAccount::wallet(&ix.accounts[3]),
+            TestAccount::token_account(&ix.accounts[4], mint, holder),
+            TestAccount::token_account(&ix.accounts[5], mint, merchant_pda),
+            TestAccount::new(&ix.accounts[6], Pubkey::default(), vec![]),
+        ]
+    };
+    let accounts = || accounts_with(&config);
+    assert_eq!(process_with(&program_id, &mut accounts(), &ix.data), Ok(()));
+    assert_spoofs_rejected(
+        &program_id,
+        accounts,
+        &ix.data,
+        &[
+            (Spoof::Owner(0), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(1), E9thTokenError::UnexpectedAccount),
+            (Spoof::Address(2), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(2), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Unsigned(3), E9thTokenError::Unauthorized),
+            (Spoof::Owner(4), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(5), E9thTokenError::UnexpectedAccount),
+        ],
+    );
+    assert_eq!(
+        process_with(&program_id, &mut accounts_with(&paused), &ix.data),
+        Err(E9thTokenError::TokenPaused.into())
+    );
+
+    // ReclaimVoucher: the holder, the voucher and the vault; pausing doesn't block it
+    let ix = instruction::reclaim_voucher(&program_id, &mint, &merchant_authority, &holder, 7);
+    let inactive = Merchant { active: false, ..merchant.clone() };
+    let accounts_with = |config: &TokenConfig| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, config),
+            TestAccount::with_state(&ix.accounts[1], program_id, &inactive),
+            TestAccount::with_state(&ix.accounts[2], program_id, &voucher),
+            TestAccount::wallet(&ix.accounts[3]),
+            TestAccount::token_account(&ix.accounts[4], mint, holder),
+            TestAccount::token_account(&ix.accounts[5], mint, merchant_pda),
+            TestAccount::new(&ix.accounts[6], Pubkey::default(), vec![]),
+        ]
+    };
+    let accounts = || accounts_with(&config);
+    assert_eq!(process_with(&program_id, &mut accounts_with(&paused), &ix.data), Ok(()));
+    assert_spoofs_rejected(
+        &program_id,
+        accounts,
+        &ix.data,
+        &[
+            (Spoof::Owner(0), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(1), E9thTokenError::UnexpectedAccount),
+            (Spoof::Address(2), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(2), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Unsigned(3), E9thTokenError::Unauthorized),
+            (Spoof::Owner(4), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(5), E9thTokenError::UnexpectedAccount),
+        ],
+    );
+}
+
+#[test]
+fn test_merchant_payments() {
+    let terms = PaymentTerms {
+        burn_basis_points: 1_000,
+        bronze_discount_basis_points: 500,
+        silver_discount_basis_points: 1_000,
+        gold_discount_basis_points: 2_500,
+    };
+    assert!(terms.is_valid());
+    assert!(!PaymentTerms { gold_discount_basis_points: 10_001, ..terms }.is_valid());
+    // The discount comes off the list price, the burn share off what is paid
+    let quote = terms.quote(10_000, UtilityTier::Gold);
+    assert_eq!((quote.discount, quote.burned, quote.paid), (2_500, 750, 6_750));
+    let quote = terms.quote(10_000, UtilityTier::None);
+    assert_eq!((quote.discount, quote.burned, quote.paid), (0, 1_000, 9_000));
+    let quote = terms.quote(9, UtilityTier::Silver);
+    assert_eq!((quote.discount, quote.burned, quote.paid), (0, 0, 9));
+
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let operator = Pubkey::new_unique();
+    let merchant_authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let (config_pda, bump) = Processor::get_token_config_pda(&program_id, &mint);
+    let config = TokenConfig::new(operator, operator, operator, mint, 0, bump);
+    let (merchant_pda, merchant_bump) =
+        MerchantProcessor::get_merchant_pda(&program_id, &config_pda, &merchant_authority);
+    let merchant = Merchant::new(config_pda, merchant_authority, Pubkey::new_unique(), 500, VoucherMode::Lock, merchant_bump);
+
+    // SetPaymentTerms: operator only, each share at most 100%
+    let ix = instruction::set_payment_terms(&program_id, &mint, &operator, &merchant_authority, terms);
+    assert_eq!(ix.accounts[1].pubkey, merchant_pda);
+    let accounts = || {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &config),
+            TestAccount::with_state(&ix.accounts[1], program_id, &merchant),
+            TestAccount::new(&ix.accounts[2], system_program::id(), vec![]),
+        ]
+    };
+    let mut valid = accounts();
+    assert_eq!(process_with(&program_id, &mut valid, &ix.data), Ok(()));
+    let updated = MerchantProcessor::load_merchant(&program_id, &config_pda, &valid[1].info()).unwrap();
+    assert_eq!(updated.payment_terms, terms);
+    let mut impostor = accounts();
+    impostor[2].key = merchant_authority;
+    assert_eq!(
+        process_with(&program_id, &mut impostor, &ix.data),
+        Err(E9thTokenError::InvalidAdmin.into())
+    );
+    let invalid = instruction::set_payment_terms(
+        &program_id,
+        &mint,
+        &operator,
+        &merchant_authority,
+        PaymentTerms { burn_basis_points: 10_001, ..terms },
+    );
+    assert_eq!(
+        process_with(&program_id, &mut accounts(), &invalid.data),
+        Err(E9thTokenError::InvalidPaymentTerms.into())
+    );
+
+    // Pay: the tier comes from the payer's stake; without tiers there's no discount
+    let ix = instruction::pay(&program_id, &mint, &payer, &merchant_authority, 2_000, "INV-7");
+    let (blacklist_pda, blacklist_bump) = Processor::get_blacklist_pda(&program_id, &config_pda);
+    let (tier_config_pda, tier_bump) = TierProcessor::get_tier_config_pda(&program_id, &config_pda);
+    let (stake_pda, stake_bump) = Processor::get_stake_entry_pda(&program_id, &config_pda, &payer);
+    assert_eq!(
+        (ix.accounts[1].pubkey, ix.accounts[2].pubkey, ix.accounts[6].pubkey),
+        (blacklist_pda, tier_config_pda, stake_pda)
+    );
+    let tier_config = TierConfig::new(config_pda, sample_tier_thresholds(), tier_bump);
+    let stake = StakeEntry::new(payer, 10_000, 30, 0, 0, stake_bump);
+    let charging = Merchant { payment_terms: terms, ..merchant.clone() };
+    let accounts_with = |merchant: &Merchant, tiers: bool, stake: &StakeEntry| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &config),
+            TestAccount::with_state(&ix.accounts[1], program_id, &Blacklist::new(blacklist_bump)),
+            if tiers {
+                TestAccount::with_state(&ix.accounts[2], program_id, &tier_config)
+            } else {
+                TestAccount::new(&ix.accounts[2], system_program::id(), vec![])
+            },
+            TestAccount::with_state(&ix.accounts[3], program_id, merchant),
+            TestAccount::new(&ix.accounts[4], system_program::id(), vec![]),
+            TestAccount::token_account_with(&ix.accounts[5], mint, payer, 10_000),
+            TestAccount::with_state(&ix.accounts[6], program_id, stake),
+            TestAccount::token_account(&ix.accounts[7], mint, merchant_authority),
+            TestAccount::new(&ix.accounts[8], spl_token::id(), vec![]),
+            TestAccount::new(&ix.accounts[9], Pubkey::default(), vec![]),
+            TestAccount::new(&ix.accounts[10], Pubkey::default(), vec![]),
+        ]
+    };
+    let accounts = |merchant: &Merchant, tiers: bool| accounts_with(merchant, tiers, &stake);
+    // Silver: 200 off the 2_000 list price, then 10% of the rest burned
+    let mut paid = accounts(&charging, true);
+    assert_eq!(process_with(&program_id, &mut paid, &ix.data), Ok(()));
+    assert_eq!((paid[5].token_amount(), paid[7].token_amount()), (8_200, 2_620));
+    let config_after: TokenConfig = deserialize_account_data(&paid[0].info()).unwrap();
+    assert_eq!(config_after.burn_stats.utility, 180);
+    let mut paid = accounts(&charging, false);
+    assert_eq!(process_with(&program_id, &mut paid, &ix.data), Ok(()));
+    assert_eq!((paid[5].token_amount(), paid[7].token_amount()), (8_000, 2_800));
+    // The wallet balance doesn't count: 10_000 held but only 9_999 staked is Bronze
+    let short = StakeEntry { amount: 9_999, ..stake.clone() };
+    let mut paid = accounts_with(&charging, true, &short);
+    assert_eq!(process_with(&program_id, &mut paid, &ix.data), Ok(()));
+    assert_eq!((paid[5].token_amount(), paid[7].token_amount()), (8_100, 2_710));
+
+    let inactive = Merchant { active: false, ..charging.clone() };
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&inactive, true), &ix.data),
+        Err(E9thTokenError::MerchantInactive.into())
+    );
+    for reference in [String::new(), "x".repeat(MAX_ORDER_REFERENCE_LEN + 1)] {
+        let ix = instruction::pay(&program_id, &mint, &payer, &merchant_authority, 2_000, &reference);
+        assert_eq!(
+            process_with(&program_id, &mut accounts(&charging, true), &ix.data),
+            Err(E9thTokenError::InvalidOrderReference.into())
+        );
+    }
+    let free = instruction::pay(&program_id, &mint, &payer, &merchant_authority, 0, "INV-7");
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&charging, true), &free.data),
+        Err(E9thTokenError::InvalidAmount.into())
+    );
+    let mut wrong_memo = accounts(&charging, true);
+    wrong_memo[10].key = Pubkey::new_unique();
+    assert_eq!(
+        process_with(&program_id, &mut wrong_memo, &ix.data),
+        Err(E9thTokenError::UnexpectedAccount.into())
+    );
+    // Payments only go to the merchant authority's own token account
+    let mut diverted = accounts(&charging, true);
+    diverted[7] = TestAccount::token_account(&ix.accounts[7], mint, payer);
+    assert_eq!(
+        process_with(&program_id, &mut diverted, &ix.data),
+        Err(E9thTokenError::InvalidTokenAccount.into())
+    );
+}
+
+#[test]
+fn test_pay_rejects_spoofed_accounts() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let operator = Pubkey::new_unique();
+    let merchant_authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let (config_pda, bump) = Processor::get_token_config_pda(&program_id, &mint);
+    let config = TokenConfig::new(operator, operator, operator, mint, 0, bump);
+    let (_, blacklist_bump) = Processor::get_blacklist_pda(&program_id, &config_pda);
+    let (_, tier_bump) = TierProcessor::get_tier_config_pda(&program_id, &config_pda);
+    let (_, stake_bump) = Processor::get_stake_entry_pda(&program_id, &config_pda, &payer);
+    let (_, merchant_bump) = MerchantProcessor::get_merchant_pda(&program_id, &config_pda, &merchant_authority);
+    let merchant = Merchant::new(config_pda, merchant_authority, Pubkey::new_unique(), 500, VoucherMode::Lock, merchant_bump);
+
+    // The config's blacklist and tiers, the merchant, and the payer's own stake entry
+    let ix = instruction::pay(&program_id, &mint, &payer, &merchant_authority, 2_000, "INV-7");
+    let accounts_with = |config: &TokenConfig, blacklisted: &[Pubkey]| {
+        let mut blacklist = Blacklist::new(blacklist_bump);
+        for account in blacklisted {
+            blacklist.add_account(*account);
+        }
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, config),
+            TestAccount::with_state(&ix.accounts[1], program_id, &blacklist),
+            TestAccount::with_state(
+                &ix.accounts[2],
+                program_id,
+                &TierConfig::new(config_pda, sample_tier_thresholds(), tier_bump),
+            ),
+            TestAccount::with_state(&ix.accounts[3], program_id, &merchant),
+            TestAccount::wallet(&ix.accounts[4]),
+            TestAccount::token_account_with(&ix.accounts[5], mint, payer, 10_000),
+            TestAccount::with_state(&ix.accounts[6], program_id, &StakeEntry::new(payer, 10_000, 30, 0, 0, stake_bump)),
+            TestAccount::token_account(&ix.accounts[7], mint, merchant_authority),
+            TestAccount::mint(&ix.accounts[8], config_pda, 10_000),
+            TestAccount::new(&ix.accounts[9], Pubkey::default(), vec![]),
+            TestAccount::new(&ix.accounts[10], Pubkey::default(), vec![]),
+        ]
+    };
+    let accounts = || accounts_with(&config, &[]);
+    assert_eq!(process_with(&program_id, &mut accounts(), &ix.data), Ok(()));
+    assert_spoofs_rejected(
+        &program_id,
+        accounts,
+        &ix.data,
+        &[
+            (Spoof::Address(0), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(0), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(1), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(1), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(2), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(2), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(3), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(3), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Unsigned(4), E9thTokenError::Unauthorized),
+            (Spoof::Owner(5), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(6), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(6), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Owner(7), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(8), E9thTokenError::InvalidMint),
+        ],
+    );
+    let mut someone_elses_tokens = accounts();
+    someone_elses_tokens[5] = TestAccount::token_account_with(&ix.accounts[5], mint, merchant_authority, 10_000);
+    assert_eq!(
+        process_with(&program_id, &mut someone_elses_tokens, &ix.data),
+        Err(E9thTokenError::InvalidTokenAccount.into())
+    );
+
+    // Paused configs and blacklisted payers or merchants are refused
+    let paused = TokenConfig { is_paused: true, ..config.clone() };
+    assert_eq!(
+        process_with(&program_id, &mut accounts_with(&paused, &[]), &ix.data),
+        Err(E9thTokenError::TokenPaused.into())
+    );
+    for blacklisted in [payer, merchant_authority] {
+        assert_eq!(
+            process_with(&program_id, &mut accounts_with(&config, &[blacklisted]), &ix.data),
+            Err(E9thTokenError::AccountBlacklisted.into())
+        );
+    }
+}
+
+#[test]
+fn test_subscriptions() {
+    let terms = sample_subscription_terms();
+    assert!(terms.is_valid());
+    assert!(!SubscriptionTerms { period_seconds: 0, ..terms }.is_valid());
+    assert_eq!(terms.total_allowance(), Some(3_000));
+    assert_eq!(SubscriptionTerms { amount: u64::MAX, ..terms }.total_allowance(), None);
+
+    // Collections follow a fixed schedule from the first due date
+    let start = 1_700_000_000;
+    let mut schedule = Subscription::new(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        1,
+        Pubkey::new_unique(),
+        SubscriptionTerms { max_periods: 2, ..terms },
+        start,
+        255,
+    );
+    assert_eq!(schedule.collect(start - 1), Err(E9thTokenError::SubscriptionPeriodNotElapsed.into()));
+    assert_eq!(schedule.collect(start + 10), Ok(()));
+    assert_eq!(schedule.next_collection_at, start + terms.period_seconds);
+    assert_eq!(
+        schedule.collect(start + terms.period_seconds - 1),
+        Err(E9thTokenError::SubscriptionPeriodNotElapsed.into())
+    );
+    assert_eq!(schedule.collect(start + terms.period_seconds), Ok(()));
+    assert_eq!(
+        schedule.collect(start + 5 * terms.period_seconds),
+        Err(E9thTokenError::SubscriptionComplete.into())
+    );
+    schedule.cancelled = true;
+    assert_eq!(schedule.collect(u64::MAX), Err(E9thTokenError::SubscriptionCancelled.into()));
+
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let operator = Pubkey::new_unique();
+    let merchant_authority = Pubkey::new_unique();
+    let subscriber = Pubkey::new_unique();
+    let vault = Pubkey::new_unique();
+    let (config_pda, bump) = Processor::get_token_config_pda(&program_id, &mint);
+    let config = TokenConfig::new(operator, operator, operator, mint, 0, bump);
+    let (merchant_pda, merchant_bump) =
+        MerchantProcessor::get_merchant_pda(&program_id, &config_pda, &merchant_authority);
+    let merchant = Merchant::new(config_pda, merchant_authority, Pubkey::new_unique(), 500, VoucherMode::Lock, merchant_bump);
+    let (subscription_pda, subscription_bump) =
+        SubscriptionProcessor::get_subscription_pda(&program_id, &merchant_pda, &subscriber, 3);
+    let subscription = Subscription::new(merchant_pda, subscriber, 3, vault, terms, start, subscription_bump);
+
+    // CreateSubscription: checked before the subscription is created
+    let ix = instruction::create_subscription(&program_id, &mint, &subscriber, &merchant_authority, &vault, 3, terms);
+    assert_eq!(ix.accounts[2].pubkey, subscription_pda);
+    let accounts = |ix: &Instruction, merchant: &Merchant, existing: Option<&Subscription>| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &config),
+            TestAccount::with_state(&ix.accounts[1], program_id, merchant),
+            match existing {
+                Some(subscription) => TestAccount::with_state(&ix.accounts[2], program_id, subscription),
+                None => TestAccount::new(&ix.accounts[2], system_program::id(), vec![]),
+            },
+            TestAccount::new(&ix.accounts[3], system_program::id(), vec![]),
+            TestAccount::token_account(&ix.accounts[4], mint, subscriber),
+            TestAccount::new(&ix.accounts[5], Pubkey::default(), vec![]),
+            TestAccount::new(&ix.accounts[6], Pubkey::default(), vec![]),
+        ]
+    };
+    let unlimited = instruction::create_subscription(
+        &program_id,
+        &mint,
+        &subscriber,
+        &merchant_authority,
+        &vault,
+        3,
+        SubscriptionTerms { max_periods: 0, ..terms },
+    );
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&unlimited, &merchant, None), &unlimited.data),
+        Err(E9thTokenError::InvalidSubscriptionTerms.into())
+    );
+    let inactive = Merchant { active: false, ..merchant.clone() };
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&ix, &inactive, None), &ix.data),
+        Err(E9thTokenError::MerchantInactive.into())
+    );
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&ix, &merchant, Some(&subscription)), &ix.data),
+        Err(E9thTokenError::AccountAlreadyInitialized.into())
+    );
+    let mut borrowed_vault = accounts(&ix, &merchant, None);
+    borrowed_vault[4] = TestAccount::token_account(&ix.accounts[4], mint, merchant_authority);
+    assert_eq!(
+        process_with(&program_id, &mut borrowed_vault, &ix.data),
+        Err(E9thTokenError::InvalidTokenAccount.into())
+    );
+
+    // CollectSubscription: anyone can collect, but only into the merchant's account
+    let ix = instruction::collect_subscription(&program_id, &mint, &merchant_authority, &subscriber, &vault, 3);
+    assert_eq!(ix.accounts[2].pubkey, subscription_pda);
+    let accounts = |config: &TokenConfig, merchant: &Merchant| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, config),
+            TestAccount::with_state(&ix.accounts[1], program_id, merchant),
+            TestAccount::with_state(&ix.accounts[2], program_id, &subscription),
+            TestAccount::token_account(&ix.accounts[3], mint, subscriber),
+            TestAccount::token_account(&ix.accounts[4], mint, merchant_authority),
+            TestAccount::new(&ix.accounts[5], Pubkey::default(), vec![]),
+        ]
+    };
+    let paused = TokenConfig { is_paused: true, ..config.clone() };
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&paused, &merchant), &ix.data),
+        Err(E9thTokenError::TokenPaused.into())
+    );
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&config, &inactive), &ix.data),
+        Err(E9thTokenError::MerchantInactive.into())
+    );
+    let mut other_vault = accounts(&config, &merchant);
+    other_vault[3].key = Pubkey::new_unique();
+    assert_eq!(
+        process_with(&program_id, &mut other_vault, &ix.data),
+        Err(E9thTokenError::UnexpectedAccount.into())
+    );
+    let mut diverted = accounts(&config, &merchant);
+    diverted[4] = TestAccount::token_account(&ix.accounts[4], mint, operator);
+    assert_eq!(
+        process_with(&program_id, &mut diverted, &ix.data),
+        Err(E9thTokenError::InvalidTokenAccount.into())
+    );
+
+    // CancelSubscription: subscriber only, once
+    let ix = instruction::cancel_subscription(&program_id, &mint, &subscriber, &merchant_authority, &vault, 3);
+    assert_eq!(ix.accounts[0].pubkey, subscription_pda);
+    let accounts = |subscription: &Subscription| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, subscription),
+            TestAccount::new(&ix.accounts[1], system_program::id(), vec![]),
+            TestAccount::token_account(&ix.accounts[2], mint, subscriber),
+            TestAccount::new(&ix.accounts[3], Pubkey::default(), vec![]),
+        ]
+    };
+    let mut cancelled = accounts(&subscription);
+    assert_eq!(process_with(&program_id, &mut cancelled, &ix.data), Ok(()));
+    let stored = SubscriptionProcessor::load_subscription(&program_id, &cancelled[0].info()).unwrap();
+    assert!(stored.cancelled);
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&stored), &ix.data),
+        Err(E9thTokenError::SubscriptionCancelled.into())
+    );
+    let mut impostor = accounts(&subscription);
+    impostor[1].key = merchant_authority;
+    assert_eq!(
+        process_with(&program_id, &mut impostor, &ix.data),
+        Err(E9thTokenError::Unauthorized.into())
+    );
+}
+
+#[test]
+fn test_subscription_handlers_reject_spoofed_accounts() {
+    let terms = sample_subscription_terms();
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let operator = Pubkey::new_unique();
+    let merchant_authority = Pubkey::new_unique();
+    let subscriber = Pubkey::new_unique();
+    let vault = Pubkey::new_unique();
+    let (config_pda, bump) = Processor::get_token_config_pda(&program_id, &mint);
+    let config = TokenConfig::new(operator, operator, operator, mint, 0, bump);
+    let (merchant_pda, merchant_bump) =
+        MerchantProcessor::get_merchant_pda(&program_id, &config_pda, &merchant_authority);
+    let merchant = Merchant::new(config_pda, merchant_authority, Pubkey::new_unique(), 500, VoucherMode::Lock, merchant_bump);
+    let (subscription_pda, subscription_bump) =
+        SubscriptionProcessor::get_subscription_pda(&program_id, &merchant_pda, &subscriber, 3);
+    let subscription = Subscription::new(merchant_pda, subscriber, 3, vault, terms, 1_650_000_000, subscription_bump);
+
+    // CreateSubscription: the merchant, the subscriber's own subscription PDA and vault
+    let ix = instruction::create_subscription(&program_id, &mint, &subscriber, &merchant_authority, &vault, 3, terms);
+    let accounts = || {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &config),
+            TestAccount::with_state(&ix.accounts[1], program_id, &merchant),
+            TestAccount::empty(&ix.accounts[2]),
+            TestAccount::wallet(&ix.accounts[3]),
+            TestAccount::token_account(&ix.accounts[4], mint, subscriber),
+            TestAccount::new(&ix.accounts[5], Pubkey::default(), vec![]),
+            TestAccount::new(&ix.accounts[6], Pubkey::default(), vec![]),
+        ]
+    };
+    assert_eq!(process_with(&program_id, &mut accounts(), &ix.data), Ok(()));
+    assert_spoofs_rejected(
+        &program_id,
+        accounts,
+        &ix.data,
+        &[
+            (Spoof::Owner(0), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(1), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(1), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(2), E9thTokenError::UnexpectedAccount),
+            (Spoof::Unsigned(3), E9thTokenError::Unauthorized),
+            (Spoof::Owner(4), E9thTokenError::InvalidAccountOwner),
+        ],
+    );
+
+    // CollectSubscription: the subscription's merchant and vault, paid to the merchant's authority
+    let ix = instruction::collect_subscription(&program_id, &mint, &merchant_authority, &subscriber, &vault, 3);
+    let accounts_with = |config: &TokenConfig| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, config),
+            TestAccount::with_state(&ix.accounts[1], program_id, &merchant),
+            TestAccount::with_state(&ix.accounts[2], program_id, &subscription),
+            TestAccount::token_account(&ix.accounts[3], mint, subscriber).delegated(subscription_pda, 3_000),
+            TestAccount::token_account(&ix.accounts[4], mint, merchant_authority),
+            TestAccount::new(&ix.accounts[5], Pubkey::default(), vec![]),
+        ]
+    };
+    let accounts = || accounts_with(&config);
+    assert_eq!(process_with(&program_id, &mut accounts(), &ix.data), Ok(()));
+    assert_spoofs_rejected(
+        &program_id,
+        accounts,
+        &ix.data,
+        &[
+            (Spoof::Owner(0), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(1), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(1), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(2), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(2), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(3), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(4), E9thTokenError::InvalidAccountOwner),
+        ],
+    );
+    let paused = TokenConfig { is_paused: true, ..config.clone() };
+    assert_eq!(
+        process_with(&program_id, &mut accounts_with(&paused), &ix.data),
+        Err(E9thTokenError::TokenPaused.into())
+    );
+
+    // CancelSubscription: only the subscriber, on the subscription's own vault
+    let ix = instruction::cancel_subscription(&program_id, &mint, &subscriber, &merchant_authority, &vault, 3);
+    let accounts = || {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &subscription),
+            TestAccount::wallet(&ix.accounts[1]),
+            TestAccount::token_account(&ix.accounts[2], mint, subscriber).delegated(subscription_pda, 3_000),
+            TestAccount::new(&ix.accounts[3], Pubkey::default(), vec![]),
+        ]
+    };
+    assert_eq!(process_with(&program_id, &mut accounts(), &ix.data), Ok(()));
+    assert_spoofs_rejected(
+        &program_id,
+        accounts,
+        &ix.data,
+        &[
+            (Spoof::Address(0), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(0), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Unsigned(1), E9thTokenError::Unauthorized),
+            (Spoof::Address(1), E9thTokenError::Unauthorized),
+            (Spoof::Address(2), E9thTokenError::UnexpectedAccount),
+        ],
+    );
+}
+
+#[test]
+fn test_launch_reservations() {
+    let terms = sample_launch_terms();
+    assert_eq!(Launch::space(Launch::MAX_CAPACITY), Launch::BASE_LEN + 200 * 48);
+    let [a, b, c, d, e] = [(); 5].map(|_| Pubkey::new_unique());
+
+    // Stake-weighted: ranked by stake, ties by reservation order
+    let mut launch = Launch::new(Pubkey::new_unique(), 1, terms, 255);
+    assert_eq!(launch.reserve(a, 100), Ok((0, None)));
+    assert_eq!(launch.reserve(b, 500), Ok((0, None)));
+    assert_eq!(launch.reserve(c, 100), Ok((2, None)));
+    assert_eq!(launch.reserve(a, 1_000), Err(E9thTokenError::AlreadyReserved.into()));
+    // Full: an equal stake doesn't bump the earlier reservation, a larger one does
+    assert_eq!(launch.reserve(d, 100), Err(E9thTokenError::LaunchFull.into()));
+    assert_eq!(launch.reserve(d, 300), Ok((1, Some(c))));
+    let order: Vec<_> = launch.reservations.iter().map(|slot| (slot.wallet, slot.weight, slot.sequence)).collect();
+    assert_eq!(order, vec![(b, 500, 1), (d, 300, 3), (a, 100, 0)]);
+    assert_eq!((launch.position(&a), launch.position(&c)), (Some(2), None));
+    // A bumped wallet can reserve again if it outranks the last slot
+    assert_eq!(launch.reserve(c, 100), Err(E9thTokenError::LaunchFull.into()));
+    assert_eq!(launch.reserve(c, 101), Ok((2, Some(a))));
+
+    // First come: reservation order only, nobody is bumped
+    let mut first_come =
+        Launch::new(Pubkey::new_unique(), 2, LaunchTerms { capacity: 2, priority: LaunchPriority::FirstCome, ..terms }, 255);
+    assert_eq!(first_come.reserve(a, 0), Ok((0, None)));
+    assert_eq!(first_come.reserve(b, 0), Ok((1, None)));
+    assert_eq!(first_come.reserve(e, 0), Err(E9thTokenError::LaunchFull.into()));
+
+    // The stored queue reads back in rank order, sized for the full capacity
+    let mut data = vec![0; launch.account_len()];
+    pack_state(&launch, &mut data).unwrap();
+    let stored: Launch = unpack_state(&data).unwrap();
+    assert_eq!(stored.reservations, launch.reservations);
+    assert_eq!(stored.next_sequence, 5);
+
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let operator = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+    let (config_pda, bump) = Processor::get_token_config_pda(&program_id, &mint);
+    let config = TokenConfig::new(operator, operator, operator, mint, 0, bump);
+    let (launch_pda, launch_bump) = LaunchProcessor::get_launch_pda(&program_id, &config_pda, 7);
+    let launch = Launch::new(config_pda, 7, terms, launch_bump);
+
+    // CreateLaunch: operator only, checked before the launch is created
+    let ix = instruction::create_launch(&program_id, &mint, &operator, 7, terms);
+    assert_eq!(ix.accounts[1].pubkey, launch_pda);
+    let accounts = |ix: &Instruction, existing: Option<&Launch>| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &config),
+            match existing {
+                Some(launch) => TestAccount::with_state(&ix.accounts[1], program_id, launch),
+                None => TestAccount::new(&ix.accounts[1], system_program::id(), vec![]),
+            },
+            TestAccount::new(&ix.accounts[2], system_program::id(), vec![]),
+            TestAccount::new(&ix.accounts[3], Pubkey::default(), vec![]),
+        ]
+    };
+    let mut impostor = accounts(&ix, None);
+    impostor[2].key = wallet;
+    assert_eq!(
+        process_with(&program_id, &mut impostor, &ix.data),
+        Err(E9thTokenError::InvalidAdmin.into())
+    );
+    for capacity in [0, Launch::MAX_CAPACITY + 1] {
+        let ix = instruction::create_launch(&program_id, &mint, &operator, 7, LaunchTerms { capacity, ..terms });
+        assert_eq!(
+            process_with(&program_id, &mut accounts(&ix, None), &ix.data),
+            Err(E9thTokenError::InvalidLaunchTerms.into())
+        );
+    }
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&ix, Some(&launch)), &ix.data),
+        Err(E9thTokenError::AccountAlreadyInitialized.into())
+    );
+
+    // ReserveLaunchSlot: the wallet's tier comes from its stake entry alone (9_500 staked: Bronze)
+    let ix = instruction::reserve_launch_slot(&program_id, &mint, &wallet, 7);
+    let (tier_config_pda, tier_bump) = TierProcessor::get_tier_config_pda(&program_id, &config_pda);
+    let (stake_pda, stake_bump) = Processor::get_stake_entry_pda(&program_id, &config_pda, &wallet);
+    assert_eq!(
+        (ix.accounts[1].pubkey, ix.accounts[2].pubkey, ix.accounts[4].pubkey),
+        (tier_config_pda, launch_pda, stake_pda)
+    );
+    let tier_config = TierConfig::new(config_pda, sample_tier_thresholds(), tier_bump);
+    let stake = StakeEntry::new(wallet, 9_500, 30, 0, 0, stake_bump);
+    let accounts = |launch: &Launch, tiers: bool| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &config),
+            if tiers {
+                TestAccount::with_state(&ix.accounts[1], program_id, &tier_config)
+            } else {
+                TestAccount::new(&ix.accounts[1], system_program::id(), vec![])
+            },
+            TestAccount::with_state(&ix.accounts[2], program_id, launch),
+            TestAccount::new(&ix.accounts[3], system_program::id(), vec![]),
+            TestAccount::with_state(&ix.accounts[4], program_id, &stake),
+        ]
+    };
+    let mut reserved = accounts(&launch, true);
+    assert_eq!(process_with(&program_id, &mut reserved, &ix.data), Ok(()));
+    let stored = LaunchProcessor::load_launch(&program_id, &config_pda, &reserved[2].info()).unwrap();
+    assert_eq!((stored.reservations[0].wallet, stored.reservations[0].weight), (wallet, 9_500));
+    assert_eq!(
+        events(),
+        vec![E9thEvent::LaunchSlotReserved(LaunchSlotReserved {
+            launch: launch_pda,
+            wallet,
+            weight: 9_500,
+            position: 0,
+            bumped: None,
+        })]
+    );
+    // Tokens in the wallet don't count, only the escrowed stake
+    let silver_only = Launch { terms: LaunchTerms { min_tier: UtilityTier::Silver, ..terms }, ..launch.clone() };
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&silver_only, true), &ix.data),
+        Err(E9thTokenError::TierTooLow.into())
+    );
+    let mut unstaked = accounts(&launch, true);
+    unstaked[4] = TestAccount::new(&ix.accounts[4], system_program::id(), vec![]);
+    assert_eq!(
+        process_with(&program_id, &mut unstaked, &ix.data),
+        Err(E9thTokenError::TierTooLow.into())
+    );
+    // Without tier thresholds every wallet is below Bronze
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&launch, false), &ix.data),
+        Err(E9thTokenError::TierTooLow.into())
+    );
+    let mut other_stake = accounts(&launch, true);
+    other_stake[4].key = Pubkey::new_unique();
+    assert_eq!(
+        process_with(&program_id, &mut other_stake, &ix.data),
+        Err(E9thTokenError::UnexpectedAccount.into())
+    );
+    // Only the escrowed stake entry weighs, not the wallet's legacy stake
+    let mut legacy_stake = accounts(&launch, true);
+    legacy_stake[4].key = StakeProcessor::get_stake_account_pda(&program_id, &wallet, &[]).unwrap().0;
+    assert_eq!(
+        process_with(&program_id, &mut legacy_stake, &ix.data),
+        Err(E9thTokenError::UnexpectedAccount.into())
+    );
+    // Launches of another config are rejected
+    let foreign = Launch { config: Pubkey::new_unique(), ..launch.clone() };
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&foreign, true), &ix.data),
+        Err(E9thTokenError::UnexpectedAccount.into())
+    );
+}
+
+#[test]
+fn test_launch_handlers_reject_spoofed_accounts() {
+    let terms = sample_launch_terms();
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let operator = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+    let (config_pda, bump) = Processor::get_token_config_pda(&program_id, &mint);
+    let config = TokenConfig::new(operator, operator, operator, mint, 0, bump);
+    let (_, launch_bump) = LaunchProcessor::get_launch_pda(&program_id, &config_pda, 7);
+    let (_, tier_bump) = TierProcessor::get_tier_config_pda(&program_id, &config_pda);
+    let (_, stake_bump) = Processor::get_stake_entry_pda(&program_id, &config_pda, &wallet);
+
+    // CreateLaunch: the operator signs for the launch PDA
+    let ix = instruction::create_launch(&program_id, &mint, &operator, 7, terms);
+    let accounts = || {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &config),
+            TestAccount::empty(&ix.accounts[1]),
+            TestAccount::wallet(&ix.accounts[2]),
+            TestAccount::new(&ix.accounts[3], Pubkey::default(), vec![]),
+        ]
+    };
+    assert_eq!(process_with(&program_id, &mut accounts(), &ix.data), Ok(()));
+    assert_spoofs_rejected(
+        &program_id,
+        accounts,
+        &ix.data,
+        &[
+            (Spoof::Address(0), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(0), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(1), E9thTokenError::UnexpectedAccount),
+            (Spoof::Unsigned(2), E9thTokenError::Unauthorized),
+            (Spoof::Address(2), E9thTokenError::InvalidAdmin),
+        ],
+    );
+
+    // ReserveLaunchSlot: the config's tiers, the launch and the wallet's own stake entry
+    let ix = instruction::reserve_launch_slot(&program_id, &mint, &wallet, 7);
+    let accounts = || {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &config),
+            TestAccount::with_state(
+                &ix.accounts[1],
+                program_id,
+                &TierConfig::new(config_pda, sample_tier_thresholds(), tier_bump),
+            ),
+            TestAccount::with_state(&ix.accounts[2], program_id, &Launch::new(config_pda, 7, terms, launch_bump)),
+            TestAccount::wallet(&ix.accounts[3]),
+            TestAccount::with_state(&ix.accounts[4], program_id, &StakeEntry::new(wallet, 9_500, 30, 0, 0, stake_bump)),
+        ]
+    };
+    assert_eq!(process_with(&program_id, &mut accounts(), &ix.data), Ok(()));
+    assert_spoofs_rejected(
+        &program_id,
+        accounts,
+        &ix.data,
+        &[
+            (Spoof::Address(0), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(0), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(1), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(1), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(2), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(2), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Unsigned(3), E9thTokenError::Unauthorized),
+            (Spoof::Address(4), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(4), E9thTokenError::InvalidAccountOwner),
+        ],
+    );
+}
+
+/// A token config for `mint` at its PDA
+fn sample_token_config(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    operator: &Pubkey,
+    treasury: &Pubkey,
+) -> TokenConfig {
+    let (_, bump) = Processor::get_token_config_pda(program_id, mint);
+    TokenConfig::new(*owner, *operator, *treasury, *mint, 250, bump)
+}
+
+#[test]
+fn test_enhanced_admin_instructions() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let operator = Pubkey::new_unique();
+    let treasury = Pubkey::new_unique();
+
+    // Initialize creates the config and blacklist for the mint authority
+    let ix = instruction::initialize(&program_id, &mint, &owner, &operator, &treasury, 250);
+    let accounts = |mint_authority: Pubkey| {
+        vec![
+            TestAccount::empty(&ix.accounts[0]),
+            TestAccount::empty(&ix.accounts[1]),
+            TestAccount::wallet(&ix.accounts[2]),
+            TestAccount::mint(&ix.accounts[3], mint_authority, 5_000),
+            TestAccount::empty(&ix.accounts[4]),
+            TestAccount::new(&ix.accounts[5], Pubkey::default(), vec![]),
+        ]
+    };
+    let mut initialized = accounts(owner);
+    assert_eq!(process_with(&program_id, &mut initialized, &ix.data), Ok(()));
+    assert_eq!(invoked().len(), 2);
+    assert_eq!(initialized[0].owner, program_id);
+    let config: TokenConfig = deserialize_account_data(&initialized[0].info()).unwrap();
+    let expected = TokenConfig {
+        total_supply: 5_000,
+        ..sample_token_config(&program_id, &mint, &owner, &operator, &treasury)
+    };
+    assert_eq!(config.try_to_vec().unwrap(), expected.try_to_vec().unwrap());
+    let blacklist: Blacklist = deserialize_account_data(&initialized[1].info()).unwrap();
+    assert!(blacklist.accounts.is_empty());
+    assert_eq!(
+        process_with(&program_id, &mut initialized, &ix.data),
+        Err(E9thTokenError::AccountAlreadyInitialized.into())
+    );
+    assert_eq!(
+        process_with(&program_id, &mut accounts(Pubkey::new_unique()), &ix.data),
+        Err(E9thTokenError::InvalidAdmin.into())
+    );
+    let mut unsigned = accounts(owner);
+    unsigned[2].is_signer = false;
+    assert_eq!(process_with(&program_id, &mut unsigned, &ix.data), Err(E9thTokenError::Unauthorized.into()));
+    let mut fake_mint = accounts(owner);
+    fake_mint[3].owner = Pubkey::new_unique();
+    assert_eq!(
+        process_with(&program_id, &mut fake_mint, &ix.data),
+        Err(E9thTokenError::InvalidAccountOwner.into())
+    );
+    let ix = instruction::initialize(&program_id, &mint, &owner, &operator, &treasury, 10_001);
+    assert_eq!(
+        process_with(&program_id, &mut accounts(owner), &ix.data),
+        Err(E9thTokenError::InvalidAmount.into())
+    );
+
+    // SetPause: only the owner
+    let config_meta = AccountMeta::new(initialized[0].key, false);
+    for (signer, expected) in [(operator, Err(E9thTokenError::InvalidAdmin.into())), (owner, Ok(()))] {
+        let ix = instruction::set_pause(&program_id, &mint, &signer, true);
+        let mut accounts = vec![
+            TestAccount::new(&config_meta, program_id, initialized[0].data.clone()),
+            TestAccount::empty(&ix.accounts[1]),
+        ];
+        assert_eq!(process_with(&program_id, &mut accounts, &ix.data), expected);
+        let config: TokenConfig = deserialize_account_data(&accounts[0].info()).unwrap();
+        assert_eq!(config.is_paused, expected.is_ok());
+        if expected.is_ok() {
+            assert_eq!(events(), vec![E9thEvent::Paused(Paused { paused: true })]);
+        }
+    }
+
+    // ModifyBlacklist: the operator or owner, on the config's own blacklist
+    let blacklisted = Pubkey::new_unique();
+    let ix = instruction::modify_blacklist(&program_id, &mint, &operator, &blacklisted, true);
+    let mut accounts = vec![
+        TestAccount::new(&ix.accounts[0], program_id, initialized[1].data.clone()),
+        TestAccount::empty(&ix.accounts[1]),
+        TestAccount::new(&ix.accounts[2], program_id, initialized[0].data.clone()),
+    ];
+    assert_eq!(process_with(&program_id, &mut accounts, &ix.data), Ok(()));
+    let blacklist: Blacklist = deserialize_account_data(&accounts[0].info()).unwrap();
+    assert_eq!(blacklist.accounts, vec![blacklisted]);
+    assert_eq!(
+        events(),
+        vec![E9thEvent::BlacklistChanged(BlacklistChanged { account: blacklisted, blacklisted: true })]
+    );
+    accounts[1].key = Pubkey::new_unique();
+    assert_eq!(process_with(&program_id, &mut accounts, &ix.data), Err(E9thTokenError::InvalidAdmin.into()));
+    accounts[1].key = operator;
+    accounts[0].key = Pubkey::new_unique();
+    assert_eq!(
+        process_with(&program_id, &mut accounts, &ix.data),
+        Err(E9thTokenError::UnexpectedAccount.into())
+    );
+}
+
+#[test]
+fn test_enhanced_transfer() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let user = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let mut config = sample_token_config(&program_id, &mint, &owner, &owner, &Pubkey::new_unique());
+    config.total_supply = 10_000;
+    let (config_pda, _) = Processor::get_token_config_pda(&program_id, &mint);
+    let (_, blacklist_bump) = Processor::get_blacklist_pda(&program_id, &config_pda);
+
+    let ix = instruction::transfer(&program_id, &mint, &user, &recipient, 1_000);
+    let accounts = |config: &TokenConfig| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, config),
+            TestAccount::with_state(&ix.accounts[1], program_id, &Blacklist::new(blacklist_bump)),
+            TestAccount::token_account_with(&ix.accounts[2], mint, user, 1_000),
+            TestAccount::token_account_with(&ix.accounts[3], mint, recipient, 0),
+            TestAccount::new(&ix.accounts[4], Pubkey::default(), vec![]),
+            TestAccount::empty(&ix.accounts[5]),
+            TestAccount::mint(&ix.accounts[6], config_pda, 10_000),
+        ]
+    };
+
+    // The 250 bps transfer tax is burned from the sender, the rest is delivered
+    let mut transferred = accounts(&config);
+    assert_eq!(process_with(&program_id, &mut transferred, &ix.data), Ok(()));
+    assert_eq!(transferred[2].token_amount(), 0);
+    assert_eq!(transferred[3].token_amount(), 975);
+    let updated: TokenConfig = deserialize_account_data(&transferred[0].info()).unwrap();
+    assert_eq!(updated.total_supply, 9_975);
+    assert_eq!(updated.burn_stats.transfer_tax, 25);
+    assert_eq!(updated.burn_stats.last_burn_timestamp, 1_650_000_000);
+    assert_eq!(
+        events(),
+        vec![
+            E9thEvent::Burned(Burned { mint, authority: user, amount: 25 }),
+            E9thEvent::Transferred(Transferred { from: user, to: recipient, amount: 975, burned: 25 }),
+        ]
+    );
+
+    // Without a transfer tax nothing is burned
+    let mut untaxed = config.clone();
+    untaxed.burn_rate_basis_points = 0;
+    let mut transferred = accounts(&untaxed);
+    assert_eq!(process_with(&program_id, &mut transferred, &ix.data), Ok(()));
+    assert_eq!(transferred[3].token_amount(), 1_000);
+    assert_eq!(
+        events(),
+        vec![E9thEvent::Transferred(Transferred { from: user, to: recipient, amount: 1_000, burned: 0 })]
+    );
+}
+
+#[test]
+fn test_set_pause_rejects_spoofed_accounts() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let operator = Pubkey::new_unique();
+    let config = sample_token_config(&program_id, &mint, &owner, &operator, &Pubkey::new_unique());
+
+    let ix = instruction::set_pause(&program_id, &mint, &owner, true);
+    let accounts = || {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &config),
+            TestAccount::wallet(&ix.accounts[1]),
+        ]
+    };
+    assert_eq!(process_with(&program_id, &mut accounts(), &ix.data), Ok(()));
+    assert_spoofs_rejected(
+        &program_id,
+        accounts,
+        &ix.data,
+        &[
+            (Spoof::Address(0), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(0), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Unsigned(1), E9thTokenError::Unauthorized),
+            (Spoof::Address(1), E9thTokenError::InvalidAdmin),
+        ],
+    );
+    let mut readonly_config = accounts();
+    readonly_config[0].is_writable = false;
+    assert_eq!(
+        process_with(&program_id, &mut readonly_config, &ix.data),
+        Err(E9thTokenError::AccountNotWritable.into())
+    );
+}
+
+#[test]
+fn test_modify_blacklist_rejects_spoofed_accounts() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let operator = Pubkey::new_unique();
+    let config = sample_token_config(&program_id, &mint, &owner, &operator, &Pubkey::new_unique());
+    let (config_pda, _) = Processor::get_token_config_pda(&program_id, &mint);
+    let (_, blacklist_bump) = Processor::get_blacklist_pda(&program_id, &config_pda);
+
+    let ix = instruction::modify_blacklist(&program_id, &mint, &operator, &Pubkey::new_unique(), true);
+    let accounts = || {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &Blacklist::new(blacklist_bump)),
+            TestAccount::wallet(&ix.accounts[1]),
+            TestAccount::with_state(&ix.accounts[2], program_id, &config),
+        ]
+    };
+    assert_eq!(process_with(&program_id, &mut accounts(), &ix.data), Ok(()));
+    assert_spoofs_rejected(
+        &program_id,
+        accounts,
+        &ix.data,
+        &[
+            (Spoof::Address(0), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(0), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Unsigned(1), E9thTokenError::Unauthorized),
+            (Spoof::Address(1), E9thTokenError::InvalidAdmin),
+            (Spoof::Address(2), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(2), E9thTokenError::InvalidAccountOwner),
+        ],
+    );
+}
+
+#[test]
+fn test_transfer_rejects_spoofed_accounts() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let user = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let config = sample_token_config(&program_id, &mint, &owner, &owner, &Pubkey::new_unique());
+    let (config_pda, _) = Processor::get_token_config_pda(&program_id, &mint);
+    let (_, blacklist_bump) = Processor::get_blacklist_pda(&program_id, &config_pda);
+
+    let ix = instruction::transfer(&program_id, &mint, &user, &recipient, 100);
+    let accounts_with = |config: &TokenConfig, blacklisted: &[Pubkey]| {
+        let mut blacklist = Blacklist::new(blacklist_bump);
+        for account in blacklisted {
+            blacklist.add_account(*account);
+        }
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, config),
+            TestAccount::with_state(&ix.accounts[1], program_id, &blacklist),
+            TestAccount::token_account(&ix.accounts[2], mint, user),
+            TestAccount::token_account(&ix.accounts[3], mint, recipient),
+            TestAccount::new(&ix.accounts[4], Pubkey::default(), vec![]),
+            TestAccount::empty(&ix.accounts[5]),
+            TestAccount::mint(&ix.accounts[6], config_pda, 10_000),
+        ]
+    };
+    let accounts = || accounts_with(&config, &[]);
+    assert_eq!(process_with(&program_id, &mut accounts(), &ix.data), Ok(()));
+    assert_spoofs_rejected(
+        &program_id,
+        accounts,
+        &ix.data,
+        &[
+            (Spoof::Address(0), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(0), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(1), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(1), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Owner(2), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Unsigned(5), E9thTokenError::Unauthorized),
+            (Spoof::Address(6), E9thTokenError::InvalidMint),
+        ],
+    );
+    let mut stolen_source = accounts();
+    stolen_source[2] = TestAccount::token_account(&ix.accounts[2], mint, Pubkey::new_unique());
+    assert_eq!(
+        process_with(&program_id, &mut stolen_source, &ix.data),
+        Err(E9thTokenError::InvalidTokenAccount.into())
+    );
+    let mut wrong_destination = accounts();
+    wrong_destination[3] = TestAccount::token_account(&ix.accounts[3], Pubkey::new_unique(), recipient);
+    assert_eq!(
+        process_with(&program_id, &mut wrong_destination, &ix.data),
+        Err(E9thTokenError::InvalidMint.into())
+    );
+    let mut fake_token_program = accounts();
+    fake_token_program[4].key = Pubkey::new_unique();
+    assert_eq!(
+        process_with(&program_id, &mut fake_token_program, &ix.data),
+        Err(ProgramError::IncorrectProgramId)
+    );
+
+    // Paused configs and blacklisted senders or recipients are refused
+    let paused = TokenConfig { is_paused: true, ..config.clone() };
+    assert_eq!(
+        process_with(&program_id, &mut accounts_with(&paused, &[]), &ix.data),
+        Err(E9thTokenError::TokenPaused.into())
+    );
+    for blacklisted in [user, recipient] {
+        assert_eq!(
+            process_with(&program_id, &mut accounts_with(&config, &[blacklisted]), &ix.data),
+            Err(E9thTokenError::AccountBlacklisted.into())
+        );
+    }
+}
+
+#[test]
+fn test_airdrop_rejects_spoofed_accounts() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let treasury = Pubkey::new_unique();
+    let [first, second] = [(); 2].map(|_| Pubkey::new_unique());
+    let config = sample_token_config(&program_id, &mint, &owner, &owner, &treasury);
+
+    // The treasury signs for its own token account, one account per recipient
+    let ix = instruction::airdrop(&program_id, &mint, &treasury, vec![first, second], vec![10, 20]);
+    let accounts = || {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &config),
+            TestAccount::token_account(&ix.accounts[1], mint, treasury),
+            TestAccount::new(&ix.accounts[2], Pubkey::default(), vec![]),
+            TestAccount::empty(&ix.accounts[3]),
+            TestAccount::token_account(&ix.accounts[4], mint, first),
+            TestAccount::token_account(&ix.accounts[5], mint, second),
+        ]
+    };
+    let mut sent = accounts();
+    assert_eq!(process_with(&program_id, &mut sent, &ix.data), Ok(()));
+    assert_eq!((sent[1].token_amount(), sent[4].token_amount(), sent[5].token_amount()), (970, 1_010, 1_020));
+    assert_eq!(
+        events(),
+        vec![
+            E9thEvent::AirdropSent(AirdropSent { round_id: 0, recipient: first, amount: 10 }),
+            E9thEvent::AirdropSent(AirdropSent { round_id: 0, recipient: second, amount: 20 }),
+        ]
+    );
+    assert_spoofs_rejected(
+        &program_id,
+        accounts,
+        &ix.data,
+        &[
+            (Spoof::Address(0), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(0), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Owner(1), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Unsigned(3), E9thTokenError::Unauthorized),
+            (Spoof::Address(3), E9thTokenError::InvalidAdmin),
+        ],
+    );
+    let zero = instruction::airdrop(&program_id, &mint, &treasury, vec![first, second], vec![10, 0]);
+    assert_eq!(process_with(&program_id, &mut accounts(), &zero.data), Err(E9thTokenError::InvalidAmount.into()));
+    let mut swapped = accounts();
+    swapped.swap(4, 5);
+    assert_eq!(
+        process_with(&program_id, &mut swapped, &ix.data),
+        Err(E9thTokenError::InvalidTokenAccount.into())
+    );
+    let mut missing = accounts();
+    missing.pop();
+    assert_eq!(
+        process_with(&program_id, &mut missing, &ix.data),
+        Err(E9thTokenError::InvalidInstruction.into())
+    );
+}
+
+#[test]
+fn test_stake_and_unstake_reject_spoofed_accounts() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let user = Pubkey::new_unique();
+    let config = sample_token_config(&program_id, &mint, &owner, &owner, &Pubkey::new_unique());
+    let (config_pda, _) = Processor::get_token_config_pda(&program_id, &mint);
+
+    // The staker's entry and token account, and the config's vault
+    for ix in [
+        instruction::stake(&program_id, &mint, &user, 100),
+        instruction::unstake(&program_id, &mint, &user, 100),
+    ] {
+        let accounts = || {
+            let mut accounts = vec![
+                TestAccount::with_state(&ix.accounts[0], program_id, &config),
+                TestAccount::empty(&ix.accounts[1]),
+                TestAccount::token_account(&ix.accounts[2], mint, user),
+                TestAccount::token_account(&ix.accounts[3], mint, config_pda),
+                TestAccount::new(&ix.accounts[4], Pubkey::default(), vec![]),
+                TestAccount::empty(&ix.accounts[5]),
+            ];
+            accounts.extend(ix.accounts[6..].iter().map(TestAccount::empty));
+            accounts
+        };
+        assert_spoofs_rejected(
+            &program_id,
+            accounts,
+            &ix.data,
+            &[
+                (Spoof::Address(0), E9thTokenError::UnexpectedAccount),
+                (Spoof::Owner(0), E9thTokenError::InvalidAccountOwner),
+                (Spoof::Address(1), E9thTokenError::UnexpectedAccount),
+                (Spoof::Owner(2), E9thTokenError::InvalidAccountOwner),
+                (Spoof::Owner(3), E9thTokenError::InvalidAccountOwner),
+                (Spoof::Unsigned(5), E9thTokenError::Unauthorized),
+            ],
+        );
+        // Another staker's entry is still the wrong PDA
+        let mut other_entry = accounts();
+        other_entry[1].key = Processor::get_stake_entry_pda(&program_id, &config_pda, &owner).0;
+        assert_eq!(
+            process_with(&program_id, &mut other_entry, &ix.data),
+            Err(E9thTokenError::UnexpectedAccount.into())
+        );
+        let mut foreign_vault = accounts();
+        foreign_vault[3] = TestAccount::token_account(&ix.accounts[3], mint, user);
+        assert_eq!(
+            process_with(&program_id, &mut foreign_vault, &ix.data),
+            Err(E9thTokenError::InvalidTokenAccount.into())
+        );
+        let mut readonly_config = accounts();
+        readonly_config[0].is_writable = false;
+        assert_eq!(
+            process_with(&program_id, &mut readonly_config, &ix.data),
+            Err(E9thTokenError::AccountNotWritable.into())
+        );
+        let mut fake_token_program = accounts();
+        fake_token_program[4].key = Pubkey::new_unique();
+        assert_eq!(
+            process_with(&program_id, &mut fake_token_program, &ix.data),
+            Err(ProgramError::IncorrectProgramId)
+        );
+    }
+}
+
+#[test]
+fn test_claim_rewards_rejects_spoofed_accounts() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let treasury = Pubkey::new_unique();
+    let user = Pubkey::new_unique();
+    let config = sample_token_config(&program_id, &mint, &owner, &owner, &treasury);
+    let (config_pda, _) = Processor::get_token_config_pda(&program_id, &mint);
+    let (_, entry_bump) = Processor::get_stake_entry_pda(&program_id, &config_pda, &user);
+
+    // The staker's own entry, paid from the treasury's account through the
+    // allowance it approved the config for
+    let ix = instruction::claim_rewards(&program_id, &mint, &user, &treasury);
+    let entry = StakeEntry {
+        last_reward_timestamp: 1_650_000_000 - 365 * 86_400,
+        carried_rewards: 500,
+        ..StakeEntry::new(user, 1_000_000, 30, 100, 1_650_000_000, entry_bump)
+    };
+    let accounts_with_allowance = |allowance: u64| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &config),
+            TestAccount::with_state(&ix.accounts[1], program_id, &entry),
+            TestAccount::token_account(&ix.accounts[2], mint, user),
+            TestAccount::token_account_with(&ix.accounts[3], mint, treasury, 100_000).delegated(config_pda, allowance),
+            TestAccount::new(&ix.accounts[4], Pubkey::default(), vec![]),
+            TestAccount::empty(&ix.accounts[5]),
+        ]
+    };
+    let accounts = || accounts_with_allowance(50_000);
+    // A year at 1% plus the carried legacy rewards
+    let mut claimed = accounts();
+    assert_eq!(process_with(&program_id, &mut claimed, &ix.data), Ok(()));
+    assert_eq!((claimed[2].token_amount(), claimed[3].token_amount()), (11_500, 89_500));
+    assert_eq!(events(), vec![E9thEvent::RewardsClaimed(RewardsClaimed { staker: user, amount: 10_500 })]);
+    let claimed_entry: StakeEntry = deserialize_account_data(&claimed[1].info()).unwrap();
+    assert_eq!((claimed_entry.carried_rewards, claimed_entry.last_reward_timestamp), (0, 1_650_000_000));
+    assert_eq!(
+        process_with(&program_id, &mut claimed, &ix.data),
+        Err(E9thTokenError::InvalidAmount.into())
+    );
+    assert_eq!(
+        process_with(&program_id, &mut accounts_with_allowance(10_499), &ix.data),
+        Err(E9thTokenError::InsufficientFunds.into())
+    );
+
+    assert_spoofs_rejected(
+        &program_id,
+        accounts,
+        &ix.data,
+        &[
+            (Spoof::Address(0), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(0), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Address(1), E9thTokenError::UnexpectedAccount),
+            (Spoof::Owner(1), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Owner(3), E9thTokenError::InvalidAccountOwner),
+            (Spoof::Unsigned(5), E9thTokenError::Unauthorized),
+        ],
+    );
+    let mut foreign_treasury = accounts();
+    foreign_treasury[3] = TestAccount::token_account(&ix.accounts[3], mint, user);
+    assert_eq!(
+        process_with(&program_id, &mut foreign_treasury, &ix.data),
+        Err(E9thTokenError::InvalidTokenAccount.into())
+    );
+}
+
+#[test]
+fn test_airdrop_round_size_limit() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+    let ix = instruction::create_airdrop_round(
+        &program_id,
+        &mint,
+        &authority,
+        1,
+        [7u8; 32],
+        10_000,
+        AirdropRound::MAX_RECIPIENTS + 1,
+        1_700_000_000,
+        AirdropGate::default(),
+    );
+    // Rejected before any account is loaded
+    let mut accounts: Vec<TestAccount> = ix.accounts.iter().map(TestAccount::empty).collect();
+    assert_eq!(
+        process_with(&program_id, &mut accounts, &ix.data),
+        Err(E9thTokenError::TooManyRecipients.into())
+    );
+}
+
+#[test]
+fn test_airdrop_clawback() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let operator = Pubkey::new_unique();
+    let treasury = Pubkey::new_unique();
+    let (config_pda, config_bump) = Processor::get_token_config_pda(&program_id, &mint);
+    let config = TokenConfig::new(operator, operator, treasury, mint, 0, config_bump);
+    let ix = instruction::clawback_airdrop(&program_id, &mint, &operator, &treasury, 1);
+    let (round_pda, round_bump) = AirdropProcessor::get_round_pda(&program_id, &config_pda, 1);
+    let round = |expiry_timestamp: u64| {
+        let params = AirdropRoundParams {
+            round_id: 1,
+            merkle_root: [7; 32],
+            total_amount: 300,
+            num_recipients: 2,
+            expiry_timestamp,
+            gate: AirdropGate::default(),
+        };
+        AirdropRound::new(params, ix.accounts[2].pubkey, round_bump)
+    };
+    let accounts = |round: &AirdropRound| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &config),
+            TestAccount::with_state(&ix.accounts[1], program_id, round),
+            TestAccount::token_account_with(&ix.accounts[2], mint, round_pda, 120),
+            TestAccount::token_account_with(&ix.accounts[3], mint, treasury, 0),
+            TestAccount::wallet(&ix.accounts[4]),
+            TestAccount::new(&ix.accounts[5], Pubkey::default(), vec![]),
+        ]
+    };
+
+    // Unclaimed tokens return to the treasury once, after the expiry
+    assert_eq!(
+        process_with(&program_id, &mut accounts(&round(1_650_000_001)), &ix.data),
+        Err(E9thTokenError::AirdropNotExpired.into())
+    );
+    let mut clawed_back = accounts(&round(1_650_000_000));
+    assert_eq!(process_with(&program_id, &mut clawed_back, &ix.data), Ok(()));
+    assert_eq!((clawed_back[2].token_amount(), clawed_back[3].token_amount()), (0, 120));
+    assert_eq!(
+        process_with(&program_id, &mut clawed_back, &ix.data),
+        Err(E9thTokenError::AirdropAlreadyClawedBack.into())
+    );
+}
+
+/// Instructions sysvar data for a transaction of `instructions`, executing the last one
+fn instructions_sysvar(instructions: &[Instruction]) -> Vec<u8> {
+    let borrowed: Vec<BorrowedInstruction> = instructions
+        .iter()
+        .map(|ix| BorrowedInstruction {
+            program_id: &ix.program_id,
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|meta| BorrowedAccountMeta {
+                    pubkey: &meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: &ix.data,
+        })
+        .collect();
+    let mut data = construct_instructions_data(&borrowed);
+    store_current_index(&mut data, (instructions.len() - 1) as u16);
+    data
+}
+
+/// An ed25519 program instruction carrying `signer` and `message` inline. The
+/// runtime verifies the signature itself; the program only checks what was signed.
+fn ed25519_attestation(signer: &Pubkey, message: &[u8]) -> Instruction {
+    let pubkey_offset = 16u16;
+    let signature_offset = pubkey_offset + 32;
+    let message_offset = signature_offset + 64;
+    let mut data = vec![1, 0];
+    for field in [signature_offset, u16::MAX, pubkey_offset, u16::MAX, message_offset, message.len() as u16, u16::MAX] {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+    data.extend_from_slice(signer.as_ref());
+    data.extend_from_slice(&[0; 64]);
+    data.extend_from_slice(message);
+    Instruction { program_id: ed25519_program::id(), accounts: vec![], data }
+}
+
+#[test]
+fn test_airdrop_claim_gates() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let operator = Pubkey::new_unique();
+    let claimant = Pubkey::new_unique();
+    let verifier = Pubkey::new_unique();
+    let (config_pda, config_bump) = Processor::get_token_config_pda(&program_id, &mint);
+    let config = TokenConfig::new(operator, operator, operator, mint, 0, config_bump);
+    let tree = MerkleTree::new(vec![
+        merkle::leaf_hash(0, &claimant, 100),
+        merkle::leaf_hash(1, &Pubkey::new_unique(), 200),
+    ]);
+    let (round_pda, round_bump) = AirdropProcessor::get_round_pda(&program_id, &config_pda, 1);
+    let (_, stake_bump) = Processor::get_stake_entry_pda(&program_id, &config_pda, &claimant);
+    // Staked 500 since epoch 90; the clock is at epoch 100
+    let stake = StakeEntry::new(claimant, 500, 30, 90, 0, stake_bump);
+
+    // A claim of leaf 0, optionally preceded by an attestation in the same transaction
+    let claim = |gate: &AirdropGate, stake: &StakeEntry, attestation: Option<Instruction>| {
+        let ix = instruction::claim_airdrop(&program_id, &mint, &claimant, 1, 0, 100, tree.proof(0), gate);
+        let params = AirdropRoundParams {
+            round_id: 1,
+            merkle_root: tree.root(),
+            total_amount: 300,
+            num_recipients: 2,
+            expiry_timestamp: 1_700_000_000,
+            gate: gate.clone(),
+        };
+        let round = AirdropRound::new(params, ix.accounts[2].pubkey, round_bump);
+        // The claimant's wallet balance never counts towards a gate
+        let mut accounts = vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &config),
+            TestAccount::with_state(&ix.accounts[1], program_id, &round),
+            TestAccount::token_account_with(&ix.accounts[2], mint, round_pda, 300),
+            TestAccount::empty(&ix.accounts[3]),
+            TestAccount::token_account_with(&ix.accounts[4], mint, claimant, 1_000_000),
+            TestAccount::new(&ix.accounts[5], Pubkey::default(), vec![]),
+        ];
+        let mut gate_accounts = ix.accounts[6..].iter();
+        if gate.requires_stake() {
+            accounts.push(TestAccount::with_state(gate_accounts.next().unwrap(), program_id, stake));
+        }
+        if let Some(meta) = gate_accounts.next() {
+            let transaction: Vec<Instruction> = attestation.into_iter().chain([ix.clone()]).collect();
+            accounts.push(TestAccount::new(meta, Pubkey::default(), instructions_sysvar(&transaction)));
+        }
+        (ix, accounts)
+    };
+
+    // Ungated: each leaf pays out once
+    let (ix, mut accounts) = claim(&AirdropGate::default(), &stake, None);
+    assert_eq!(process_with(&program_id, &mut accounts, &ix.data), Ok(()));
+    assert_eq!((accounts[2].token_amount(), accounts[4].token_amount()), (200, 1_000_100));
+    assert_eq!(
+        process_with(&program_id, &mut accounts, &ix.data),
+        Err(E9thTokenError::AirdropAlreadyClaimed.into())
+    );
+
+    // Minimum balance: only the stake entry counts
+    let gate = AirdropGate { min_balance: 500, ..AirdropGate::default() };
+    let (ix, mut accounts) = claim(&gate, &stake, None);
+    assert_eq!(ix.accounts.len(), 7);
+    assert_eq!(process_with(&program_id, &mut accounts, &ix.data), Ok(()));
+    let gate = AirdropGate { min_balance: 501, ..AirdropGate::default() };
+    let (ix, mut accounts) = claim(&gate, &stake, None);
+    assert_eq!(
+        process_with(&program_id, &mut accounts, &ix.data),
+        Err(E9thTokenError::InsufficientHolding.into())
+    );
+    let (ix, mut accounts) = claim(&gate, &StakeEntry { amount: 0, ..stake.clone() }, None);
+    assert_eq!(
+        process_with(&program_id, &mut accounts, &ix.data),
+        Err(E9thTokenError::InvalidStakeAccount.into())
+    );
+    let (ix, mut accounts) = claim(&gate, &stake, None);
+    accounts[6].owner = Pubkey::new_unique();
+    assert_eq!(
+        process_with(&program_id, &mut accounts, &ix.data),
+        Err(E9thTokenError::InvalidAccountOwner.into())
+    );
+    let (ix, mut accounts) = claim(&gate, &stake, None);
+    accounts.pop();
+    assert_eq!(process_with(&program_id, &mut accounts, &ix.data), Err(ProgramError::NotEnoughAccountKeys));
+
+    // Holding period, in epochs since the stake started
+    let gate = AirdropGate { min_holding_epochs: 10, ..AirdropGate::default() };
+    let (ix, mut accounts) = claim(&gate, &stake, None);
+    assert_eq!(process_with(&program_id, &mut accounts, &ix.data), Ok(()));
+    let (ix, mut accounts) = claim(&gate, &StakeEntry { start_epoch: 91, ..stake.clone() }, None);
+    assert_eq!(
+        process_with(&program_id, &mut accounts, &ix.data),
+        Err(E9thTokenError::HoldingPeriodTooShort.into())
+    );
+
+    // Attestation: the verifier signed this round and claimant in the previous instruction
+    let gate = AirdropGate { verifier, ..AirdropGate::default() };
+    let message = AirdropProcessor::attestation_message(&round_pda, &claimant);
+    let (ix, mut accounts) = claim(&gate, &stake, Some(ed25519_attestation(&verifier, &message)));
+    assert_eq!(process_with(&program_id, &mut accounts, &ix.data), Ok(()));
+    let other_round = AirdropProcessor::attestation_message(&Pubkey::new_unique(), &claimant);
+    for attestation in [
+        None,
+        Some(ed25519_attestation(&Pubkey::new_unique(), &message)),
+        Some(ed25519_attestation(&verifier, &other_round)),
+        Some(Instruction { program_id: Pubkey::new_unique(), ..ed25519_attestation(&verifier, &message) }),
+    ] {
+        let (ix, mut accounts) = claim(&gate, &stake, attestation);
+        assert_eq!(
+            process_with(&program_id, &mut accounts, &ix.data),
+            Err(E9thTokenError::InvalidAttestation.into())
+        );
+    }
+    let (ix, mut accounts) = claim(&gate, &stake, Some(ed25519_attestation(&verifier, &message)));
+    accounts[6].key = Pubkey::new_unique();
+    assert_eq!(
+        process_with(&program_id, &mut accounts, &ix.data),
+        Err(E9thTokenError::UnexpectedAccount.into())
+    );
+}
+
+/// Run `CreateSale` for a lamport sale funded from the owner's token account,
+/// returning the sale and vault accounts
+fn create_lamport_sale(program_id: &Pubkey, config: &TokenConfig, terms: &SaleTerms) -> (TestAccount, TestAccount) {
+    let ix = instruction::create_sale(program_id, &config.mint, &config.owner, 1, terms.clone());
+    let (sale_pda, _) = SaleProcessor::get_sale_pda(program_id, &ix.accounts[0].pubkey, 1);
+    let mut accounts = vec![
+        TestAccount::with_state(&ix.accounts[0], *program_id, config),
+        TestAccount::empty(&ix.accounts[1]),
+        TestAccount::token_account_with(&ix.accounts[2], config.mint, sale_pda, 0),
+        TestAccount::empty(&ix.accounts[3]),
+        TestAccount::token_account_with(&ix.accounts[4], config.mint, config.owner, terms.hard_cap),
+        TestAccount::wallet(&ix.accounts[5]),
+        TestAccount::empty(&ix.accounts[6]),
+        TestAccount::new(&ix.accounts[7], Pubkey::default(), vec![]),
+    ];
+    assert_eq!(process_with(program_id, &mut accounts, &ix.data), Ok(()));
+    let vault = accounts.remove(2);
+    (accounts.remove(1), vault)
+}
+
+/// Accounts for a lamport `BuySale` or `RefundSale`, which share their layout
+/// up to the quote accounts. The config, sale, vault, purchase record and buyer
+/// token account carry over from `previous`.
+fn sale_buyer_accounts(ix: &Instruction, previous: &[TestAccount]) -> Vec<TestAccount> {
+    let mut accounts: Vec<TestAccount> = ix.accounts.iter().map(TestAccount::empty).collect();
+    accounts[4] = TestAccount::wallet(&ix.accounts[4]);
+    for index in [0, 1, 2, 3, 5] {
+        accounts[index] = previous[index].reuse(&ix.accounts[index]);
+    }
+    accounts
+}
+
+#[test]
+fn test_sale_purchases_and_withdrawal() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let treasury = Pubkey::new_unique();
+    let buyer = Pubkey::new_unique();
+    let config = sample_token_config(&program_id, &mint, &owner, &owner, &treasury);
+    let tree = MerkleTree::new(vec![
+        merkle::leaf_hash(0, &buyer, 2_000_000),
+        merkle::leaf_hash(1, &Pubkey::new_unique(), 2_000_000),
+    ]);
+    let terms = SaleTerms { whitelist_root: tree.root(), soft_cap: 2_000_000, ..sample_sale_terms() };
+
+    // CreateSale: owner only, with consistent terms; the vault holds the hard cap
+    let ix = instruction::create_sale(&program_id, &mint, &owner, 1, terms.clone());
+    let mut accounts = vec![
+        TestAccount::with_state(&ix.accounts[0], program_id, &config),
+        TestAccount::empty(&ix.accounts[1]),
+        TestAccount::token_account_with(&ix.accounts[2], mint, ix.accounts[1].pubkey, 0),
+        TestAccount::empty(&ix.accounts[3]),
+        TestAccount::token_account_with(&ix.accounts[4], mint, owner, terms.hard_cap),
+        TestAccount::wallet(&AccountMeta::new(treasury, true)),
+        TestAccount::empty(&ix.accounts[6]),
+        TestAccount::new(&ix.accounts[7], Pubkey::default(), vec![]),
+    ];
+    assert_eq!(process_with(&program_id, &mut accounts, &ix.data), Err(E9thTokenError::InvalidAdmin.into()));
+    let inverted = instruction::create_sale(
+        &program_id,
+        &mint,
+        &owner,
+        1,
+        SaleTerms { soft_cap: terms.hard_cap + 1, ..terms.clone() },
+    );
+    accounts[5].key = owner;
+    assert_eq!(process_with(&program_id, &mut accounts, &inverted.data), Err(E9thTokenError::InvalidAmount.into()));
+    let (sale, vault) = create_lamport_sale(&program_id, &config, &terms);
+    assert_eq!(sale.owner, program_id);
+    assert_eq!(vault.token_amount(), terms.hard_cap);
+
+    // Whitelist phase: a proof of the buyer's allocation is required
+    set_clock(1_200, 100);
+    let buy = |amount: u64, proof: Vec<[u8; 32]>| {
+        instruction::buy_sale(&program_id, &mint, &buyer, &Pubkey::default(), 1, amount, 0, 2_000_000, proof)
+    };
+    let ix = buy(1_000_000, tree.proof(0));
+    let mut last = vec![
+        TestAccount::with_state(&ix.accounts[0], program_id, &config),
+        sale,
+        vault,
+        TestAccount::empty(&ix.accounts[3]),
+        TestAccount::empty(&ix.accounts[4]),
+        TestAccount::token_account_with(&ix.accounts[5], mint, buyer, 0),
+    ];
+    let mut accounts = sale_buyer_accounts(&ix, &last);
+    let buyer_lamports = accounts[4].lamports;
+    assert_eq!(process_with(&program_id, &mut accounts, &ix.data), Ok(()));
+    // 5 lamports per 100_000 base units, plus rent for the purchase record
+    let rent = Rent::default().minimum_balance(SalePurchase::LEN);
+    assert_eq!(accounts[4].lamports, buyer_lamports - 50 - rent);
+    assert_eq!(accounts[1].lamports, last[1].lamports + 50);
+    assert_eq!((accounts[2].token_amount(), accounts[5].token_amount()), (terms.hard_cap - 1_000_000, 1_000_000));
+    let record: SalePurchase = deserialize_account_data(&accounts[3].info()).unwrap();
+    assert_eq!((record.buyer, record.amount_bought, record.amount_paid), (buyer, 1_000_000, 50));
+    last = accounts;
+
+    for (ix, expected) in [
+        (buy(1_000_000, vec![]), E9thTokenError::NotWhitelisted),
+        // Allocation of 2_000_000 across the whitelist phase
+        (buy(1_000_001, tree.proof(0)), E9thTokenError::PurchaseAboveMaximum),
+        (buy(terms.min_purchase - 1, tree.proof(0)), E9thTokenError::PurchaseBelowMinimum),
+    ] {
+        let mut accounts = sale_buyer_accounts(&ix, &last);
+        assert_eq!(process_with(&program_id, &mut accounts, &ix.data), Err(expected.into()));
+    }
+
+    // Public phase: no proof, up to the per-wallet maximum; costs round up
+    set_clock(1_600, 100);
+    let ix = buy(1_000_001, vec![]);
+    let mut accounts = sale_buyer_accounts(&ix, &last);
+    assert_eq!(process_with(&program_id, &mut accounts, &ix.data), Ok(()));
+    let record: SalePurchase = deserialize_account_data(&accounts[3].info()).unwrap();
+    assert_eq!((record.amount_bought, record.amount_paid), (2_000_001, 101));
+    last = accounts;
+    let ix = buy(terms.max_purchase - 2_000_000, vec![]);
+    assert_eq!(
+        process_with(&program_id, &mut sale_buyer_accounts(&ix, &last), &ix.data),
+        Err(E9thTokenError::PurchaseAboveMaximum.into())
+    );
+    // Another wallet's purchase record
+    let mut accounts = sale_buyer_accounts(&ix, &last);
+    accounts[3].key = SaleProcessor::get_purchase_pda(&program_id, &last[1].key, &owner).0;
+    assert_eq!(
+        process_with(&program_id, &mut accounts, &ix.data),
+        Err(E9thTokenError::UnexpectedAccount.into())
+    );
+    for now in [999, 2_000] {
+        set_clock(now, 100);
+        assert_eq!(
+            process_with(&program_id, &mut sale_buyer_accounts(&ix, &last), &ix.data),
+            Err(E9thTokenError::SaleNotActive.into())
+        );
+    }
+
+    // WithdrawSaleProceeds: owner only, once the sale has ended
+    let ix = instruction::withdraw_sale_proceeds(&program_id, &mint, &owner, &treasury, &Pubkey::default(), 1);
+    let withdraw_accounts = |treasury_wallet: &AccountMeta| {
+        vec![
+            last[0].reuse(&ix.accounts[0]),
+            last[1].reuse(&ix.accounts[1]),
+            last[2].reuse(&ix.accounts[2]),
+            TestAccount::token_account_with(&ix.accounts[3], mint, treasury, 0),
+            TestAccount::empty(&ix.accounts[4]),
+            TestAccount::wallet(treasury_wallet),
+            TestAccount::empty(&ix.accounts[6]),
+            TestAccount::new(&ix.accounts[7], Pubkey::default(), vec![]),
+        ]
+    };
+    set_clock(1_999, 100);
+    assert_eq!(
+        process_with(&program_id, &mut withdraw_accounts(&ix.accounts[5]), &ix.data),
+        Err(E9thTokenError::SaleNotEnded.into())
+    );
+    set_clock(2_000, 100);
+    let mut diverted = withdraw_accounts(&AccountMeta::new(Pubkey::new_unique(), false));
+    assert_eq!(
+        process_with(&program_id, &mut diverted, &ix.data),
+        Err(E9thTokenError::UnexpectedAccount.into())
+    );
+    let mut impostor = withdraw_accounts(&ix.accounts[5]);
+    impostor[6].key = buyer;
+    assert_eq!(process_with(&program_id, &mut impostor, &ix.data), Err(E9thTokenError::InvalidAdmin.into()));
+    // The soft cap was met: the treasury gets the proceeds above rent and the unsold tokens
+    let mut accounts = withdraw_accounts(&ix.accounts[5]);
+    let treasury_lamports = accounts[5].lamports;
+    assert_eq!(process_with(&program_id, &mut accounts, &ix.data), Ok(()));
+    assert_eq!(accounts[1].lamports, Rent::default().minimum_balance(Sale::LEN));
+    assert_eq!(accounts[5].lamports, treasury_lamports + 101);
+    assert_eq!((accounts[2].token_amount(), accounts[3].token_amount()), (0, terms.hard_cap - 2_000_001));
+}
+
+#[test]
+fn test_sale_refunds() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let treasury = Pubkey::new_unique();
+    let buyer = Pubkey::new_unique();
+    let config = sample_token_config(&program_id, &mint, &owner, &owner, &treasury);
+    let terms = sample_sale_terms();
+    let (sale, vault) = create_lamport_sale(&program_id, &config, &terms);
+
+    // A single public purchase leaves the sale well below its soft cap
+    set_clock(1_600, 100);
+    let ix = instruction::buy_sale(&program_id, &mint, &buyer, &Pubkey::default(), 1, 1_000_000, 0, 0, vec![]);
+    let previous = vec![
+        TestAccount::with_state(&ix.accounts[0], program_id, &config),
+        sale,
+        vault,
+        TestAccount::empty(&ix.accounts[3]),
+        TestAccount::empty(&ix.accounts[4]),
+        TestAccount::token_account_with(&ix.accounts[5], mint, buyer, 0),
+    ];
+    let mut last = sale_buyer_accounts(&ix, &previous);
+    assert_eq!(process_with(&program_id, &mut last, &ix.data), Ok(()));
+    let sale_lamports = last[1].lamports;
+
+    // RefundSale: only once the sale has ended below its soft cap
+    let ix = instruction::refund_sale(&program_id, &mint, &buyer, &Pubkey::default(), 1);
+    set_clock(1_999, 100);
+    assert_eq!(
+        process_with(&program_id, &mut sale_buyer_accounts(&ix, &last), &ix.data),
+        Err(E9thTokenError::RefundNotAvailable.into())
+    );
+    set_clock(2_000, 100);
+    // Another wallet's purchase record
+    let mut accounts = sale_buyer_accounts(&ix, &last);
+    accounts[3].key = SaleProcessor::get_purchase_pda(&program_id, &last[1].key, &owner).0;
+    assert_eq!(
+        process_with(&program_id, &mut accounts, &ix.data),
+        Err(E9thTokenError::UnexpectedAccount.into())
+    );
+    // The tokens go back to the vault and the payment back to the buyer
+    let mut accounts = sale_buyer_accounts(&ix, &last);
+    let buyer_lamports = accounts[4].lamports;
+    assert_eq!(process_with(&program_id, &mut accounts, &ix.data), Ok(()));
+    assert_eq!((accounts[2].token_amount(), accounts[5].token_amount()), (terms.hard_cap, 0));
+    assert_eq!(accounts[4].lamports, buyer_lamports + 50);
+    assert_eq!(accounts[1].lamports, sale_lamports - 50);
+    let record: SalePurchase = deserialize_account_data(&accounts[3].info()).unwrap();
+    assert_eq!((record.amount_bought, record.amount_paid), (0, 0));
+    let state: Sale = deserialize_account_data(&accounts[1].info()).unwrap();
+    assert_eq!((state.total_sold, state.total_refunded), (1_000_000, 50));
+    last = accounts;
+    // A purchase is refunded once
+    assert_eq!(
+        process_with(&program_id, &mut sale_buyer_accounts(&ix, &last), &ix.data),
+        Err(E9thTokenError::InvalidAmount.into())
+    );
+
+    // WithdrawSaleProceeds after a failed sale: the unsold tokens only, the
+    // lamports stay behind for the remaining refunds
+    let ix = instruction::withdraw_sale_proceeds(&program_id, &mint, &owner, &treasury, &Pubkey::default(), 1);
+    let mut accounts = vec![
+        last[0].reuse(&ix.accounts[0]),
+        last[1].reuse(&ix.accounts[1]),
+        last[2].reuse(&ix.accounts[2]),
+        TestAccount::token_account_with(&ix.accounts[3], mint, treasury, 0),
+        TestAccount::empty(&ix.accounts[4]),
+        TestAccount::wallet(&ix.accounts[5]),
+        TestAccount::empty(&ix.accounts[6]),
+        TestAccount::new(&ix.accounts[7], Pubkey::default(), vec![]),
+    ];
+    let treasury_lamports = accounts[5].lamports;
+    let sale_lamports = accounts[1].lamports;
+    assert_eq!(process_with(&program_id, &mut accounts, &ix.data), Ok(()));
+    assert_eq!((accounts[1].lamports, accounts[5].lamports), (sale_lamports, treasury_lamports));
+    assert_eq!((accounts[2].token_amount(), accounts[3].token_amount()), (0, terms.hard_cap));
+}
+
+#[test]
+fn test_migrate_account_checks_type_seeds() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let config = sample_token_config(&program_id, &mint, &owner, &owner, &Pubkey::new_unique());
+    let config_key = Processor::get_token_config_pda(&program_id, &mint).0;
+    // Original (version 0) layouts have no header; pad them to the current size
+    let headerless = |mut data: Vec<u8>, len: usize| {
+        data.resize(len, 0);
+        data
+    };
+    let migrate = |account: TestAccount, account_type: AccountType| {
+        let ix = instruction::migrate_account(&program_id, &mint, &owner, &account.key, account_type, 0);
+        let mut accounts = vec![
+            account.reuse(&ix.accounts[0]),
+            TestAccount::with_state(&ix.accounts[1], program_id, &config),
+            TestAccount::wallet(&ix.accounts[2]),
+            TestAccount::new(&ix.accounts[3], Pubkey::default(), vec![]),
+        ];
+        process_with(&program_id, &mut accounts, &ix.data).map(|()| accounts.remove(0))
+    };
+
+    // A blacklist only migrates at its own PDA under the config
+    let (blacklist_key, blacklist_bump) = Processor::get_blacklist_pda(&program_id, &config_key);
+    let blacklist = Blacklist { accounts: vec![Pubkey::new_unique()], bump: blacklist_bump };
+    let data = headerless(blacklist.try_to_vec().unwrap(), Blacklist::LEN);
+    let stray = TestAccount::new(&AccountMeta::new(Pubkey::new_unique(), false), program_id, data.clone());
+    assert_eq!(
+        migrate(stray, AccountType::Blacklist).err(),
+        Some(E9thTokenError::UnexpectedAccount.into())
+    );
+    let blacklist_account = TestAccount::new(&AccountMeta::new(blacklist_key, false), program_id, data);
+    let mut migrated = migrate(blacklist_account, AccountType::Blacklist).unwrap();
+    let loaded: Blacklist = deserialize_account_data(&migrated.info()).unwrap();
+    assert_eq!(loaded.accounts, blacklist.accounts);
+
+    // Headerless data of another type doesn't decode into a PDA of the claimed type
+    let entry = StakeEntry::new(owner, 500, 30, 90, 0, 255);
+    let entry_meta = AccountMeta::new(Processor::get_stake_entry_pda(&program_id, &config_key, &owner).0, false);
+    let data = headerless(entry.try_to_vec().unwrap(), StakeEntry::LEN);
+    let entry_account = TestAccount::new(&entry_meta, program_id, data);
+    assert_eq!(
+        migrate(entry_account.reuse(&entry_meta), AccountType::Blacklist).err(),
+        Some(E9thTokenError::UnexpectedAccount.into())
+    );
+    let mut migrated = migrate(entry_account, AccountType::StakeEntry).unwrap();
+    let loaded: StakeEntry = deserialize_account_data(&migrated.info()).unwrap();
+    assert_eq!((loaded.staker, loaded.amount, loaded.carried_rewards), (owner, 500, 0));
+
+    // Types created after headers existed have no older layout to migrate from
+    let sale = Sale::new(7, sample_sale_terms(), Pubkey::new_unique(), Pubkey::default(), 255);
+    let sale_key = SaleProcessor::get_sale_pda(&program_id, &config_key, 7).0;
+    let data = headerless(sale.try_to_vec().unwrap(), Sale::LEN);
+    let sale_account = TestAccount::new(&AccountMeta::new(sale_key, false), program_id, data);
+    assert_eq!(
+        migrate(sale_account, AccountType::Sale).err(),
+        Some(E9thTokenError::UnsupportedAccountVersion.into())
+    );
+}
+
+#[test]
+fn test_migrate_stake() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let staker = Pubkey::new_unique();
+    let (_, state_bump) = StakeProcessor::get_program_state_pda(&program_id).unwrap();
+    let state = ProgramState { total_staked: 3_000, ..ProgramState::new(admin, mint, state_bump) };
+    let config = sample_token_config(&program_id, &mint, &admin, &admin, &Pubkey::new_unique());
+    let config_key = Processor::get_token_config_pda(&program_id, &mint).0;
+    // Matures at epoch 120
+    let stake = StakeAccount::new(staker, 1_000, 30, 90, 0);
+
+    let ix = instruction::migrate_stake(&program_id, &mint, &staker);
+    let accounts = |wallet_balance: u64, vault_authority: Pubkey| {
+        vec![
+            TestAccount::with_state(&ix.accounts[0], program_id, &state),
+            TestAccount::with_state(&ix.accounts[1], program_id, &config),
+            TestAccount::with_state(&ix.accounts[2], program_id, &stake),
+            TestAccount::empty(&ix.accounts[3]),
+            TestAccount::wallet(&ix.accounts[4]),
+            TestAccount::new(&ix.accounts[5], Pubkey::default(), vec![]),
+            TestAccount::token_account_with(&ix.accounts[6], mint, staker, wallet_balance),
+            TestAccount::token_account_with(&ix.accounts[7], mint, vault_authority, 0),
+            TestAccount::new(&ix.accounts[8], Pubkey::default(), vec![]),
+        ]
+    };
+
+    // Maturity follows the clock sysvar's epoch
+    set_clock(1_650_000_000, 119);
+    assert_eq!(
+        process_with(&program_id, &mut accounts(1_000, config_key), &ix.data),
+        Err(E9thTokenError::StakeNotMature.into())
+    );
+    set_clock(1_650_000_000, 120);
+    // The vault must be the config's
+    assert_eq!(
+        process_with(&program_id, &mut accounts(1_000, staker), &ix.data),
+        Err(E9thTokenError::InvalidTokenAccount.into())
+    );
+    // Legacy stakes were never escrowed: the wallet must still hold the amount
+    assert_eq!(
+        process_with(&program_id, &mut accounts(999, config_key), &ix.data),
+        Err(spl_token::error::TokenError::InsufficientFunds.into())
+    );
+
+    let mut migrated = accounts(1_000, config_key);
+    assert_eq!(process_with(&program_id, &mut migrated, &ix.data), Ok(()));
+    assert_eq!((migrated[6].token_amount(), migrated[7].token_amount()), (0, 1_000));
+    let entry: StakeEntry = deserialize_account_data(&migrated[3].info()).unwrap();
+    // The holding period still counts from the legacy stake
+    assert_eq!((entry.staker, entry.amount, entry.start_epoch), (staker, 1_000, 90));
+    let state: ProgramState = deserialize_account_data(&migrated[0].info()).unwrap();
+    assert_eq!(state.total_staked, 2_000);
+    assert_eq!(load_token_config(&program_id, &migrated[1].info()).unwrap().total_staked, 1_000);
+    // The legacy stake is closed
+    assert_eq!(migrated[2].lamports, 0);
+    assert!(migrated[2].data.iter().all(|byte| *byte == 0));
+
+    // With the membership accounts, the escrowed stake issues the staker's pass
+    let (_, tier_bump) = TierProcessor::get_tier_config_pda(&program_id, &config_key);
+    let tier_config = TierConfig::new(config_key, sample_tier_thresholds(), tier_bump);
+    let mut with_pass = accounts(1_000, config_key);
+    with_pass.push(TestAccount::with_state(&ix.accounts[9], program_id, &tier_config));
+    with_pass.push(TestAccount::empty(&ix.accounts[10]));
+    assert_eq!(process_with(&program_id, &mut with_pass, &ix.data), Ok(()));
+    let pass: MembershipPass = deserialize_account_data(&with_pass[10].info()).unwrap();
+    assert_eq!((pass.holder, pass.tier, pass.staked), (staker, UtilityTier::Bronze, 1_000));
+    set_clock(1_650_000_000, 100);
+}
+
+#[test]
+fn test_migrated_burns() {
+    let program_id = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let treasury = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let (_, state_bump) = StakeProcessor::get_program_state_pda(&program_id).unwrap();
+    let state = ProgramState { total_supply: 1_000_000, ..ProgramState::new(admin, mint, state_bump) };
+
+    // Migrating the program state marks it
+    let ix = instruction::migrate_program_state(&program_id, &mint, &admin, &admin, &treasury, 250);
+    let mut migrated = vec![
+        TestAccount::with_state(&ix.accounts[0], program_id, &state),
+        TestAccount::empty(&ix.accounts[1]),
+        TestAccount::wallet(&ix.accounts[2]),
+        TestAccount::new(&ix.accounts[3], Pubkey::default(), vec![]),
+    ];
+    assert_eq!(process_with(&program_id, &mut migrated, &ix.data), Ok(()));
+    let state: ProgramState = deserialize_account_data(&migrated[0].info()).unwrap();
+    assert!(state.migrated);
+
+    // From then on a legacy burn must bring the config to record into
+    let ix = instruction::legacy::burn(&program_id, &admin, &mint, 10, true);
+    let accounts = || {
+        vec![
+            migrated[0].reuse(&ix.accounts[0]),
+            TestAccount::wallet(&ix.accounts[1]),
+            TestAccount::mint(&ix.accounts[2], admin, 1_000_000),
+            TestAccount::token_account(&ix.accounts[3], mint, admin),
+            TestAccount::new(&ix.accounts[4], Pubkey::default(), vec![]),
+            migrated[1].reuse(&ix.accounts[5]),
+        ]
+    };
+    let mut without_config = accounts();
+    without_config.pop();
+    assert_eq!(
+        process_with(&program_id, &mut without_config, &ix.data),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+    let mut burned = accounts();
+    assert_eq!(process_with(&program_id, &mut burned, &ix.data), Ok(()));
+    assert_eq!(burned[3].token_amount(), 990);
+    let config: TokenConfig = deserialize_account_data(&burned[5].info()).unwrap();
+    assert_eq!((config.total_supply, config.burn_stats.manual), (999_990, 10));
+}